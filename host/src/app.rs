@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use ratatui::crossterm::event::KeyCode;
+
+use esp_csi_protocol::event::{is_valid_label, Label, MAX_LABEL_LEN};
+use esp_csi_protocol::{Command, CsiRecord, Decoded, Decoder, Event, MacAddr};
+
+use crate::capture::{self, Writer};
+use crate::serial::Line;
+
+// Number of amplitude columns kept for the heatmap.
+const HISTORY: usize = 512;
+// Number of CLI/log lines kept for the console pane.
+const CONSOLE_LINES: usize = 200;
+// Window over which the frame rate is computed.
+const RATE_WINDOW: Duration = Duration::from_secs(2);
+
+pub const LABEL_PREFIX: &str = "# label:";
+
+#[derive(Debug, Default, Clone)]
+pub struct SourceStats {
+    pub frames: u64,
+    pub last_rssi: i8,
+    pub min_rssi: i8,
+    pub max_rssi: i8,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    /// The user is typing a label; the buffer holds the text so far.
+    Label(String),
+}
+
+pub struct App {
+    decoder: Decoder,
+    /// Capture being recorded and the instant receive times are measured from.
+    save: Option<(Writer, Instant)>,
+    pub heatmap: VecDeque<Vec<f32>>,
+    pub sources: BTreeMap<MacAddr, SourceStats>,
+    pub console: VecDeque<String>,
+    arrivals: VecDeque<Instant>,
+    pub frames: u64,
+    pub malformed: u64,
+    /// Records missing from the sequence numbering.
+    pub lost: u64,
+    last_seq: Option<u32>,
+    pub label: Option<String>,
+    pub mode: Mode,
+    pub live: bool,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(live: bool, save: Option<(Writer, Instant)>) -> Self {
+        Self {
+            decoder: Decoder::new(),
+            save,
+            heatmap: VecDeque::with_capacity(HISTORY),
+            sources: BTreeMap::new(),
+            console: VecDeque::with_capacity(CONSOLE_LINES),
+            arrivals: VecDeque::new(),
+            frames: 0,
+            malformed: 0,
+            lost: 0,
+            last_seq: None,
+            label: None,
+            mode: Mode::Normal,
+            live,
+            quit: false,
+        }
+    }
+
+    pub fn handle_line(&mut self, Line { at, text: line }: Line) {
+        if let Some((writer, epoch)) = self.save.as_mut() {
+            let _ = writer.line(0, capture::host_us(*epoch, at), &line);
+        }
+
+        match self.decoder.push_line(&line) {
+            Decoded::Record(record) => self.handle_record(record),
+            Decoded::Event(event) => {
+                // Sequence numbers restart with every collection run.
+                match event {
+                    Event::Started { .. } => self.last_seq = None,
+                    // Labels set on the device replace the one shown, an empty one clears it.
+                    Event::Label { label, .. } => {
+                        self.label = (!label.is_empty()).then(|| label.to_string())
+                    }
+                    _ => {}
+                }
+                self.log(line);
+            }
+            Decoded::Pending => {}
+            Decoded::Malformed => self.malformed += 1,
+            Decoded::Other if line.trim().is_empty() => {}
+            Decoded::Other => {
+                if let Some(label) = line.strip_prefix(LABEL_PREFIX) {
+                    self.label = Some(label.trim().to_string());
+                }
+                self.log(line);
+            }
+        }
+    }
+
+    fn handle_record(&mut self, record: CsiRecord) {
+        self.frames += 1;
+        self.arrivals.push_back(Instant::now());
+
+        let stats = self
+            .sources
+            .entry(record.mac)
+            .or_insert_with(|| SourceStats {
+                min_rssi: record.rssi,
+                max_rssi: record.rssi,
+                ..SourceStats::default()
+            });
+        stats.frames += 1;
+        stats.last_rssi = record.rssi;
+        stats.min_rssi = stats.min_rssi.min(record.rssi);
+        stats.max_rssi = stats.max_rssi.max(record.rssi);
+        if let Some(prev) = self.last_seq {
+            self.lost += record.seq.wrapping_sub(prev).saturating_sub(1) as u64;
+        }
+        self.last_seq = Some(record.seq);
+
+        if self.heatmap.len() == HISTORY {
+            self.heatmap.pop_front();
+        }
+        self.heatmap.push_back(record.amplitudes().collect());
+    }
+
+    /// Frames per second over the last [`RATE_WINDOW`].
+    pub fn frame_rate(&mut self) -> f64 {
+        let now = Instant::now();
+        while self
+            .arrivals
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > RATE_WINDOW)
+        {
+            self.arrivals.pop_front();
+        }
+        self.arrivals.len() as f64 / RATE_WINDOW.as_secs_f64()
+    }
+
+    /// Applies a key press, returning the command to send to the device, if any.
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<Command> {
+        match &mut self.mode {
+            Mode::Label(text) => match code {
+                KeyCode::Enter => {
+                    let label = std::mem::take(text);
+                    self.mode = Mode::Normal;
+                    if label.is_empty() {
+                        return None;
+                    }
+                    let mark = self.mark(&label);
+                    self.insert_label(label);
+                    mark
+                }
+                KeyCode::Esc => {
+                    self.mode = Mode::Normal;
+                    None
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                    None
+                }
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    None
+                }
+                _ => None,
+            },
+            Mode::Normal => match code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    self.quit = true;
+                    None
+                }
+                KeyCode::Char('l') => {
+                    self.mode = Mode::Label(String::new());
+                    None
+                }
+                KeyCode::Char('s') if self.live => Some(Command::Start {
+                    duration_s: None,
+                    count: None,
+                }),
+                KeyCode::Char('x') if self.live => Some(Command::Stop),
+                _ => None,
+            },
+        }
+    }
+
+    /// `mark` command putting `label` into the device stream as well, so it
+    /// also reaches the flash log. Labels the device cannot take stay on the
+    /// host.
+    fn mark(&mut self, label: &str) -> Option<Command> {
+        if !self.live {
+            return None;
+        }
+        match is_valid_label(label) {
+            true => Label::try_from(label)
+                .ok()
+                .map(|label| Command::Mark { label }),
+            false => {
+                self.log(format!(
+                    "# label kept on the host, the device takes up to {MAX_LABEL_LEN} characters \
+                     without spaces, commas, = or quotes"
+                ));
+                None
+            }
+        }
+    }
+
+    /// Records a label in the saved capture so a later replay can segment it.
+    pub fn insert_label(&mut self, label: String) {
+        if let Some((writer, epoch)) = self.save.as_mut() {
+            let _ = writer.label(capture::host_us(*epoch, Instant::now()), &label);
+        }
+        self.log(format!("{LABEL_PREFIX} {label}"));
+        self.label = Some(label);
+    }
+
+    /// Completes the capture being recorded, if any.
+    pub fn finish(self) -> Result<()> {
+        if let Some((writer, _)) = self.save {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+
+    pub fn log(&mut self, line: String) {
+        if self.console.len() == CONSOLE_LINES {
+            self.console.pop_front();
+        }
+        self.console.push_back(line);
+    }
+}
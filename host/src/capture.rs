@@ -0,0 +1,53 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use esp_csi_protocol::capture::{CaptureWriter, Header, Source};
+use esp_csi_protocol::Command;
+
+use crate::experiment;
+use crate::serial::{Device, Line};
+
+pub type Writer = CaptureWriter<BufWriter<File>>;
+
+/// Name of the capture written into an output directory.
+pub const CAPTURE_FILE: &str = "capture.csi";
+/// Source id used for captures of a single device.
+pub const SINGLE_SOURCE: &str = "device";
+
+/// Describes the device as a capture source from its `show-config` dump.
+pub fn describe(id: &str, device: &mut Device, lines: &Receiver<Line>) -> Result<Source> {
+    let dump = experiment::execute(device, lines, &Command::ShowConfig.to_string())?;
+    Ok(source(id, &dump))
+}
+
+pub fn source(id: &str, dump: &[String]) -> Source {
+    let mut source = Source::new(id);
+    source.add_dump(dump.iter().map(String::as_str));
+    source
+}
+
+/// Creates a capture at `path`. Receive times are measured from the
+/// returned instant.
+pub fn create(path: &Path, sources: Vec<Source>) -> Result<(Writer, Instant)> {
+    let started_unix_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    let header = Header {
+        started_unix_ms,
+        sources,
+    };
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let writer = CaptureWriter::new(BufWriter::new(file), &header)?;
+    Ok((writer, Instant::now()))
+}
+
+/// Receive time of `at` in microseconds since `epoch`.
+pub fn host_us(epoch: Instant, at: Instant) -> u64 {
+    at.saturating_duration_since(epoch).as_micros() as u64
+}
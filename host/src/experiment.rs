@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use esp_csi_protocol::command::{
+    CsiFlag, NetArch, SetCsi, SetTraffic, SetWifi, TrafficKind, WifiMode,
+};
+use esp_csi_protocol::config::{key, parse_dump_line, MAX_LINE_LEN};
+use esp_csi_protocol::{Command, Decoded, Decoder, Event, OutputFormat};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+
+use crate::capture::{self, CAPTURE_FILE};
+use crate::serial::{Device, Line};
+
+// The device is considered done with a command once it has been quiet this long.
+const SETTLE: Duration = Duration::from_millis(300);
+// Extra time allowed past the experiment duration before `stop` is sent.
+const GRACE: Duration = Duration::from_secs(30);
+// Time the device is given to report stopping once stop was sent.
+pub(crate) const DRAIN: Duration = Duration::from_secs(2);
+// Starts of the lines the firmware answers a refused command with.
+const REFUSALS: &[&str] = &["Invalid", "Rejected:", "Cannot Change"];
+
+/// An experiment described in a TOML file: the device settings plus how the
+/// run is recorded.
+#[derive(Debug, Deserialize)]
+pub struct Experiment {
+    pub name: String,
+    /// Collection duration in seconds, or until interrupted if left out.
+    pub duration_s: Option<u64>,
+    /// Number of CSI records after which collection stops, combined with
+    /// `duration_s` as a timeout if both are given.
+    pub count: Option<u32>,
+    /// Added to the start of the capture as labels.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(flatten)]
+    pub settings: Settings,
+}
+
+/// Device settings. Every setting is optional; settings left out keep the
+/// device defaults restored by `reset-config`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    #[serde(default)]
+    pub wifi: Wifi,
+    #[serde(default)]
+    pub network: Network,
+    #[serde(default)]
+    pub traffic: Traffic,
+    #[serde(default)]
+    pub csi: Csi,
+    #[serde(default)]
+    pub output: Output,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wifi {
+    #[serde(default, deserialize_with = "keyword")]
+    pub mode: Option<WifiMode>,
+    pub sta_ssid: Option<String>,
+    pub sta_password: Option<String>,
+    pub ap_ssid: Option<String>,
+    pub ap_password: Option<String>,
+    #[serde(default)]
+    pub hide_ssid: bool,
+    pub max_connections: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Network {
+    #[serde(default, deserialize_with = "keyword")]
+    pub arch: Option<NetArch>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Traffic {
+    /// Traffic can only be switched on from the CLI; `false` is checked
+    /// against the device default.
+    pub enable: Option<bool>,
+    #[serde(rename = "type", default, deserialize_with = "keyword")]
+    pub kind: Option<TrafficKind>,
+    pub interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Csi {
+    /// `set-csi` flags, e.g. `disable-lltf`.
+    #[serde(default, deserialize_with = "keywords")]
+    pub disable: Vec<CsiFlag>,
+    pub he_stbc: Option<u32>,
+    pub val_scale_cfg: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Output {
+    #[serde(default, deserialize_with = "keyword")]
+    pub format: Option<OutputFormat>,
+}
+
+fn keyword<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse()
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("unknown keyword `{s}`")))
+}
+
+fn keywords<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|s| {
+            s.parse()
+                .map_err(|_| serde::de::Error::custom(format!("unknown keyword `{s}`")))
+        })
+        .collect()
+}
+
+/// Reads a TOML file describing an experiment or a multi-device setup.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("invalid {}", path.display()))
+}
+
+impl Settings {
+    /// `set-*` commands applying the experiment on top of the device defaults.
+    /// Every setting goes in its own command to stay within the CLI line length.
+    pub fn commands(&self) -> Result<Vec<Command>> {
+        let mut commands = Vec::new();
+
+        let w = &self.wifi;
+        if let Some(mode) = w.mode {
+            commands.push(Command::SetWifi(SetWifi {
+                mode: Some(mode),
+                ..Default::default()
+            }));
+        }
+        if let Some(max_connections) = w.max_connections {
+            commands.push(Command::SetWifi(SetWifi {
+                max_connections: Some(max_connections),
+                ..Default::default()
+            }));
+        }
+        if w.hide_ssid {
+            commands.push(Command::SetWifi(SetWifi {
+                hide_ssid: true,
+                ..Default::default()
+            }));
+        }
+        if let Some(s) = &w.ap_ssid {
+            commands.push(Command::SetWifi(SetWifi {
+                ap_ssid: Some(text(s, "wifi.ap_ssid")?),
+                ..Default::default()
+            }));
+        }
+        if let Some(s) = &w.ap_password {
+            commands.push(Command::SetWifi(SetWifi {
+                ap_password: Some(text(s, "wifi.ap_password")?),
+                ..Default::default()
+            }));
+        }
+        if let Some(s) = &w.sta_ssid {
+            commands.push(Command::SetWifi(SetWifi {
+                sta_ssid: Some(text(s, "wifi.sta_ssid")?),
+                ..Default::default()
+            }));
+        }
+        if let Some(s) = &w.sta_password {
+            commands.push(Command::SetWifi(SetWifi {
+                sta_password: Some(text(s, "wifi.sta_password")?),
+                ..Default::default()
+            }));
+        }
+
+        if let Some(arch) = self.network.arch {
+            commands.push(Command::SetNetwork { arch: Some(arch) });
+        }
+
+        let t = &self.traffic;
+        if t.enable == Some(true) {
+            commands.push(Command::SetTraffic(SetTraffic {
+                enable: true,
+                ..Default::default()
+            }));
+        }
+        if let Some(kind) = t.kind {
+            commands.push(Command::SetTraffic(SetTraffic {
+                kind: Some(kind),
+                ..Default::default()
+            }));
+        }
+        if let Some(interval_ms) = t.interval_ms {
+            commands.push(Command::SetTraffic(SetTraffic {
+                interval_ms: Some(interval_ms),
+                ..Default::default()
+            }));
+        }
+
+        let c = &self.csi;
+        for flag in &c.disable {
+            let mut disable = heapless::Vec::new();
+            let _ = disable.push(*flag);
+            commands.push(Command::SetCsi(SetCsi {
+                disable,
+                ..Default::default()
+            }));
+        }
+        if let Some(he_stbc) = c.he_stbc {
+            commands.push(Command::SetCsi(SetCsi {
+                he_stbc: Some(he_stbc),
+                ..Default::default()
+            }));
+        }
+        if let Some(val_scale_cfg) = c.val_scale_cfg {
+            commands.push(Command::SetCsi(SetCsi {
+                val_scale_cfg: Some(val_scale_cfg),
+                ..Default::default()
+            }));
+        }
+
+        if let Some(format) = self.output.format {
+            commands.push(Command::SetOutput {
+                format: Some(format),
+            });
+        }
+
+        for command in &commands {
+            if command.to_string().len() >= MAX_LINE_LEN {
+                bail!("`{command}` exceeds the device's {MAX_LINE_LEN} character line limit");
+            }
+        }
+        Ok(commands)
+    }
+
+    /// `show-config` entries the device must report once the experiment is applied.
+    pub fn expected(&self) -> Vec<(&'static str, String)> {
+        let mut expected = Vec::new();
+
+        let w = &self.wifi;
+        if let Some(mode) = w.mode {
+            expected.push((key::WIFI_MODE, mode.to_string()));
+        }
+        if let Some(max_connections) = w.max_connections {
+            expected.push((key::AP_MAX_CONNECTIONS, max_connections.to_string()));
+        }
+        if w.hide_ssid {
+            expected.push((key::AP_SSID_HIDDEN, true.to_string()));
+        }
+        for (key, value) in [
+            (key::AP_SSID, &w.ap_ssid),
+            (key::AP_PASSWORD, &w.ap_password),
+            (key::STA_SSID, &w.sta_ssid),
+            (key::STA_PASSWORD, &w.sta_password),
+        ] {
+            if let Some(value) = value {
+                expected.push((key, value.clone()));
+            }
+        }
+
+        if let Some(arch) = self.network.arch {
+            expected.push((key::NETWORK_ARCH, arch.to_string()));
+        }
+
+        let t = &self.traffic;
+        if let Some(enable) = t.enable {
+            expected.push((key::TRAFFIC_ENABLED, enable.to_string()));
+        }
+        if let Some(kind) = t.kind {
+            expected.push((key::TRAFFIC_TYPE, kind.to_string()));
+        }
+        if let Some(interval_ms) = t.interval_ms {
+            expected.push((key::TRAFFIC_INTERVAL, format!("{interval_ms}ms")));
+        }
+
+        let c = &self.csi;
+        for flag in &c.disable {
+            let (key, value) = flag.dump_entry();
+            expected.push((key, value.to_string()));
+        }
+        if let Some(he_stbc) = c.he_stbc {
+            expected.push((key::CSI_HE_STBC, he_stbc.to_string()));
+        }
+        if let Some(val_scale_cfg) = c.val_scale_cfg {
+            expected.push((key::VAL_SCALE_CFG, val_scale_cfg.to_string()));
+        }
+
+        if let Some(format) = self.output.format {
+            expected.push((key::OUTPUT_FORMAT, format.to_string()));
+        }
+        expected
+    }
+}
+
+fn text<const N: usize>(value: &str, field: &str) -> Result<heapless::String<N>> {
+    if value.contains('_') {
+        bail!("{field} cannot contain underscores, they are sent in place of spaces");
+    }
+    heapless::String::try_from(value)
+        .map_err(|_| anyhow::anyhow!("{field} is longer than {N} characters"))
+}
+
+/// Applies `path` to the device on `port`, verifies it, and records the run
+/// into `out` together with a copy of the experiment file.
+pub fn run(path: &Path, port: &str, baud: u32, out: Option<PathBuf>) -> Result<()> {
+    let experiment: Experiment = load(path)?;
+    let commands = experiment.settings.commands()?;
+
+    let (tx, lines) = mpsc::channel();
+    let mut device = Device::open(port, baud, tx)?;
+
+    println!("Applying {} ({} settings)", experiment.name, commands.len());
+    let dump = configure(&mut device, &lines, &experiment.settings)?;
+    println!("Configuration verified");
+
+    let out = output_dir(&experiment.name, out)?;
+    fs::copy(path, out.join("experiment.toml"))?;
+    let source = capture::source(capture::SINGLE_SOURCE, &dump);
+    let (mut writer, epoch) = capture::create(&out.join(CAPTURE_FILE), vec![source])?;
+    for label in &experiment.labels {
+        writer.label(0, label)?;
+    }
+
+    let start = Command::Start {
+        duration_s: experiment.duration_s,
+        count: experiment.count,
+    };
+    let interrupted = interrupt_flag()?;
+    device.send_command(&start.to_string())?;
+    println!("Collecting into {}", out.display());
+
+    let deadline = experiment.duration_s.map(stop_deadline);
+    let mut decoder = Decoder::new();
+    let mut records = 0u64;
+    let mut stop_sent: Option<Instant> = None;
+    loop {
+        if stop_sent.is_some_and(|at| at.elapsed() > DRAIN) {
+            eprintln!("The device did not report stopping after stop was sent");
+            break;
+        }
+        if stop_sent.is_none() {
+            if interrupted.load(Ordering::Relaxed) {
+                device.send_command(&Command::Stop.to_string())?;
+                eprintln!("Interrupted, sent stop");
+                stop_sent = Some(Instant::now());
+            } else if deadline.is_some_and(|d| Instant::now() > d) {
+                device.send_command(&Command::Stop.to_string())?;
+                eprintln!("No stop event received in time, sent stop");
+                stop_sent = Some(Instant::now());
+            }
+        }
+        let line = match lines.recv_timeout(SETTLE) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => bail!("serial port closed"),
+        };
+        writer.line(0, capture::host_us(epoch, line.at), &line.text)?;
+        match decoder.push_line(&line.text) {
+            Decoded::Record(_) => records += 1,
+            Decoded::Event(Event::Stopped { elapsed_ms, .. }) => {
+                println!("Stopped after {:.1}s", elapsed_ms as f64 / 1000.0);
+                break;
+            }
+            _ => {}
+        }
+    }
+    writer.finish()?;
+    println!("Done, {records} records captured");
+    Ok(())
+}
+
+/// Resets the device, applies `settings` and checks them against the
+/// `show-config` dump, which is returned.
+pub fn configure(
+    device: &mut Device,
+    lines: &Receiver<Line>,
+    settings: &Settings,
+) -> Result<Vec<String>> {
+    execute(device, lines, &Command::ResetConfig.to_string())?;
+    for command in settings.commands()? {
+        execute(device, lines, &command.to_string())?;
+    }
+
+    let dump = execute(device, lines, &Command::ShowConfig.to_string())?;
+    let reported: HashMap<&str, &str> = dump.iter().filter_map(|l| parse_dump_line(l)).collect();
+    let mut mismatches = 0;
+    for (key, value) in settings.expected() {
+        match reported.get(key) {
+            Some(v) if *v == value => {}
+            Some(v) => {
+                eprintln!("{key}: expected {value}, device reports {v}");
+                mismatches += 1;
+            }
+            None => {
+                eprintln!("{key}: expected {value}, not reported by the device");
+                mismatches += 1;
+            }
+        }
+    }
+    if mismatches > 0 {
+        bail!("device configuration does not match the experiment");
+    }
+    Ok(dump)
+}
+
+/// Creates `out`, defaulting to `name` followed by a unix timestamp.
+pub fn output_dir(name: &str, out: Option<PathBuf>) -> Result<PathBuf> {
+    let out = out.unwrap_or_else(|| {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        PathBuf::from(format!("{}-{secs}", name.replace(' ', "_")))
+    });
+    fs::create_dir_all(&out).with_context(|| format!("failed to create {}", out.display()))?;
+    Ok(out)
+}
+
+/// Installs a Ctrl-C handler raising the returned flag, so that a run can
+/// stop the devices and finish its capture instead of being killed.
+pub fn interrupt_flag() -> Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let raised = flag.clone();
+    ctrlc::set_handler(move || raised.store(true, Ordering::Relaxed))
+        .context("failed to install the Ctrl-C handler")?;
+    Ok(flag)
+}
+
+/// Time after which a run of `duration_s` is stopped from the host if the
+/// device has not reported it stopped.
+pub fn stop_deadline(duration_s: u64) -> Instant {
+    Instant::now() + Duration::from_secs(duration_s) + GRACE
+}
+
+/// Sends `command` and returns the output printed until the device goes quiet,
+/// failing if the device rejected a value.
+pub fn execute(device: &mut Device, lines: &Receiver<Line>, command: &str) -> Result<Vec<String>> {
+    device.send_command(command)?;
+    let mut output = Vec::new();
+    while let Ok(Line { text, .. }) = lines.recv_timeout(SETTLE) {
+        if REFUSALS.iter().any(|p| text.trim_start().starts_with(p)) {
+            bail!("device rejected `{command}`: {}", text.trim());
+        }
+        output.push(text);
+    }
+    Ok(output)
+}
@@ -0,0 +1,17 @@
+//! Host-side companion tools for esp-csi-cli-rs, behind the `csi-host` binary.
+//!
+//! - [`app`]: the dashboard state fed by the device stream and the keyboard.
+//! - [`ui`]: drawing the dashboard.
+//! - [`serial`]: the serial connection to a device.
+//! - [`replay`]: playing back captures and raw serial logs.
+//! - [`capture`]: creating capture files.
+//! - [`experiment`]: applying and verifying experiment files.
+//! - [`session`]: multi-device captures.
+
+pub mod app;
+pub mod capture;
+pub mod experiment;
+pub mod replay;
+pub mod serial;
+pub mod session;
+pub mod ui;
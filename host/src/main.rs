@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use clap::{Args, Parser, Subcommand};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::DefaultTerminal;
+
+use esp_csi_host::app::App;
+use esp_csi_host::serial::{Device, Line};
+use esp_csi_host::{capture, experiment, replay, session, ui};
+
+// Redraw interval of the dashboard.
+const TICK: Duration = Duration::from_millis(50);
+
+#[derive(Parser)]
+#[command(version, about = "Host-side companion tools for esp-csi-cli-rs")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Live dashboard for a device connected over serial.
+    Monitor(MonitorArgs),
+    /// Play back a capture written by `monitor --save`, `run` or `capture`.
+    Replay(ReplayArgs),
+    /// Apply an experiment file, verify it and record the collection run.
+    Run(RunArgs),
+    /// Configure several devices from a setup file, start them together and
+    /// merge their streams into one capture.
+    Capture(CaptureArgs),
+}
+
+#[derive(Args)]
+struct MonitorArgs {
+    /// Serial port the device is connected to (e.g. /dev/ttyACM0).
+    port: String,
+    /// Baud rate, only relevant for UART-attached boards such as the ESP32.
+    #[arg(long, default_value_t = 115_200)]
+    baud: u32,
+    /// Save the stream, including inserted labels, to this capture file.
+    #[arg(long)]
+    save: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ReplayArgs {
+    /// Capture file to play back.
+    file: PathBuf,
+    /// Playback speed relative to the original capture.
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Experiment file describing the settings and the run.
+    experiment: PathBuf,
+    /// Serial port the device is connected to (e.g. /dev/ttyACM0).
+    port: String,
+    /// Baud rate, only relevant for UART-attached boards such as the ESP32.
+    #[arg(long, default_value_t = 115_200)]
+    baud: u32,
+    /// Directory receiving the capture and a copy of the experiment file.
+    /// Defaults to the experiment name followed by a timestamp.
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct CaptureArgs {
+    /// Setup file listing the devices and the settings of each role.
+    setup: PathBuf,
+    /// Directory receiving the merged capture and a copy of the setup file.
+    /// Defaults to the setup name followed by a timestamp.
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let (tx, rx) = mpsc::channel();
+
+    let (mut app, device) = match cli.command {
+        Command::Monitor(args) => {
+            let mut device = Device::open(&args.port, args.baud, tx)?;
+            let save = match args.save {
+                Some(path) => {
+                    let source = capture::describe(capture::SINGLE_SOURCE, &mut device, &rx)?;
+                    Some(capture::create(&path, vec![source])?)
+                }
+                None => None,
+            };
+            (App::new(true, save), Some(device))
+        }
+        Command::Replay(args) => {
+            if args.speed.is_nan() || args.speed <= 0.0 {
+                bail!("--speed must be positive");
+            }
+            replay::spawn(&args.file, args.speed, tx)?;
+            (App::new(false, None), None)
+        }
+        Command::Run(args) => {
+            return experiment::run(&args.experiment, &args.port, args.baud, args.out);
+        }
+        Command::Capture(args) => return session::run(&args.setup, args.out),
+    };
+
+    let terminal = ratatui::init();
+    let result = run(terminal, &mut app, device, rx);
+    ratatui::restore();
+    result.and(app.finish())
+}
+
+fn run(
+    mut terminal: DefaultTerminal,
+    app: &mut App,
+    mut device: Option<Device>,
+    lines: Receiver<Line>,
+) -> Result<()> {
+    while !app.quit {
+        while let Ok(line) = lines.try_recv() {
+            app.handle_line(line);
+        }
+
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    handle_key(app, device.as_mut(), key.code)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_key(app: &mut App, device: Option<&mut Device>, code: KeyCode) -> Result<()> {
+    if let (Some(command), Some(device)) = (app.handle_key(code), device) {
+        device.send_command(&command.to_string())?;
+    }
+    Ok(())
+}
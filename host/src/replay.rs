@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use esp_csi_protocol::capture::{CaptureReader, Entry, MAGIC};
+use esp_csi_protocol::config::key;
+use esp_csi_protocol::{Decoded, Decoder, OutputFormat};
+
+use crate::app::LABEL_PREFIX;
+use crate::serial::Line;
+
+// Gaps longer than this (idle periods, timestamp wrap) are not reproduced.
+const MAX_GAP: Duration = Duration::from_secs(1);
+
+/// Streams a saved capture into `lines`, pacing frames by their receive times
+/// divided by `speed`. Raw serial logs are paced by the device timestamps.
+pub fn spawn(path: &Path, speed: f64, lines: Sender<Line>) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut input = BufReader::new(file);
+
+    if input.fill_buf()?.starts_with(MAGIC.as_bytes()) {
+        let reader = CaptureReader::new(input)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        thread::spawn(move || replay_capture(reader, speed, lines));
+    } else {
+        thread::spawn(move || replay_raw(input, speed, lines));
+    }
+    Ok(())
+}
+
+fn replay_capture(mut reader: CaptureReader<BufReader<File>>, speed: f64, lines: Sender<Line>) {
+    for source in &reader.header().sources {
+        let get = |k| source.get(k).unwrap_or("?");
+        let line = format!(
+            "# source {}: {}, firmware {} ({})",
+            source.id,
+            get(key::CHIP),
+            get(key::FIRMWARE_VERSION),
+            get(key::GIT_HASH)
+        );
+        if lines.send(Line::now(line)).is_err() {
+            return;
+        }
+    }
+
+    let mut last_us: Option<u64> = None;
+    let mut pace = |host_us: u64| {
+        if let Some(prev) = last_us {
+            let delta = Duration::from_micros(host_us.saturating_sub(prev));
+            thread::sleep(delta.min(MAX_GAP).div_f64(speed));
+        }
+        last_us = Some(host_us);
+    };
+
+    while let Ok(Some(entry)) = reader.next_entry() {
+        let line = match entry {
+            // Records are forwarded as single CSV lines so that multi-line
+            // records of several sources cannot interleave.
+            Entry::Line {
+                host_us,
+                decoded: Decoded::Record(record),
+                ..
+            } => {
+                pace(host_us);
+                record.display(OutputFormat::Csv).to_string()
+            }
+            Entry::Line { line, .. } => line,
+            Entry::Label { host_us, label } => {
+                pace(host_us);
+                format!("{LABEL_PREFIX} {label}")
+            }
+            Entry::Index(_) => continue,
+        };
+        if lines.send(Line::now(line)).is_err() {
+            return;
+        }
+    }
+}
+
+fn replay_raw(input: BufReader<File>, speed: f64, lines: Sender<Line>) {
+    let mut decoder = Decoder::new();
+    let mut last_timestamp: Option<u32> = None;
+    for line in input.lines() {
+        let Ok(line) = line else { return };
+
+        // Records are paced as a whole, once their last line has been read.
+        if let Decoded::Record(record) = decoder.push_line(&line) {
+            let ts = record.timestamp;
+            if let Some(prev) = last_timestamp {
+                let delta = Duration::from_micros(ts.wrapping_sub(prev) as u64);
+                thread::sleep(delta.min(MAX_GAP).div_f64(speed));
+            }
+            last_timestamp = Some(ts);
+        }
+
+        if lines.send(Line::now(line)).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serialport::SerialPort;
+
+/// A line received from a device, stamped when its newline was read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub at: Instant,
+    pub text: String,
+}
+
+impl Line {
+    /// A line stamped now, for lines that do not come from a serial port.
+    pub fn now(text: impl Into<String>) -> Self {
+        Self {
+            at: Instant::now(),
+            text: text.into(),
+        }
+    }
+}
+
+/// Write half of a device connection, used to type CLI commands.
+pub struct Device {
+    port: Box<dyn SerialPort>,
+}
+
+impl Device {
+    /// Opens `path` and spawns a thread forwarding every received line to `lines`.
+    pub fn open(path: &str, baud: u32, lines: Sender<Line>) -> Result<Self> {
+        let port = serialport::new(path, baud)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .with_context(|| format!("failed to open serial port {path}"))?;
+        let reader = port.try_clone().context("failed to clone serial port")?;
+
+        thread::spawn(move || read_lines(reader, lines));
+
+        Ok(Self { port })
+    }
+
+    /// Sends a single command line to the device CLI.
+    pub fn send_command(&mut self, command: &str) -> Result<()> {
+        // The menu runner on the device executes the line on carriage return.
+        self.port.write_all(command.as_bytes())?;
+        self.port.write_all(b"\r")?;
+        self.port.flush()?;
+        Ok(())
+    }
+}
+
+fn read_lines(port: Box<dyn SerialPort>, lines: Sender<Line>) {
+    let mut reader = BufReader::new(port);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => return,
+            Ok(_) if buf.ends_with(b"\n") => {
+                let line = Line::now(String::from_utf8_lossy(&buf).trim_end());
+                buf.clear();
+                if lines.send(line).is_err() {
+                    return;
+                }
+            }
+            // Partial line, keep accumulating until the newline arrives.
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+            Err(_) => return,
+        }
+    }
+}
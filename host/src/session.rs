@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use esp_csi_protocol::{Command, Decoded, Decoder, Event};
+use serde::Deserialize;
+
+use crate::capture::{self, CAPTURE_FILE};
+use crate::experiment::{self, Settings, DRAIN};
+use crate::serial::{Device, Line};
+
+// Lines are held back this long so that lines from all devices can be
+// written in receive order.
+const REORDER: Duration = Duration::from_millis(200);
+
+// Capture source entries added next to the device's own configuration.
+const PORT_KEY: &str = "Port";
+const ROLE_KEY: &str = "Role";
+
+/// Several devices configured from per-role settings and started together.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Setup {
+    pub name: String,
+    /// Collection duration in seconds, or until interrupted if left out.
+    pub duration_s: Option<u64>,
+    /// Number of CSI records after which collection stops, combined with
+    /// `duration_s` as a timeout if both are given.
+    pub count: Option<u32>,
+    /// Added to the start of the capture as labels.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub roles: BTreeMap<String, Settings>,
+    #[serde(rename = "device")]
+    pub devices: Vec<DeviceSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceSpec {
+    /// Name tagging the device's lines in the merged capture.
+    pub id: String,
+    pub port: String,
+    pub role: String,
+    #[serde(default = "default_baud")]
+    pub baud: u32,
+}
+
+fn default_baud() -> u32 {
+    115_200
+}
+
+impl Setup {
+    fn validate(&self) -> Result<()> {
+        if self.devices.is_empty() {
+            bail!("no devices listed");
+        }
+        let mut ids = HashSet::new();
+        for device in &self.devices {
+            if device.id.is_empty() || device.id.contains([',', ' ']) {
+                bail!(
+                    "device id `{}` must be non-empty and without commas or spaces",
+                    device.id
+                );
+            }
+            if !ids.insert(&device.id) {
+                bail!("device id `{}` is used twice", device.id);
+            }
+            let role = self.roles.get(&device.role).with_context(|| {
+                format!("device {} has unknown role `{}`", device.id, device.role)
+            })?;
+            role.commands()
+                .with_context(|| format!("role `{}`", device.role))?;
+        }
+        Ok(())
+    }
+}
+
+/// A line received from one of the devices.
+struct Received {
+    device: usize,
+    line: Line,
+}
+
+/// Configures every device in `path`, starts them together and merges their
+/// streams into `out`.
+///
+/// Each device is a source of the capture, tagged with its id, and lines are
+/// written in the order the host received them.
+pub fn run(path: &Path, out: Option<PathBuf>) -> Result<()> {
+    let setup: Setup = experiment::load(path)?;
+    setup.validate()?;
+
+    let mut devices = Vec::new();
+    let mut receivers = Vec::new();
+    for spec in &setup.devices {
+        let (tx, rx) = mpsc::channel();
+        devices.push(Device::open(&spec.port, spec.baud, tx)?);
+        receivers.push(rx);
+    }
+
+    println!("Configuring {} devices", devices.len());
+    let dumps = thread::scope(|s| {
+        let handles: Vec<_> = devices
+            .iter_mut()
+            .zip(receivers.iter_mut())
+            .zip(&setup.devices)
+            .map(|((device, lines), spec)| {
+                let settings = &setup.roles[&spec.role];
+                s.spawn(move || {
+                    experiment::configure(device, lines, settings)
+                        .with_context(|| format!("failed to configure {}", spec.id))
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("configuration thread panicked"))
+            .collect::<Result<Vec<_>>>()
+    })?;
+    println!("Configuration verified");
+
+    let out = experiment::output_dir(&setup.name, out)?;
+    fs::copy(path, out.join("setup.toml"))?;
+    let (merged_tx, merged) = mpsc::channel();
+    for (device, lines) in receivers.into_iter().enumerate() {
+        let merged_tx = merged_tx.clone();
+        thread::spawn(move || forward(device, lines, merged_tx));
+    }
+    drop(merged_tx);
+
+    // Every device waits at the barrier with its command ready, so the start
+    // commands go out within a few microseconds of each other.
+    let start = Command::Start {
+        duration_s: setup.duration_s,
+        count: setup.count,
+    }
+    .to_string();
+    let sources = setup
+        .devices
+        .iter()
+        .zip(&dumps)
+        .map(|(spec, dump)| {
+            let mut source = capture::source(&spec.id, dump);
+            source.info.push((PORT_KEY.into(), spec.port.clone()));
+            source.info.push((ROLE_KEY.into(), spec.role.clone()));
+            source
+        })
+        .collect();
+    let (mut writer, epoch) = capture::create(&out.join(CAPTURE_FILE), sources)?;
+    for label in &setup.labels {
+        writer.label(0, label)?;
+    }
+    let interrupted = experiment::interrupt_flag()?;
+    let barrier = Barrier::new(devices.len());
+    thread::scope(|s| {
+        let handles: Vec<_> = devices
+            .iter_mut()
+            .map(|device| {
+                let (barrier, start) = (&barrier, &start);
+                s.spawn(move || {
+                    barrier.wait();
+                    device.send_command(start)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .try_for_each(|h| h.join().expect("start thread panicked"))
+    })?;
+    println!("Collecting into {}", out.display());
+
+    let deadline = setup.duration_s.map(experiment::stop_deadline);
+    let mut decoders: Vec<Decoder> = devices.iter().map(|_| Decoder::new()).collect();
+    let mut stopped = HashSet::new();
+    let mut pending: Vec<Received> = Vec::new();
+    let mut records = 0u64;
+    let mut stop_sent: Option<Instant> = None;
+    loop {
+        let finished = stopped.len() == devices.len();
+        let gave_up = stop_sent.is_some_and(|at| at.elapsed() > DRAIN);
+        if gave_up {
+            eprintln!("Not every device reported stopping after stop was sent");
+            pending.extend(merged.try_iter());
+        }
+        let late = deadline.is_some_and(|d| Instant::now() > d);
+        let interrupt = interrupted.load(Ordering::Relaxed);
+        if stop_sent.is_none() && !finished && (late || interrupt) {
+            for (i, device) in devices.iter_mut().enumerate() {
+                if !stopped.contains(&i) {
+                    device.send_command(&Command::Stop.to_string())?;
+                }
+            }
+            match interrupt {
+                true => eprintln!("Interrupted, sent stop"),
+                false => eprintln!("Not every device reported stopping in time, sent stop"),
+            }
+            stop_sent = Some(Instant::now());
+        }
+        if !finished && !gave_up {
+            match merged.recv_timeout(REORDER) {
+                Ok(received) => pending.push(received),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => bail!("serial port closed"),
+            }
+        }
+
+        // Each device has its own decoder, so text records from different
+        // devices may interleave line by line. A record is stamped with the
+        // arrival of its last line. Once done, lines still held back are
+        // written without waiting.
+        pending.sort_by_key(|r| r.line.at);
+        let ready = match finished || gave_up {
+            true => pending.len(),
+            false => pending.partition_point(|r| r.line.at.elapsed() > REORDER),
+        };
+        for Received { device, line } in pending.drain(..ready) {
+            writer.line(device, capture::host_us(epoch, line.at), &line.text)?;
+            match decoders[device].push_line(&line.text) {
+                Decoded::Record(_) => records += 1,
+                Decoded::Event(Event::Stopped { .. }) => {
+                    stopped.insert(device);
+                }
+                Decoded::Malformed => eprintln!("{}: malformed record", setup.devices[device].id),
+                _ => {}
+            }
+        }
+        if finished || gave_up {
+            break;
+        }
+    }
+    writer.finish()?;
+    println!("Done, {records} records captured");
+    Ok(())
+}
+
+fn forward(device: usize, lines: Receiver<Line>, merged: Sender<Received>) {
+    for line in lines {
+        if merged.send(Received { device, line }).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, Widget};
+use ratatui::Frame;
+
+use crate::app::{App, Mode};
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let [main, console, footer] = Layout::vertical([
+        Constraint::Min(10),
+        Constraint::Length(8),
+        Constraint::Length(2),
+    ])
+    .areas(frame.area());
+    let [heatmap, sources] =
+        Layout::horizontal([Constraint::Min(20), Constraint::Length(48)]).areas(main);
+
+    let block = Block::bordered().title(" Subcarrier amplitude ");
+    let inner = block.inner(heatmap);
+    frame.render_widget(block, heatmap);
+    frame.render_widget(
+        Heatmap {
+            columns: &app.heatmap,
+        },
+        inner,
+    );
+
+    let rows = app.sources.iter().map(|(mac, stats)| {
+        Row::new([
+            mac.to_string(),
+            stats.last_rssi.to_string(),
+            format!("{}..{}", stats.min_rssi, stats.max_rssi),
+            stats.frames.to_string(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(17),
+            Constraint::Length(5),
+            Constraint::Length(9),
+            Constraint::Min(6),
+        ],
+    )
+    .header(
+        Row::new(["Source", "RSSI", "Range", "Frames"])
+            .style(Style::new().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::bordered().title(" Sources "));
+    frame.render_widget(table, sources);
+
+    let height = console.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = app
+        .console
+        .iter()
+        .skip(app.console.len().saturating_sub(height))
+        .map(|l| Line::raw(l.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Console ")),
+        console,
+    );
+
+    let rate = app.frame_rate();
+    let stats = Line::from(format!(
+        " {:.1} fps | frames: {} | malformed: {} | lost: {} | label: {}",
+        rate,
+        app.frames,
+        app.malformed,
+        app.lost,
+        app.label.as_deref().unwrap_or("-"),
+    ));
+    let help = match &app.mode {
+        Mode::Label(text) => Line::from(format!(" label> {text}_   (enter: insert, esc: cancel)"))
+            .style(Style::new().add_modifier(Modifier::REVERSED)),
+        Mode::Normal if app.live => Line::from(" s: start  x: stop  l: label  q: quit"),
+        Mode::Normal => Line::from(" l: label  q: quit"),
+    };
+    frame.render_widget(Paragraph::new(vec![stats, help]), footer);
+}
+
+/// Rolling heatmap: one column per frame (newest on the right), one row per
+/// group of subcarriers, colour mapped to amplitude.
+struct Heatmap<'a> {
+    columns: &'a VecDeque<Vec<f32>>,
+}
+
+impl Widget for Heatmap<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let skip = self.columns.len().saturating_sub(area.width as usize);
+        let visible: Vec<&Vec<f32>> = self.columns.iter().skip(skip).collect();
+        let peak = visible
+            .iter()
+            .flat_map(|c| c.iter())
+            .fold(1.0f32, |acc, v| acc.max(*v));
+
+        let x0 = area.right() - visible.len() as u16;
+        for (i, column) in visible.iter().enumerate() {
+            if column.is_empty() {
+                continue;
+            }
+            for row in 0..area.height {
+                // Subcarrier 0 at the top, split evenly across the rows.
+                let start = row as usize * column.len() / area.height as usize;
+                let end = ((row as usize + 1) * column.len() / area.height as usize).max(start + 1);
+                let bin = &column[start..end.min(column.len())];
+                let value = bin.iter().sum::<f32>() / bin.len() as f32;
+                buf[(x0 + i as u16, area.y + row)].set_bg(colour(value / peak));
+            }
+        }
+    }
+}
+
+// Five-stop dark-blue to yellow gradient, readable on most terminals.
+fn colour(level: f32) -> Color {
+    const STOPS: [(u8, u8, u8); 5] = [
+        (13, 8, 135),
+        (84, 2, 163),
+        (185, 50, 137),
+        (249, 142, 9),
+        (240, 249, 33),
+    ];
+    let scaled = level.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let idx = (scaled.floor() as usize).min(STOPS.len() - 2);
+    let t = scaled - idx as f32;
+    let (a, b) = (STOPS[idx], STOPS[idx + 1]);
+    let lerp = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t) as u8;
+    Color::Rgb(lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+}
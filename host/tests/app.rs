@@ -0,0 +1,109 @@
+use esp_csi_host::app::{App, Mode, LABEL_PREFIX};
+use esp_csi_host::serial::Line;
+use esp_csi_protocol::{Command, CsiRecord, Event, OutputFormat};
+use ratatui::crossterm::event::KeyCode;
+
+fn record(seq: u32) -> String {
+    CsiRecord {
+        seq,
+        mac: "24:0A:C4:00:00:01".parse().unwrap(),
+        rssi: -50,
+        ..Default::default()
+    }
+    .display(OutputFormat::Csv)
+    .to_string()
+}
+
+fn event(event: Event) -> String {
+    event.display(OutputFormat::Csv).to_string()
+}
+
+fn type_label(app: &mut App, label: &str) -> Option<Command> {
+    assert_eq!(app.handle_key(KeyCode::Char('l')), None);
+    for c in label.chars() {
+        app.handle_key(KeyCode::Char(c));
+    }
+    app.handle_key(KeyCode::Enter)
+}
+
+#[test]
+fn counts_records_and_gaps_until_the_next_run() {
+    let mut app = App::new(false, None);
+    for seq in [0, 1, 4] {
+        app.handle_line(Line::now(record(seq)));
+    }
+    assert_eq!((app.frames, app.lost), (3, 2));
+    assert_eq!(app.sources.len(), 1);
+
+    // Sequence numbers restart with every run.
+    app.handle_line(Line::now(event(Event::Started { duration_s: 0 })));
+    app.handle_line(Line::now(record(0)));
+    assert_eq!(app.lost, 2);
+
+    app.handle_line(Line::now("0,1"));
+    app.handle_line(Line::now("not a record"));
+    assert!(app.console.back().is_some_and(|l| l == "not a record"));
+}
+
+#[test]
+fn labels_follow_the_device_and_the_capture() {
+    let mut app = App::new(false, None);
+    app.handle_line(Line::now(event(Event::Label {
+        label: "walk".try_into().unwrap(),
+        timestamp_us: 0,
+    })));
+    assert_eq!(app.label.as_deref(), Some("walk"));
+    app.handle_line(Line::now(event(Event::Label {
+        label: "".try_into().unwrap(),
+        timestamp_us: 0,
+    })));
+    assert_eq!(app.label, None);
+    app.handle_line(Line::now(format!("{LABEL_PREFIX} sit")));
+    assert_eq!(app.label.as_deref(), Some("sit"));
+}
+
+#[test]
+fn label_mode_sends_a_mark_to_a_live_device() {
+    let mut app = App::new(true, None);
+    let mark = type_label(&mut app, "door-open");
+    assert_eq!(
+        mark,
+        Some(Command::Mark {
+            label: "door-open".try_into().unwrap()
+        })
+    );
+    assert_eq!(app.mode, Mode::Normal);
+    assert_eq!(app.label.as_deref(), Some("door-open"));
+
+    // Labels the device cannot take stay on the host.
+    assert_eq!(type_label(&mut app, "door open"), None);
+    assert_eq!(app.label.as_deref(), Some("door open"));
+    // Escape and empty labels change nothing.
+    app.handle_key(KeyCode::Char('l'));
+    app.handle_key(KeyCode::Char('x'));
+    app.handle_key(KeyCode::Backspace);
+    assert_eq!(app.mode, Mode::Label(String::new()));
+    app.handle_key(KeyCode::Esc);
+    assert_eq!(type_label(&mut app, ""), None);
+    assert_eq!(app.label.as_deref(), Some("door open"));
+}
+
+#[test]
+fn replays_send_no_commands() {
+    let mut app = App::new(false, None);
+    assert_eq!(type_label(&mut app, "walk"), None);
+    assert_eq!(app.handle_key(KeyCode::Char('s')), None);
+    assert_eq!(app.handle_key(KeyCode::Char('x')), None);
+
+    let mut app = App::new(true, None);
+    assert_eq!(
+        app.handle_key(KeyCode::Char('s')),
+        Some(Command::Start {
+            duration_s: None,
+            count: None
+        })
+    );
+    assert_eq!(app.handle_key(KeyCode::Char('x')), Some(Command::Stop));
+    app.handle_key(KeyCode::Char('q'));
+    assert!(app.quit);
+}
@@ -0,0 +1,60 @@
+use esp_csi_host::experiment::{Experiment, Settings};
+use esp_csi_protocol::config::key;
+
+fn settings(text: &str) -> Settings {
+    toml::from_str::<Experiment>(&format!("name = \"test\"\n{text}"))
+        .unwrap()
+        .settings
+}
+
+#[test]
+fn expects_every_setting_as_the_dump_shows_it() {
+    let settings = settings(
+        r#"
+        [wifi]
+        mode = "station"
+        sta_ssid = "Lab Network"
+        [traffic]
+        enable = true
+        type = "udp"
+        interval_ms = 50
+        [output]
+        format = "csv"
+        "#,
+    );
+    let expected = settings.expected();
+    for entry in [
+        (key::WIFI_MODE, "station"),
+        (key::STA_SSID, "Lab Network"),
+        (key::TRAFFIC_ENABLED, "true"),
+        (key::TRAFFIC_TYPE, "udp"),
+        (key::TRAFFIC_INTERVAL, "50ms"),
+        (key::OUTPUT_FORMAT, "csv"),
+    ] {
+        assert!(
+            expected.contains(&(entry.0, entry.1.to_string())),
+            "{entry:?} in {expected:?}"
+        );
+    }
+    assert_eq!(expected.len(), 6);
+    // One command per setting, SSIDs sent with underscores.
+    let commands = settings.commands().unwrap();
+    assert_eq!(commands.len(), 6);
+    assert!(commands
+        .iter()
+        .any(|c| c.to_string() == "set-wifi --sta-ssid=Lab_Network"));
+}
+
+#[test]
+fn empty_settings_expect_nothing() {
+    let settings = settings("");
+    assert!(settings.expected().is_empty());
+    assert!(settings.commands().unwrap().is_empty());
+}
+
+#[test]
+fn rejects_values_the_device_cannot_take() {
+    assert!(toml::from_str::<Experiment>("name = \"x\"\n[wifi]\nmode = \"mesh\"").is_err());
+    let settings = settings("[wifi]\nsta_ssid = \"has_underscore\"");
+    assert!(settings.commands().is_err());
+}
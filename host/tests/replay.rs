@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use esp_csi_host::app::LABEL_PREFIX;
+use esp_csi_host::{capture, replay};
+use esp_csi_protocol::capture::Source;
+use esp_csi_protocol::{CsiRecord, OutputFormat};
+
+// Fast enough that pacing never slows the tests down.
+const SPEED: f64 = 1e6;
+
+fn temp(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("csi-host-{}-{name}", std::process::id()))
+}
+
+fn replay(path: &Path) -> Vec<String> {
+    let (tx, rx) = mpsc::channel();
+    replay::spawn(path, SPEED, tx).unwrap();
+    let mut lines = Vec::new();
+    while let Ok(line) = rx.recv_timeout(Duration::from_secs(5)) {
+        lines.push(line.text);
+    }
+    lines
+}
+
+fn record(seq: u32, format: OutputFormat) -> String {
+    CsiRecord {
+        seq,
+        ..Default::default()
+    }
+    .display(format)
+    .to_string()
+}
+
+#[test]
+fn plays_back_raw_serial_logs_line_by_line() {
+    let path = temp("raw.log");
+    let text = format!(
+        "booting\n{}\n{}\n",
+        record(0, OutputFormat::Csv),
+        record(1, OutputFormat::Csv)
+    );
+    fs::write(&path, &text).unwrap();
+    let lines = replay(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(lines, text.lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn plays_back_captures_with_sources_and_labels() {
+    let path = temp("capture.csi");
+    let (mut writer, _) =
+        capture::create(&path, vec![Source::new(capture::SINGLE_SOURCE)]).unwrap();
+    writer.line(0, 0, "hello").unwrap();
+    writer.label(10, "walk").unwrap();
+    for line in record(0, OutputFormat::Text).lines() {
+        writer.line(0, 20, line).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let lines = replay(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(lines[0].starts_with("# source device:"), "{}", lines[0]);
+    // Records come out as single CSV lines, whatever format they were saved in.
+    assert_eq!(
+        lines[1..],
+        [
+            "hello".to_string(),
+            format!("{LABEL_PREFIX} walk"),
+            record(0, OutputFormat::Csv)
+        ]
+    );
+}
@@ -0,0 +1,411 @@
+//! Capture container written and read by the host tools.
+//!
+//! A capture is a text file. It starts with a header describing each source
+//! device, continues with the lines received from the devices, and ends with
+//! a trailer:
+//!
+//! ```text
+//! #!csi-capture 1
+//! #!started 1760000000123
+//! #!source ap
+//! #!info ap Chip: esp32c3
+//! #!info ap Traffic Type: icmp
+//! #!end-header
+//! ap,1532,CSI_DATA,0,aa:bb:cc:dd:ee:ff,...
+//! #!label 2001 walking
+//! #!index records=1000 first_us=1532 last_us=98012 offset=412 prev=0
+//! #!end records=1000 index=40512
+//! ```
+//!
+//! - `#!info` lines carry the device's `show-config` dump, which includes its
+//!   build information (see [`crate::config::key`]).
+//! - Body lines are `<source>,<host_us>,<line>`. `host_us` is the host
+//!   receive time in microseconds since `started`.
+//! - An `#!index` line follows every [`INDEX_INTERVAL`] records and closes
+//!   the capture. `offset` is the byte offset of the block's first line, and
+//!   `prev` is the offset of the previous index line. `#!end` points at the
+//!   last index line, so readers can walk the blocks backwards from the end.
+//!
+//! Readers skip directives they do not know, so new directives can be added
+//! without bumping [`VERSION`]. The version only changes when old readers
+//! would misread a file.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use crate::config::parse_dump_line;
+use crate::format::{Decoded, Decoder};
+
+pub const MAGIC: &str = "#!csi-capture";
+pub const VERSION: u32 = 1;
+/// Number of records between index lines.
+pub const INDEX_INTERVAL: u64 = 1000;
+
+const DIRECTIVE: &str = "#!";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Header {
+    /// Host time the capture started, in milliseconds since the unix epoch.
+    pub started_unix_ms: u64,
+    pub sources: Vec<Source>,
+}
+
+/// A device contributing to the capture.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Source {
+    pub id: String,
+    /// `show-config` entries in the order the device reported them.
+    pub info: Vec<(String, String)>,
+}
+
+impl Source {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            info: Vec::new(),
+        }
+    }
+
+    /// Adds the `key: value` lines among `dump`, ignoring everything else.
+    pub fn add_dump<'a>(&mut self, dump: impl IntoIterator<Item = &'a str>) {
+        for line in dump {
+            if let Some((key, value)) = parse_dump_line(line) {
+                self.info.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.info
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Writes a capture, keeping track of the byte offsets needed for the index.
+pub struct CaptureWriter<W: Write> {
+    out: W,
+    offset: u64,
+    sources: Vec<String>,
+    decoders: Vec<Decoder>,
+    records: u64,
+    block: Block,
+    last_index: u64,
+}
+
+#[derive(Default)]
+struct Block {
+    records: u64,
+    first_us: Option<u64>,
+    last_us: u64,
+    offset: u64,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    pub fn new(mut out: W, header: &Header) -> io::Result<Self> {
+        let mut text = String::new();
+        let _ = write_header(&mut text, header);
+        out.write_all(text.as_bytes())?;
+        let offset = text.len() as u64;
+        Ok(Self {
+            out,
+            offset,
+            sources: header.sources.iter().map(|s| s.id.clone()).collect(),
+            decoders: header.sources.iter().map(|_| Decoder::new()).collect(),
+            records: 0,
+            block: Block {
+                offset,
+                ..Block::default()
+            },
+            last_index: 0,
+        })
+    }
+
+    /// Appends a line received from the source at `source` in the header.
+    pub fn line(&mut self, source: usize, host_us: u64, line: &str) -> io::Result<()> {
+        let id = &self.sources[source];
+        let text = std::format!("{id},{host_us},{line}\n");
+        self.write(&text)?;
+
+        self.block.first_us.get_or_insert(host_us);
+        self.block.last_us = host_us;
+        if let Decoded::Record(_) = self.decoders[source].push_line(line) {
+            self.records += 1;
+            self.block.records += 1;
+            if self.block.records == INDEX_INTERVAL {
+                self.index()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn label(&mut self, host_us: u64, label: &str) -> io::Result<()> {
+        self.write(&std::format!("{DIRECTIVE}label {host_us} {label}\n"))
+    }
+
+    /// Writes the last index line and the trailer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.block.first_us.is_some() {
+            self.index()?;
+        }
+        let end = std::format!(
+            "{DIRECTIVE}end records={} index={}\n",
+            self.records,
+            self.last_index
+        );
+        self.write(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn index(&mut self) -> io::Result<()> {
+        let b = &self.block;
+        let line = std::format!(
+            "{DIRECTIVE}index records={} first_us={} last_us={} offset={} prev={}\n",
+            b.records,
+            b.first_us.unwrap_or_default(),
+            b.last_us,
+            b.offset,
+            self.last_index
+        );
+        self.last_index = self.offset;
+        self.write(&line)?;
+        self.block = Block {
+            offset: self.offset,
+            ..Block::default()
+        };
+        Ok(())
+    }
+
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.out.write_all(text.as_bytes())?;
+        self.offset += text.len() as u64;
+        Ok(())
+    }
+}
+
+fn write_header(out: &mut impl fmt::Write, header: &Header) -> fmt::Result {
+    writeln!(out, "{MAGIC} {VERSION}")?;
+    writeln!(out, "{DIRECTIVE}started {}", header.started_unix_ms)?;
+    for source in &header.sources {
+        writeln!(out, "{DIRECTIVE}source {}", source.id)?;
+        for (key, value) in &source.info {
+            writeln!(out, "{DIRECTIVE}info {} {key}: {value}", source.id)?;
+        }
+    }
+    writeln!(out, "{DIRECTIVE}end-header")
+}
+
+/// An item read back from a capture.
+// Entries are handled one at a time, never stored in bulk.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    /// A decoded line of `source`. Lines completing nothing yet are not
+    /// reported, so a multi-line record shows up once.
+    Line {
+        source: usize,
+        host_us: u64,
+        decoded: Decoded,
+        line: String,
+    },
+    Label {
+        host_us: u64,
+        label: String,
+    },
+    Index(Index),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Index {
+    pub records: u64,
+    pub first_us: u64,
+    pub last_us: u64,
+    pub offset: u64,
+    pub prev: u64,
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    /// The file does not start with [`MAGIC`].
+    NotACapture,
+    /// The file was written by a newer, incompatible version.
+    UnsupportedVersion(u32),
+    /// A line could not be parsed; holds the 1-based line number.
+    Malformed(u64),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "{e}"),
+            ReadError::NotACapture => f.write_str("not a capture file"),
+            ReadError::UnsupportedVersion(v) => write!(f, "unsupported capture version {v}"),
+            ReadError::Malformed(line) => write!(f, "malformed capture line {line}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+/// Reads a capture sequentially.
+pub struct CaptureReader<R: BufRead> {
+    input: R,
+    header: Header,
+    ids: BTreeMap<String, usize>,
+    decoders: Vec<Decoder>,
+    line_no: u64,
+    buf: String,
+}
+
+impl<R: BufRead> CaptureReader<R> {
+    /// Reads the header, failing if `input` is not a capture.
+    pub fn new(mut input: R) -> Result<Self, ReadError> {
+        let mut buf = String::new();
+        input.read_line(&mut buf)?;
+        let version = buf
+            .trim_end()
+            .strip_prefix(MAGIC)
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .ok_or(ReadError::NotACapture)?;
+        if version > VERSION {
+            return Err(ReadError::UnsupportedVersion(version));
+        }
+
+        let mut reader = Self {
+            input,
+            header: Header::default(),
+            ids: BTreeMap::new(),
+            decoders: Vec::new(),
+            line_no: 1,
+            buf,
+        };
+        loop {
+            let Some(line) = reader.next_line()? else {
+                return Err(ReadError::Malformed(reader.line_no));
+            };
+            let Some((directive, rest)) = split_directive(&line) else {
+                return Err(ReadError::Malformed(reader.line_no));
+            };
+            match directive {
+                "end-header" => break,
+                "started" => {
+                    reader.header.started_unix_ms = rest
+                        .parse()
+                        .map_err(|_| ReadError::Malformed(reader.line_no))?;
+                }
+                "source" => {
+                    reader
+                        .ids
+                        .insert(rest.to_string(), reader.header.sources.len());
+                    reader.header.sources.push(Source::new(rest));
+                    reader.decoders.push(Decoder::new());
+                }
+                "info" => {
+                    let (id, entry) = rest
+                        .split_once(' ')
+                        .ok_or(ReadError::Malformed(reader.line_no))?;
+                    let source = reader
+                        .ids
+                        .get(id)
+                        .ok_or(ReadError::Malformed(reader.line_no))?;
+                    reader.header.sources[*source].add_dump([entry]);
+                }
+                _ => {}
+            }
+        }
+        Ok(reader)
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Returns the next entry, or `None` at the end of the capture.
+    pub fn next_entry(&mut self) -> Result<Option<Entry>, ReadError> {
+        loop {
+            let Some(line) = self.next_line()? else {
+                return Ok(None);
+            };
+            let malformed = ReadError::Malformed(self.line_no);
+            if let Some((directive, rest)) = split_directive(&line) {
+                match directive {
+                    "label" => {
+                        let (host_us, label) = rest.split_once(' ').unwrap_or((rest, ""));
+                        let host_us = host_us.parse().map_err(|_| malformed)?;
+                        return Ok(Some(Entry::Label {
+                            host_us,
+                            label: label.to_string(),
+                        }));
+                    }
+                    "index" => return Ok(Some(Entry::Index(parse_index(rest).ok_or(malformed)?))),
+                    "end" => return Ok(None),
+                    _ => continue,
+                }
+            }
+
+            let mut fields = line.splitn(3, ',');
+            let (Some(id), Some(host_us), Some(rest)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(malformed);
+            };
+            let source = *self.ids.get(id).ok_or(ReadError::Malformed(self.line_no))?;
+            let host_us = host_us
+                .parse()
+                .map_err(|_| ReadError::Malformed(self.line_no))?;
+            let decoded = self.decoders[source].push_line(rest);
+            if decoded == Decoded::Pending {
+                continue;
+            }
+            return Ok(Some(Entry::Line {
+                source,
+                host_us,
+                decoded,
+                line: rest.to_string(),
+            }));
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        self.buf.clear();
+        if self.input.read_line(&mut self.buf)? == 0 {
+            return Ok(None);
+        }
+        self.line_no += 1;
+        Ok(Some(self.buf.trim_end_matches(['\r', '\n']).to_string()))
+    }
+}
+
+fn split_directive(line: &str) -> Option<(&str, &str)> {
+    let line = line.strip_prefix(DIRECTIVE)?;
+    Some(line.split_once(' ').unwrap_or((line, "")))
+}
+
+fn parse_index(rest: &str) -> Option<Index> {
+    let mut index = Index::default();
+    for field in rest.split_whitespace() {
+        let (key, value) = field.split_once('=')?;
+        let value = value.parse().ok()?;
+        match key {
+            "records" => index.records = value,
+            "first_us" => index.first_us = value,
+            "last_us" => index.last_us = value,
+            "offset" => index.offset = value,
+            "prev" => index.prev = value,
+            _ => {}
+        }
+    }
+    Some(index)
+}
@@ -0,0 +1,274 @@
+//! Channel selection and the regulatory limits it is checked against.
+//!
+//! The collector leaves the channel to the WiFi driver, so the firmware sets
+//! the [`Country`] and the [`ChannelConfig`] chosen with `set-wifi` itself
+//! once WiFi is set up. A sniffer may instead cycle through the channels of a
+//! [`HopConfig`], reporting every switch with [`Event::Hop`](crate::Event::Hop).
+
+use core::fmt;
+use core::str::FromStr;
+
+use heapless::Vec;
+
+keyword_enum! {
+    /// Position of the secondary channel of a 40 MHz channel.
+    pub enum Secondary {
+        /// 20 MHz only.
+        None => "none",
+        Above => "above",
+        Below => "below",
+    }
+}
+
+/// Distance between a primary channel and its secondary channel.
+const SECONDARY_OFFSET: u8 = 4;
+
+/// Channels accepted in a [`ChannelList`].
+pub const MAX_HOP_CHANNELS: usize = 14;
+pub const DEFAULT_DWELL_MS: u64 = 200;
+/// Shortest dwell time, below which switching takes up most of the time.
+pub const MIN_DWELL_MS: u64 = 10;
+
+/// Country codes the WiFi driver accepts, with the highest 2.4 GHz channel
+/// allowed in each.
+const COUNTRIES: &[([u8; 2], u8)] = &[
+    (*b"01", 11),
+    (*b"AT", 13),
+    (*b"AU", 13),
+    (*b"BE", 13),
+    (*b"BG", 13),
+    (*b"BR", 13),
+    (*b"CA", 11),
+    (*b"CH", 13),
+    (*b"CN", 13),
+    (*b"CO", 11),
+    (*b"CY", 13),
+    (*b"CZ", 13),
+    (*b"DE", 13),
+    (*b"DK", 13),
+    (*b"EE", 13),
+    (*b"ES", 13),
+    (*b"FI", 13),
+    (*b"FR", 13),
+    (*b"GB", 13),
+    (*b"GR", 13),
+    (*b"HK", 13),
+    (*b"HR", 13),
+    (*b"HU", 13),
+    (*b"IE", 13),
+    (*b"IN", 13),
+    (*b"IS", 13),
+    (*b"IT", 13),
+    (*b"JP", 14),
+    (*b"KR", 13),
+    (*b"LI", 13),
+    (*b"LT", 13),
+    (*b"LU", 13),
+    (*b"LV", 13),
+    (*b"MT", 13),
+    (*b"MX", 11),
+    (*b"NL", 13),
+    (*b"NO", 13),
+    (*b"NZ", 13),
+    (*b"PL", 13),
+    (*b"PR", 11),
+    (*b"PT", 13),
+    (*b"RO", 13),
+    (*b"SE", 13),
+    (*b"SI", 13),
+    (*b"SK", 13),
+    (*b"TW", 11),
+    (*b"US", 11),
+];
+
+/// Two letter country code selecting the channels the radio may use, such as
+/// `US`, or `01` for the world safe set. Only the codes the WiFi driver
+/// accepts parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Country([u8; 2]);
+
+impl Country {
+    /// The country the WiFi driver starts with.
+    pub const DEFAULT: Country = Country(*b"CN");
+
+    pub fn as_bytes(&self) -> &[u8; 2] {
+        &self.0
+    }
+
+    /// Highest 2.4 GHz channel allowed, channels start at 1.
+    pub fn max_channel(&self) -> u8 {
+        COUNTRIES
+            .iter()
+            .find(|(code, _)| *code == self.0)
+            .map_or(13, |(_, max)| *max)
+    }
+}
+
+impl Default for Country {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for Country {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.0[0] as char, self.0[1] as char)
+    }
+}
+
+impl FromStr for Country {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes: [u8; 2] = s.as_bytes().try_into().map_err(|_| ())?;
+        let code = bytes.map(|b| b.to_ascii_uppercase());
+        match COUNTRIES.iter().any(|(known, _)| *known == code) {
+            true => Ok(Country(code)),
+            false => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelConfig {
+    /// Primary channel.
+    pub channel: u8,
+    pub secondary: Secondary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelError {
+    /// The channel is not allowed in the country.
+    NotAllowed { channel: u8, country: Country },
+    /// The secondary channel would fall outside the allowed channels.
+    NoSecondary { channel: u8, secondary: Secondary },
+    /// The dwell time is below [`MIN_DWELL_MS`].
+    ShortDwell,
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelError::NotAllowed { channel, country } => {
+                write!(f, "channel {} is not allowed in {}", channel, country)
+            }
+            ChannelError::NoSecondary { channel, secondary } => {
+                write!(
+                    f,
+                    "channel {} has no secondary channel {}",
+                    channel, secondary
+                )
+            }
+            ChannelError::ShortDwell => write!(f, "dwell time must be at least {}ms", MIN_DWELL_MS),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChannelError {}
+
+impl ChannelConfig {
+    /// Channel 14 only allows 802.11b, so it never has a secondary channel.
+    pub fn validate(&self, country: &Country) -> Result<(), ChannelError> {
+        let max = country.max_channel();
+        if !(1..=max).contains(&self.channel) {
+            return Err(ChannelError::NotAllowed {
+                channel: self.channel,
+                country: *country,
+            });
+        }
+        let last = max.min(13);
+        match self.secondary_channel() {
+            Some(secondary) if !(1..=last).contains(&secondary) || self.channel > last => {
+                Err(ChannelError::NoSecondary {
+                    channel: self.channel,
+                    secondary: self.secondary,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The secondary channel of a 40 MHz channel, which may be out of range.
+    pub fn secondary_channel(&self) -> Option<u8> {
+        match self.secondary {
+            Secondary::None => None,
+            Secondary::Above => Some(self.channel + SECONDARY_OFFSET),
+            Secondary::Below => Some(self.channel.wrapping_sub(SECONDARY_OFFSET)),
+        }
+    }
+}
+
+/// Comma separated channel numbers, written as `none` when empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelList(pub Vec<u8, MAX_HOP_CHANNELS>);
+
+impl ChannelList {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for ChannelList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("none");
+        }
+        for (i, channel) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", channel)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ChannelList {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut list = ChannelList::default();
+        if s == "none" {
+            return Ok(list);
+        }
+        for part in s.split(',') {
+            list.0.push(part.parse().map_err(|_| ())?).map_err(|_| ())?;
+        }
+        Ok(list)
+    }
+}
+
+/// Channels a sniffer cycles through, staying `dwell_ms` on each. Hopping is
+/// off while the list is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HopConfig {
+    pub channels: ChannelList,
+    pub dwell_ms: u64,
+}
+
+impl Default for HopConfig {
+    fn default() -> Self {
+        Self {
+            channels: ChannelList::default(),
+            dwell_ms: DEFAULT_DWELL_MS,
+        }
+    }
+}
+
+impl HopConfig {
+    /// Hopping uses 20 MHz channels, so only the primary channels are checked.
+    pub fn validate(&self, country: &Country) -> Result<(), ChannelError> {
+        for channel in self.channels.0.iter() {
+            ChannelConfig {
+                channel: *channel,
+                secondary: Secondary::None,
+            }
+            .validate(country)?;
+        }
+        if self.dwell_ms < MIN_DWELL_MS {
+            return Err(ChannelError::ShortDwell);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1115 @@
+//! CLI command and argument schema.
+//!
+//! The firmware names its menu items and parameters with the constants in
+//! [`name`] and [`arg`], and host tools use [`Command`] to build and validate
+//! command lines. Arguments are written as `--flag`, `--name=value` or, for
+//! a few commands, bare words right after the command name; values
+//! cannot contain whitespace, so spaces in SSIDs and passwords are sent as
+//! underscores and converted back by the firmware.
+
+use core::fmt;
+use core::str::FromStr;
+
+use heapless::String;
+
+use crate::channel::{ChannelList, Country, Secondary};
+use crate::event::{is_valid_label, Label};
+use crate::filter::{FilterConfig, MacList, RssiBound};
+use crate::format::OutputFormat;
+use crate::frame::FrameTypes;
+use crate::gpio::{GpioConfig, Polarity};
+use crate::headless::Sink;
+use crate::ppdu::{Bandwidths, SigModes, Stbc};
+use crate::record::MacAddr;
+use crate::trigger::TriggerConfig;
+
+/// Command names.
+pub mod name {
+    pub const SET_TRAFFIC: &str = "set-traffic";
+    pub const SET_NETWORK: &str = "set-network";
+    pub const SET_CSI: &str = "set-csi";
+    pub const SET_WIFI: &str = "set-wifi";
+    pub const SET_OUTPUT: &str = "set-output";
+    pub const START: &str = "start";
+    pub const STOP: &str = "stop";
+    pub const SCHEDULE: &str = "schedule";
+    pub const PAUSE: &str = "pause";
+    pub const RESUME: &str = "resume";
+    pub const STATUS: &str = "status";
+    pub const SET_TRIGGER: &str = "set-trigger";
+    pub const TRIGGER: &str = "trigger";
+    pub const SET_GPIO: &str = "set-gpio";
+    pub const MARK: &str = "mark";
+    pub const LABEL: &str = "label";
+    pub const SET_HEADLESS: &str = "set-headless";
+    pub const LOG: &str = "log";
+    pub const SET_FILTER: &str = "set-filter";
+    pub const SHOW_CONFIG: &str = "show-config";
+    pub const RESET_CONFIG: &str = "reset-config";
+}
+
+/// Argument names, without the leading `--`.
+pub mod arg {
+    // set-traffic
+    pub const ENABLE: &str = "enable";
+    pub const TYPE: &str = "type";
+    pub const INTERVAL_MS: &str = "interval-ms";
+    // set-network
+    pub const ARCH: &str = "arch";
+    // set-csi (all chips except the ESP32-C6)
+    pub const DISABLE_LLTF: &str = "disable-lltf";
+    pub const DISABLE_HTLTF: &str = "disable-htltf";
+    pub const DISABLE_STBC_HTLTF: &str = "disable-stbc-htltf";
+    pub const DISABLE_LTF_MERGE: &str = "disable-ltf-merge";
+    // set-csi (ESP32-C6)
+    pub const DISABLE_CSI: &str = "disable-csi";
+    pub const DISABLE_CSI_LEGACY: &str = "disable-csi-legacy";
+    pub const DISABLE_CSI_HT20: &str = "disable-csi-ht20";
+    pub const DISABLE_CSI_HT40: &str = "disable-csi-ht40";
+    pub const DISABLE_CSI_SU: &str = "disable-csi-su";
+    pub const DISABLE_CSI_MU: &str = "disable-csi-mu";
+    pub const DISABLE_CSI_DCM: &str = "disable-csi-dcm";
+    pub const DISABLE_CSI_BEAMFORMED: &str = "disable-csi-beamformed";
+    pub const CSI_HE_STBC: &str = "csi-he-stbc";
+    pub const VAL_SCALE_CFG: &str = "val-scale-cfg";
+    // set-wifi
+    pub const MODE: &str = "mode";
+    pub const MAX_CONNECTIONS: &str = "max-connections";
+    pub const HIDE_SSID: &str = "hide-ssid";
+    pub const AP_SSID: &str = "ap-ssid";
+    pub const AP_PASSWORD: &str = "ap-password";
+    pub const STA_SSID: &str = "sta-ssid";
+    pub const STA_PASSWORD: &str = "sta-password";
+    pub const CHANNEL: &str = "channel";
+    pub const SECONDARY: &str = "secondary";
+    pub const COUNTRY: &str = "country";
+    pub const HOP: &str = "hop";
+    pub const DWELL_MS: &str = "dwell-ms";
+    // set-output
+    pub const FORMAT: &str = "format";
+    // start, schedule
+    pub const DURATION: &str = "duration";
+    // schedule
+    pub const ACTION: &str = "action";
+    pub const START_IN: &str = "start-in";
+    pub const EVERY: &str = "every";
+    pub const COUNT: &str = "count";
+    // pause
+    pub const TRAFFIC: &str = "traffic";
+    // set-trigger
+    pub const OFF: &str = "off";
+    pub const RSSI: &str = "rssi";
+    pub const MAC: &str = "mac";
+    pub const VARIANCE: &str = "variance";
+    pub const PRE: &str = "pre";
+    pub const POST: &str = "post";
+    // set-gpio, also takes `off`
+    pub const START_PIN: &str = "start-pin";
+    pub const EVENT_PIN: &str = "event-pin";
+    pub const POLARITY: &str = "polarity";
+    pub const DEBOUNCE_MS: &str = "debounce-ms";
+    // mark, label (which also takes `action`)
+    pub const LABEL: &str = "label";
+    // set-headless, also takes `enable` and `duration`
+    pub const DISABLE: &str = "disable";
+    pub const SINK: &str = "sink";
+    // set-filter, also takes `off`
+    pub const ALLOW_MAC: &str = "allow-mac";
+    pub const DENY_MAC: &str = "deny-mac";
+    pub const MIN_RSSI: &str = "min-rssi";
+    pub const MAX_RSSI: &str = "max-rssi";
+    pub const FRAME_TYPES: &str = "frame-types";
+    pub const MAX_RATE: &str = "max-rate";
+    pub const MAX_RATE_PER_MAC: &str = "max-rate-per-mac";
+    pub const SIG_MODE: &str = "sig-mode";
+    pub const BANDWIDTH: &str = "bandwidth";
+    pub const STBC: &str = "stbc";
+    pub const DEDUP: &str = "dedup";
+    pub const BSSID: &str = "bssid";
+    pub const SSID: &str = "ssid";
+    pub const REPLIES: &str = "replies";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// `--name`
+    Flag,
+    /// `--name=value`
+    Value,
+    /// A bare word before any `--` argument, matched to the positional
+    /// arguments in schema order.
+    Positional,
+}
+
+#[derive(Debug)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub help: &'static str,
+}
+
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub args: &'static [ArgSpec],
+    pub summary: &'static str,
+}
+
+impl CommandSpec {
+    pub fn arg(&self, name: &str) -> Option<&'static ArgSpec> {
+        self.args.iter().find(|a| a.name == name)
+    }
+}
+
+const fn flag(name: &'static str, help: &'static str) -> ArgSpec {
+    ArgSpec {
+        name,
+        kind: ArgKind::Flag,
+        help,
+    }
+}
+
+const fn value(name: &'static str, help: &'static str) -> ArgSpec {
+    ArgSpec {
+        name,
+        kind: ArgKind::Value,
+        help,
+    }
+}
+
+const fn positional(name: &'static str, help: &'static str) -> ArgSpec {
+    ArgSpec {
+        name,
+        kind: ArgKind::Positional,
+        help,
+    }
+}
+
+/// Every command understood by the firmware CLI.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: name::SET_TRAFFIC,
+        args: &[
+            flag(arg::ENABLE, "Enable traffic generation"),
+            value(arg::TYPE, "Traffic type: icmp|udp"),
+            value(arg::INTERVAL_MS, "Traffic interval in milliseconds"),
+        ],
+        summary: "Configure traffic-related parameters",
+    },
+    CommandSpec {
+        name: name::SET_NETWORK,
+        args: &[value(
+            arg::ARCH,
+            "Network architecture: rsta|rapsta|apsta|sniff",
+        )],
+        summary: "Configure network architecture settings",
+    },
+    CommandSpec {
+        name: name::SET_CSI,
+        args: &[
+            flag(arg::DISABLE_LLTF, "Disable LLTF"),
+            flag(arg::DISABLE_HTLTF, "Disable HTLTF"),
+            flag(arg::DISABLE_STBC_HTLTF, "Disable STBC HTLTF"),
+            flag(arg::DISABLE_LTF_MERGE, "Disable LTF Merge"),
+            flag(arg::DISABLE_CSI, "Disable acquisition of CSI (ESP32-C6)"),
+            flag(
+                arg::DISABLE_CSI_LEGACY,
+                "Disable L-LTF for 11g PPDUs (ESP32-C6)",
+            ),
+            flag(
+                arg::DISABLE_CSI_HT20,
+                "Disable HT-LTF for HT20 PPDUs (ESP32-C6)",
+            ),
+            flag(
+                arg::DISABLE_CSI_HT40,
+                "Disable HT-LTF for HT40 PPDUs (ESP32-C6)",
+            ),
+            flag(
+                arg::DISABLE_CSI_SU,
+                "Disable HE-LTF for HE20 SU PPDUs (ESP32-C6)",
+            ),
+            flag(
+                arg::DISABLE_CSI_MU,
+                "Disable HE-LTF for HE20 MU PPDUs (ESP32-C6)",
+            ),
+            flag(
+                arg::DISABLE_CSI_DCM,
+                "Disable HE-LTF for HE20 DCM PPDUs (ESP32-C6)",
+            ),
+            flag(
+                arg::DISABLE_CSI_BEAMFORMED,
+                "Disable HE-LTF for beamformed HE20 PPDUs (ESP32-C6)",
+            ),
+            value(
+                arg::CSI_HE_STBC,
+                "HE-LTF selection for STBC HE PPDUs, 0-2 (ESP32-C6)",
+            ),
+            value(arg::VAL_SCALE_CFG, "CSI value scaling, 0-3 (ESP32-C6)"),
+        ],
+        summary: "Configure CSI feature flags",
+    },
+    CommandSpec {
+        name: name::SET_WIFI,
+        args: &[
+            value(arg::MODE, "Operation mode: ap|station|sniffer|ap-station"),
+            value(arg::MAX_CONNECTIONS, "Maximum number of AP connections"),
+            flag(arg::HIDE_SSID, "Hide the AP SSID"),
+            value(arg::AP_SSID, "SSID of the AP"),
+            value(arg::AP_PASSWORD, "Password of the AP"),
+            value(arg::STA_SSID, "SSID the station connects to"),
+            value(arg::STA_PASSWORD, "Password the station connects with"),
+            value(arg::CHANNEL, "Channel 1-14, 0 to leave it to the driver"),
+            value(arg::SECONDARY, "Secondary channel: none|above|below"),
+            value(arg::COUNTRY, "Country code the channel must be allowed in"),
+            value(
+                arg::HOP,
+                "Comma separated channels a sniffer cycles through, or none",
+            ),
+            value(
+                arg::DWELL_MS,
+                "Time spent on each hop channel in milliseconds",
+            ),
+        ],
+        summary: "Configure WiFi settings",
+    },
+    CommandSpec {
+        name: name::SET_OUTPUT,
+        args: &[value(arg::FORMAT, "Output format: text|csv")],
+        summary: "Configure how CSI records are printed",
+    },
+    CommandSpec {
+        name: name::START,
+        args: &[
+            value(arg::DURATION, "Collection duration in seconds"),
+            value(arg::COUNT, "Stop after this many CSI records"),
+        ],
+        summary: "Start the CSI collection process",
+    },
+    CommandSpec {
+        name: name::STOP,
+        args: &[],
+        summary: "Stop an ongoing CSI collection process",
+    },
+    CommandSpec {
+        name: name::SCHEDULE,
+        args: &[
+            positional(arg::ACTION, "show|clear, or leave out to set a schedule"),
+            value(arg::START_IN, "Seconds until the first window"),
+            value(arg::EVERY, "Seconds between window starts"),
+            value(arg::DURATION, "Window duration in seconds"),
+            value(arg::COUNT, "Number of windows, unlimited if left out"),
+        ],
+        summary: "Schedule periodic collection windows",
+    },
+    CommandSpec {
+        name: name::PAUSE,
+        args: &[flag(arg::TRAFFIC, "Pause traffic generation as well")],
+        summary: "Pause CSI output of the ongoing collection",
+    },
+    CommandSpec {
+        name: name::RESUME,
+        args: &[],
+        summary: "Resume a paused collection",
+    },
+    CommandSpec {
+        name: name::STATUS,
+        args: &[],
+        summary: "Display the collection state",
+    },
+    CommandSpec {
+        name: name::SET_TRIGGER,
+        args: &[
+            flag(arg::OFF, "Disarm the trigger"),
+            value(arg::RSSI, "Fire on a frame at or above this RSSI in dBm"),
+            value(arg::MAC, "Fire on the first frame from this MAC"),
+            value(
+                arg::VARIANCE,
+                "Fire once the amplitude variance reaches this level",
+            ),
+            value(arg::PRE, "Records kept from before the trigger"),
+            value(arg::POST, "Seconds collected after the trigger"),
+        ],
+        summary: "Arm triggered capture for the next collection",
+    },
+    CommandSpec {
+        name: name::TRIGGER,
+        args: &[],
+        summary: "Fire the trigger of the ongoing collection",
+    },
+    CommandSpec {
+        name: name::SET_GPIO,
+        args: &[
+            flag(arg::OFF, "Stop watching both pins"),
+            value(arg::START_PIN, "Pin starting and stopping collection"),
+            value(arg::EVENT_PIN, "Pin whose edges are reported as events"),
+            value(arg::POLARITY, "Active level: high|low"),
+            value(arg::DEBOUNCE_MS, "Debounce time in milliseconds"),
+        ],
+        summary: "Configure the GPIO start/stop and event inputs",
+    },
+    CommandSpec {
+        name: name::MARK,
+        args: &[positional(arg::LABEL, "Annotation to insert")],
+        summary: "Insert an annotation into the stream",
+    },
+    CommandSpec {
+        name: name::LABEL,
+        args: &[
+            positional(arg::ACTION, "set|clear, or leave out to show the label"),
+            positional(arg::LABEL, "Label to set"),
+        ],
+        summary: "Set the label of the records that follow",
+    },
+    CommandSpec {
+        name: name::SET_HEADLESS,
+        args: &[
+            flag(arg::ENABLE, "Run headless from the next boot"),
+            flag(arg::DISABLE, "Start with the CLI from the next boot"),
+            value(arg::SINK, "Output while headless: serial|flash"),
+            value(
+                arg::DURATION,
+                "Duration of the collection started at boot, 0 for a week",
+            ),
+        ],
+        summary: "Configure headless operation, stored in flash",
+    },
+    CommandSpec {
+        name: name::LOG,
+        args: &[positional(arg::ACTION, "show|clear, show if left out")],
+        summary: "Read back or clear the flash log",
+    },
+    CommandSpec {
+        name: name::SET_FILTER,
+        args: &[
+            flag(arg::OFF, "Let every frame through"),
+            value(
+                arg::ALLOW_MAC,
+                "Only keep these comma separated MAC patterns, or none",
+            ),
+            value(
+                arg::DENY_MAC,
+                "Drop these comma separated MAC patterns, or none",
+            ),
+            value(arg::MIN_RSSI, "Drop frames below this RSSI in dBm, or none"),
+            value(arg::MAX_RSSI, "Drop frames above this RSSI in dBm, or none"),
+            value(
+                arg::FRAME_TYPES,
+                "Only keep these comma separated frame types or subtypes, or all",
+            ),
+            value(arg::MAX_RATE, "Frames per second let through in total"),
+            value(
+                arg::MAX_RATE_PER_MAC,
+                "Frames per second let through from each transmitter",
+            ),
+            value(
+                arg::SIG_MODE,
+                "Only keep these comma separated PHYs (legacy, ht, he), or any",
+            ),
+            value(
+                arg::BANDWIDTH,
+                "Only keep these comma separated bandwidths in MHz, or any",
+            ),
+            value(arg::STBC, "Keep frames sent with STBC: any, on or off"),
+            value(
+                arg::DEDUP,
+                "Drop retransmissions among this many recent frames",
+            ),
+            value(arg::BSSID, "Only keep frames of this BSS"),
+            value(
+                arg::SSID,
+                "Only keep frames of the BSS advertising this SSID, _ for spaces",
+            ),
+            value(
+                arg::REPLIES,
+                "Only keep frames sent to this device by the traffic target: on or off",
+            ),
+        ],
+        summary: "Filter frames before output",
+    },
+    CommandSpec {
+        name: name::SHOW_CONFIG,
+        args: &[],
+        summary: "Display the current configuration settings",
+    },
+    CommandSpec {
+        name: name::RESET_CONFIG,
+        args: &[],
+        summary: "Reset all configurations to their default values",
+    },
+];
+
+/// Looks up a command by name.
+pub fn spec(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+keyword_enum! {
+    pub enum TrafficKind {
+        Icmp => "icmp",
+        Udp => "udp",
+    }
+}
+
+keyword_enum! {
+    pub enum NetArch {
+        RouterStation => "rsta",
+        RouterAccessPointStation => "rapsta",
+        AccessPointStation => "apsta",
+        Sniffer => "sniff",
+    }
+}
+
+keyword_enum! {
+    pub enum WifiMode {
+        AccessPoint => "ap",
+        Station => "station",
+        Sniffer => "sniffer",
+        AccessPointStation => "ap-station",
+    }
+}
+
+keyword_enum! {
+    /// `set-csi` flags disabling part of the CSI acquisition.
+    pub enum CsiFlag {
+        DisableLltf => "disable-lltf",
+        DisableHtltf => "disable-htltf",
+        DisableStbcHtltf => "disable-stbc-htltf",
+        DisableLtfMerge => "disable-ltf-merge",
+        DisableCsi => "disable-csi",
+        DisableCsiLegacy => "disable-csi-legacy",
+        DisableCsiHt20 => "disable-csi-ht20",
+        DisableCsiHt40 => "disable-csi-ht40",
+        DisableCsiSu => "disable-csi-su",
+        DisableCsiMu => "disable-csi-mu",
+        DisableCsiDcm => "disable-csi-dcm",
+        DisableCsiBeamformed => "disable-csi-beamformed",
+    }
+}
+
+keyword_enum! {
+    pub enum ScheduleAction {
+        Show => "show",
+        Clear => "clear",
+    }
+}
+
+keyword_enum! {
+    pub enum LogAction {
+        Show => "show",
+        Clear => "clear",
+    }
+}
+
+keyword_enum! {
+    pub enum LabelAction {
+        Set => "set",
+        Clear => "clear",
+    }
+}
+
+keyword_enum! {
+    /// A setting turned on or off by a value rather than a flag.
+    pub enum Switch {
+        On => "on",
+        Off => "off",
+    }
+}
+
+impl From<Switch> for bool {
+    fn from(switch: Switch) -> bool {
+        switch == Switch::On
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetTraffic {
+    pub enable: bool,
+    pub kind: Option<TrafficKind>,
+    pub interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetCsi {
+    pub disable: heapless::Vec<CsiFlag, 12>,
+    pub he_stbc: Option<u32>,
+    pub val_scale_cfg: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetWifi {
+    pub mode: Option<WifiMode>,
+    pub max_connections: Option<u16>,
+    pub hide_ssid: bool,
+    pub ap_ssid: Option<String<32>>,
+    pub ap_password: Option<String<64>>,
+    pub sta_ssid: Option<String<32>>,
+    pub sta_password: Option<String<64>>,
+    /// `0` leaves the channel to the driver.
+    pub channel: Option<u8>,
+    pub secondary: Option<Secondary>,
+    pub country: Option<Country>,
+    pub hop: Option<ChannelList>,
+    pub dwell_ms: Option<u64>,
+}
+
+/// `set-trigger` arms the trigger with the given conditions unless `off` is
+/// set. Arguments left out keep their previous values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SetTrigger {
+    pub off: bool,
+    pub min_rssi: Option<i8>,
+    pub mac: Option<MacAddr>,
+    pub min_variance: Option<f32>,
+    pub pre_records: Option<usize>,
+    pub post_s: Option<u64>,
+}
+
+impl From<&TriggerConfig> for SetTrigger {
+    /// The arguments arming the trigger with `config`.
+    fn from(config: &TriggerConfig) -> Self {
+        Self {
+            off: false,
+            min_rssi: config.min_rssi,
+            mac: config.mac,
+            min_variance: config.min_variance,
+            pre_records: Some(config.pre_records),
+            post_s: Some(config.post_s),
+        }
+    }
+}
+
+/// `set-gpio` stops watching both pins if `off` is set. Arguments left out
+/// keep their previous values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetGpio {
+    pub off: bool,
+    pub start_pin: Option<u8>,
+    pub event_pin: Option<u8>,
+    pub polarity: Option<Polarity>,
+    pub debounce_ms: Option<u64>,
+}
+
+impl From<&GpioConfig> for SetGpio {
+    /// The arguments watching the pins of `config`.
+    fn from(config: &GpioConfig) -> Self {
+        Self {
+            off: false,
+            start_pin: config.start_pin,
+            event_pin: config.event_pin,
+            polarity: Some(config.polarity),
+            debounce_ms: Some(config.debounce_ms),
+        }
+    }
+}
+
+/// `set-headless` stores its settings in flash, where they apply from the
+/// next boot. Arguments left out keep their previous values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetHeadless {
+    pub enable: bool,
+    pub disable: bool,
+    pub sink: Option<Sink>,
+    /// `0` clears the duration.
+    pub duration_s: Option<u64>,
+}
+
+/// `set-filter` lets every frame through if `off` is set. Arguments left out
+/// keep their previous values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetFilter {
+    pub off: bool,
+    pub allow_mac: Option<MacList>,
+    pub deny_mac: Option<MacList>,
+    pub min_rssi: Option<RssiBound>,
+    pub max_rssi: Option<RssiBound>,
+    pub frame_types: Option<FrameTypes>,
+    pub max_rate: Option<u32>,
+    pub max_rate_per_mac: Option<u32>,
+    pub sig_mode: Option<SigModes>,
+    pub bandwidth: Option<Bandwidths>,
+    pub stbc: Option<Stbc>,
+    pub dedup: Option<u8>,
+    pub bssid: Option<MacAddr>,
+    pub ssid: Option<String<32>>,
+    pub replies: Option<Switch>,
+}
+
+impl From<&FilterConfig> for SetFilter {
+    /// The arguments setting up `config` from the defaults, leaving out the
+    /// stages that let every frame through.
+    fn from(config: &FilterConfig) -> Self {
+        Self {
+            off: false,
+            allow_mac: (!config.allow_mac.is_empty()).then(|| config.allow_mac.clone()),
+            deny_mac: (!config.deny_mac.is_empty()).then(|| config.deny_mac.clone()),
+            min_rssi: config.min_rssi.map(|rssi| RssiBound(Some(rssi))),
+            max_rssi: config.max_rssi.map(|rssi| RssiBound(Some(rssi))),
+            frame_types: (!config.frame_types.is_empty()).then(|| config.frame_types.clone()),
+            max_rate: config.max_rate,
+            max_rate_per_mac: config.max_rate_per_mac,
+            sig_mode: (!config.sig_mode.is_empty()).then(|| config.sig_mode.clone()),
+            bandwidth: (!config.bandwidth.is_empty()).then(|| config.bandwidth.clone()),
+            stbc: (config.stbc != Stbc::Any).then_some(config.stbc),
+            dedup: config.dedup,
+            bssid: config.bssid,
+            ssid: config.ssid.clone(),
+            replies: config.replies.then_some(Switch::On),
+        }
+    }
+}
+
+/// `schedule` sets a schedule unless an action is given.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScheduleArgs {
+    pub action: Option<ScheduleAction>,
+    pub start_in_s: Option<u64>,
+    pub every_s: Option<u64>,
+    pub duration_s: Option<u64>,
+    pub count: Option<u32>,
+}
+
+/// A typed CLI command. `Display` produces the command line to send to the
+/// device and `FromStr` parses one.
+// Boxing is not an option without an allocator on the firmware side.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    SetTraffic(SetTraffic),
+    SetNetwork {
+        arch: Option<NetArch>,
+    },
+    SetCsi(SetCsi),
+    SetWifi(SetWifi),
+    SetOutput {
+        format: Option<OutputFormat>,
+    },
+    /// Collects until `duration_s` elapses or `count` records have been
+    /// emitted, whichever comes first.
+    Start {
+        duration_s: Option<u64>,
+        count: Option<u32>,
+    },
+    Stop,
+    Schedule(ScheduleArgs),
+    /// Suppresses CSI output, and traffic generation if `traffic` is set,
+    /// without ending the collection run.
+    Pause {
+        traffic: bool,
+    },
+    Resume,
+    Status,
+    SetTrigger(SetTrigger),
+    Trigger,
+    SetGpio(SetGpio),
+    Mark {
+        label: Label,
+    },
+    /// Shows the current label unless an action is given. `label` goes with
+    /// [`LabelAction::Set`].
+    Label {
+        action: Option<LabelAction>,
+        label: Option<Label>,
+    },
+    SetHeadless(SetHeadless),
+    /// Shows the flash log unless told to clear it.
+    Log {
+        action: Option<LogAction>,
+    },
+    SetFilter(SetFilter),
+    ShowConfig,
+    ResetConfig,
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::SetTraffic(_) => name::SET_TRAFFIC,
+            Command::SetNetwork { .. } => name::SET_NETWORK,
+            Command::SetCsi(_) => name::SET_CSI,
+            Command::SetWifi(_) => name::SET_WIFI,
+            Command::SetOutput { .. } => name::SET_OUTPUT,
+            Command::Start { .. } => name::START,
+            Command::Stop => name::STOP,
+            Command::Schedule(_) => name::SCHEDULE,
+            Command::Pause { .. } => name::PAUSE,
+            Command::Resume => name::RESUME,
+            Command::Status => name::STATUS,
+            Command::SetTrigger(_) => name::SET_TRIGGER,
+            Command::Trigger => name::TRIGGER,
+            Command::SetGpio(_) => name::SET_GPIO,
+            Command::Mark { .. } => name::MARK,
+            Command::Label { .. } => name::LABEL,
+            Command::SetHeadless(_) => name::SET_HEADLESS,
+            Command::Log { .. } => name::LOG,
+            Command::SetFilter(_) => name::SET_FILTER,
+            Command::ShowConfig => name::SHOW_CONFIG,
+            Command::ResetConfig => name::RESET_CONFIG,
+        }
+    }
+
+    // Command without any arguments, the starting point for parsing.
+    fn bare(name: &str) -> Option<Command> {
+        Some(match name {
+            name::SET_TRAFFIC => Command::SetTraffic(SetTraffic::default()),
+            name::SET_NETWORK => Command::SetNetwork { arch: None },
+            name::SET_CSI => Command::SetCsi(SetCsi::default()),
+            name::SET_WIFI => Command::SetWifi(SetWifi::default()),
+            name::SET_OUTPUT => Command::SetOutput { format: None },
+            name::START => Command::Start {
+                duration_s: None,
+                count: None,
+            },
+            name::STOP => Command::Stop,
+            name::SCHEDULE => Command::Schedule(ScheduleArgs::default()),
+            name::PAUSE => Command::Pause { traffic: false },
+            name::RESUME => Command::Resume,
+            name::STATUS => Command::Status,
+            name::SET_TRIGGER => Command::SetTrigger(SetTrigger::default()),
+            name::TRIGGER => Command::Trigger,
+            name::SET_GPIO => Command::SetGpio(SetGpio::default()),
+            name::MARK => Command::Mark {
+                label: Label::new(),
+            },
+            name::LABEL => Command::Label {
+                action: None,
+                label: None,
+            },
+            name::SET_HEADLESS => Command::SetHeadless(SetHeadless::default()),
+            name::LOG => Command::Log { action: None },
+            name::SET_FILTER => Command::SetFilter(SetFilter::default()),
+            name::SHOW_CONFIG => Command::ShowConfig,
+            name::RESET_CONFIG => Command::ResetConfig,
+            _ => return None,
+        })
+    }
+
+    // Applies one argument already validated against the command's schema.
+    fn apply(&mut self, arg: &'static str, value: Option<&str>) -> Result<(), ParseError> {
+        let v = value.unwrap_or_default();
+        match self {
+            Command::SetTraffic(c) => match arg {
+                arg::ENABLE => c.enable = true,
+                arg::TYPE => c.kind = Some(parse(arg, v)?),
+                arg::INTERVAL_MS => c.interval_ms = Some(parse(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::SetNetwork { arch } => *arch = Some(parse(arg, v)?),
+            Command::SetCsi(c) => match arg {
+                arg::CSI_HE_STBC => c.he_stbc = Some(parse(arg, v)?),
+                arg::VAL_SCALE_CFG => c.val_scale_cfg = Some(parse(arg, v)?),
+                _ => c
+                    .disable
+                    .push(parse(arg, arg)?)
+                    .map_err(|_| ParseError::InvalidValue(arg))?,
+            },
+            Command::SetWifi(c) => match arg {
+                arg::MODE => c.mode = Some(parse(arg, v)?),
+                arg::MAX_CONNECTIONS => c.max_connections = Some(parse(arg, v)?),
+                arg::HIDE_SSID => c.hide_ssid = true,
+                arg::AP_SSID => c.ap_ssid = Some(unescape(arg, v)?),
+                arg::AP_PASSWORD => c.ap_password = Some(unescape(arg, v)?),
+                arg::STA_SSID => c.sta_ssid = Some(unescape(arg, v)?),
+                arg::STA_PASSWORD => c.sta_password = Some(unescape(arg, v)?),
+                arg::CHANNEL => c.channel = Some(parse(arg, v)?),
+                arg::SECONDARY => c.secondary = Some(parse(arg, v)?),
+                arg::COUNTRY => c.country = Some(parse(arg, v)?),
+                arg::HOP => c.hop = Some(parse(arg, v)?),
+                arg::DWELL_MS => c.dwell_ms = Some(parse(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::SetOutput { format } => *format = Some(parse(arg, v)?),
+            Command::Start { duration_s, count } => match arg {
+                arg::DURATION => *duration_s = Some(parse(arg, v)?),
+                arg::COUNT => *count = Some(parse(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::Schedule(c) => match arg {
+                arg::ACTION => c.action = Some(parse(arg, v)?),
+                arg::START_IN => c.start_in_s = Some(parse(arg, v)?),
+                arg::EVERY => c.every_s = Some(parse(arg, v)?),
+                arg::DURATION => c.duration_s = Some(parse(arg, v)?),
+                arg::COUNT => c.count = Some(parse(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::Pause { traffic } => *traffic = true,
+            Command::SetTrigger(c) => match arg {
+                arg::OFF => c.off = true,
+                arg::RSSI => c.min_rssi = Some(parse(arg, v)?),
+                arg::MAC => c.mac = Some(parse(arg, v)?),
+                arg::VARIANCE => c.min_variance = Some(parse(arg, v)?),
+                arg::PRE => c.pre_records = Some(parse(arg, v)?),
+                arg::POST => c.post_s = Some(parse(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::SetGpio(c) => match arg {
+                arg::OFF => c.off = true,
+                arg::START_PIN => c.start_pin = Some(parse(arg, v)?),
+                arg::EVENT_PIN => c.event_pin = Some(parse(arg, v)?),
+                arg::POLARITY => c.polarity = Some(parse(arg, v)?),
+                arg::DEBOUNCE_MS => c.debounce_ms = Some(parse(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::Mark { label } => *label = parse_label(arg, v)?,
+            Command::Label { action, label } => match arg {
+                arg::ACTION => *action = Some(parse(arg, v)?),
+                arg::LABEL => *label = Some(parse_label(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::SetHeadless(c) => match arg {
+                arg::ENABLE => c.enable = true,
+                arg::DISABLE => c.disable = true,
+                arg::SINK => c.sink = Some(parse(arg, v)?),
+                arg::DURATION => c.duration_s = Some(parse(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::Log { action } => *action = Some(parse(arg, v)?),
+            Command::SetFilter(c) => match arg {
+                arg::OFF => c.off = true,
+                arg::ALLOW_MAC => c.allow_mac = Some(parse(arg, v)?),
+                arg::DENY_MAC => c.deny_mac = Some(parse(arg, v)?),
+                arg::MIN_RSSI => c.min_rssi = Some(parse(arg, v)?),
+                arg::MAX_RSSI => c.max_rssi = Some(parse(arg, v)?),
+                arg::FRAME_TYPES => c.frame_types = Some(parse(arg, v)?),
+                arg::MAX_RATE => c.max_rate = Some(parse(arg, v)?),
+                arg::MAX_RATE_PER_MAC => c.max_rate_per_mac = Some(parse(arg, v)?),
+                arg::SIG_MODE => c.sig_mode = Some(parse(arg, v)?),
+                arg::BANDWIDTH => c.bandwidth = Some(parse(arg, v)?),
+                arg::STBC => c.stbc = Some(parse(arg, v)?),
+                arg::DEDUP => c.dedup = Some(parse(arg, v)?),
+                arg::BSSID => c.bssid = Some(parse(arg, v)?),
+                arg::SSID => c.ssid = Some(unescape(arg, v)?),
+                arg::REPLIES => c.replies = Some(parse(arg, v)?),
+                _ => unreachable!(),
+            },
+            Command::Stop
+            | Command::Resume
+            | Command::Status
+            | Command::Trigger
+            | Command::ShowConfig
+            | Command::ResetConfig => {
+                unreachable!()
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse<T: FromStr>(arg: &'static str, value: &str) -> Result<T, ParseError> {
+    value.parse().map_err(|_| ParseError::InvalidValue(arg))
+}
+
+fn parse_label(arg: &'static str, value: &str) -> Result<Label, ParseError> {
+    match is_valid_label(value) {
+        true => parse(arg, value),
+        false => Err(ParseError::InvalidValue(arg)),
+    }
+}
+
+// SSIDs and passwords are sent with spaces replaced by underscores.
+fn unescape<const N: usize>(arg: &'static str, value: &str) -> Result<String<N>, ParseError> {
+    let mut s = String::new();
+    for c in value.chars() {
+        s.push(if c == '_' { ' ' } else { c })
+            .map_err(|_| ParseError::InvalidValue(arg))?;
+    }
+    Ok(s)
+}
+
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            fmt::Write::write_char(f, if c == ' ' { '_' } else { c })?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())?;
+        // Positional arguments are written first, then flags, then values,
+        // each in schema order.
+        match self {
+            Command::SetTraffic(c) => {
+                write_flag(f, arg::ENABLE, c.enable)?;
+                write_value(f, arg::TYPE, c.kind)?;
+                write_value(f, arg::INTERVAL_MS, c.interval_ms)
+            }
+            Command::SetNetwork { arch } => write_value(f, arg::ARCH, *arch),
+            Command::SetCsi(c) => {
+                for flag in c.disable.iter() {
+                    write_flag(f, flag.as_str(), true)?;
+                }
+                write_value(f, arg::CSI_HE_STBC, c.he_stbc)?;
+                write_value(f, arg::VAL_SCALE_CFG, c.val_scale_cfg)
+            }
+            Command::SetWifi(c) => {
+                write_flag(f, arg::HIDE_SSID, c.hide_ssid)?;
+                write_value(f, arg::MODE, c.mode)?;
+                write_value(f, arg::MAX_CONNECTIONS, c.max_connections)?;
+                write_value(f, arg::AP_SSID, c.ap_ssid.as_deref().map(Escaped))?;
+                write_value(f, arg::AP_PASSWORD, c.ap_password.as_deref().map(Escaped))?;
+                write_value(f, arg::STA_SSID, c.sta_ssid.as_deref().map(Escaped))?;
+                write_value(f, arg::STA_PASSWORD, c.sta_password.as_deref().map(Escaped))?;
+                write_value(f, arg::CHANNEL, c.channel)?;
+                write_value(f, arg::SECONDARY, c.secondary)?;
+                write_value(f, arg::COUNTRY, c.country)?;
+                write_value(f, arg::HOP, c.hop.as_ref())?;
+                write_value(f, arg::DWELL_MS, c.dwell_ms)
+            }
+            Command::SetOutput { format } => write_value(f, arg::FORMAT, *format),
+            Command::Start { duration_s, count } => {
+                write_value(f, arg::DURATION, *duration_s)?;
+                write_value(f, arg::COUNT, *count)
+            }
+            Command::Schedule(c) => {
+                if let Some(action) = c.action {
+                    write!(f, " {}", action)?;
+                }
+                write_value(f, arg::START_IN, c.start_in_s)?;
+                write_value(f, arg::EVERY, c.every_s)?;
+                write_value(f, arg::DURATION, c.duration_s)?;
+                write_value(f, arg::COUNT, c.count)
+            }
+            Command::Pause { traffic } => write_flag(f, arg::TRAFFIC, *traffic),
+            Command::SetTrigger(c) => {
+                write_flag(f, arg::OFF, c.off)?;
+                write_value(f, arg::RSSI, c.min_rssi)?;
+                write_value(f, arg::MAC, c.mac)?;
+                write_value(f, arg::VARIANCE, c.min_variance)?;
+                write_value(f, arg::PRE, c.pre_records)?;
+                write_value(f, arg::POST, c.post_s)
+            }
+            Command::SetGpio(c) => {
+                write_flag(f, arg::OFF, c.off)?;
+                write_value(f, arg::START_PIN, c.start_pin)?;
+                write_value(f, arg::EVENT_PIN, c.event_pin)?;
+                write_value(f, arg::POLARITY, c.polarity)?;
+                write_value(f, arg::DEBOUNCE_MS, c.debounce_ms)
+            }
+            Command::Mark { label } => write!(f, " {}", label),
+            Command::Label { action, label } => {
+                if let Some(action) = action {
+                    write!(f, " {}", action)?;
+                }
+                match label {
+                    Some(label) => write!(f, " {}", label),
+                    None => Ok(()),
+                }
+            }
+            Command::SetHeadless(c) => {
+                write_flag(f, arg::ENABLE, c.enable)?;
+                write_flag(f, arg::DISABLE, c.disable)?;
+                write_value(f, arg::SINK, c.sink)?;
+                write_value(f, arg::DURATION, c.duration_s)
+            }
+            Command::Log { action } => match action {
+                Some(action) => write!(f, " {}", action),
+                None => Ok(()),
+            },
+            Command::SetFilter(c) => {
+                write_flag(f, arg::OFF, c.off)?;
+                write_value(f, arg::ALLOW_MAC, c.allow_mac.as_ref())?;
+                write_value(f, arg::DENY_MAC, c.deny_mac.as_ref())?;
+                write_value(f, arg::MIN_RSSI, c.min_rssi)?;
+                write_value(f, arg::MAX_RSSI, c.max_rssi)?;
+                write_value(f, arg::FRAME_TYPES, c.frame_types.as_ref())?;
+                write_value(f, arg::MAX_RATE, c.max_rate)?;
+                write_value(f, arg::MAX_RATE_PER_MAC, c.max_rate_per_mac)?;
+                write_value(f, arg::SIG_MODE, c.sig_mode.as_ref())?;
+                write_value(f, arg::BANDWIDTH, c.bandwidth.as_ref())?;
+                write_value(f, arg::STBC, c.stbc)?;
+                write_value(f, arg::DEDUP, c.dedup)?;
+                write_value(f, arg::BSSID, c.bssid)?;
+                write_value(f, arg::SSID, c.ssid.as_deref().map(Escaped))?;
+                write_value(f, arg::REPLIES, c.replies)
+            }
+            Command::Stop
+            | Command::Resume
+            | Command::Status
+            | Command::Trigger
+            | Command::ShowConfig
+            | Command::ResetConfig => Ok(()),
+        }
+    }
+}
+
+fn write_flag(f: &mut fmt::Formatter<'_>, name: &str, set: bool) -> fmt::Result {
+    match set {
+        true => write!(f, " --{}", name),
+        false => Ok(()),
+    }
+}
+
+fn write_value<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    value: Option<T>,
+) -> fmt::Result {
+    match value {
+        Some(v) => write!(f, " --{}={}", name, v),
+        None => Ok(()),
+    }
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut tokens = line.split_whitespace();
+        let name = tokens.next().ok_or(ParseError::Empty)?;
+        let spec = spec(name).ok_or(ParseError::UnknownCommand)?;
+        let mut command = Command::bare(spec.name).ok_or(ParseError::UnknownCommand)?;
+        let mut positionals = spec.args.iter().filter(|a| a.kind == ArgKind::Positional);
+
+        for token in tokens {
+            let Some(token) = token.strip_prefix("--") else {
+                let arg = positionals.next().ok_or(ParseError::UnknownArgument)?;
+                command.apply(arg.name, Some(token))?;
+                continue;
+            };
+            let (name, value) = match token.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (token, None),
+            };
+            let arg = spec.arg(name).ok_or(ParseError::UnknownArgument)?;
+            match (arg.kind, value) {
+                (ArgKind::Flag, Some(_)) => return Err(ParseError::UnexpectedValue(arg.name)),
+                (ArgKind::Value, None) => return Err(ParseError::MissingValue(arg.name)),
+                (ArgKind::Positional, _) => return Err(ParseError::UnknownArgument),
+                _ => command.apply(arg.name, value)?,
+            }
+        }
+        Ok(command)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    UnknownCommand,
+    UnknownArgument,
+    /// A `--name=value` argument was given without a value.
+    MissingValue(&'static str),
+    /// A flag was given a value.
+    UnexpectedValue(&'static str),
+    InvalidValue(&'static str),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => f.write_str("empty command"),
+            ParseError::UnknownCommand => f.write_str("unknown command"),
+            ParseError::UnknownArgument => f.write_str("unknown argument"),
+            ParseError::MissingValue(arg) => write!(f, "--{} requires a value", arg),
+            ParseError::UnexpectedValue(arg) => write!(f, "--{} does not take a value", arg),
+            ParseError::InvalidValue(arg) => write!(f, "invalid value for --{}", arg),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
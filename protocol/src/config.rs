@@ -0,0 +1,141 @@
+//! Keys of the `show-config` dump.
+//!
+//! `show-config` prints every setting on its own `key: value` line, using the
+//! keywords accepted on the command line for enumerated values. Host tools read
+//! the dump back to verify a configuration or to snapshot it.
+
+use crate::command::CsiFlag;
+
+/// Maximum length of a CLI line accepted by the firmware, including the
+/// command name. Host tools split longer configurations into several commands.
+pub const MAX_LINE_LEN: usize = 128;
+
+pub mod key {
+    // Build
+    pub const CHIP: &str = "Chip";
+    /// Space separated list of the cargo features the firmware was built with.
+    pub const FEATURES: &str = "Features";
+    pub const FIRMWARE_VERSION: &str = "Firmware Version";
+    pub const GIT_HASH: &str = "Git Hash";
+    // Traffic
+    pub const TRAFFIC_ENABLED: &str = "Traffic Enabled";
+    pub const TRAFFIC_TYPE: &str = "Traffic Type";
+    /// Value carries an `ms` suffix.
+    pub const TRAFFIC_INTERVAL: &str = "Traffic Interval";
+    pub const NETWORK_ARCH: &str = "Network Architecture";
+    // CSI flags, all chips except the ESP32-C6
+    pub const LLTF_ENABLED: &str = "LLTF Enabled";
+    pub const HTLTF_ENABLED: &str = "HTLTF Enabled";
+    pub const STBC_HTLTF_ENABLED: &str = "STBC HTLTF Enabled";
+    pub const LTF_MERGE_ENABLED: &str = "LTF Merge Enabled";
+    pub const CHANNEL_FILTER_ENABLED: &str = "Channel Filter Enabled";
+    // CSI flags, ESP32-C6
+    pub const ACQUIRE_CSI: &str = "Acquire CSI";
+    pub const ACQUIRE_CSI_LEGACY: &str = "Acquire Legacy CSI";
+    pub const ACQUIRE_CSI_HT20: &str = "Acquire HT20";
+    pub const ACQUIRE_CSI_HT40: &str = "Acquire HT40";
+    pub const ACQUIRE_CSI_SU: &str = "Acquire HE20 SU";
+    pub const ACQUIRE_CSI_MU: &str = "Acquire HE20 MU";
+    pub const ACQUIRE_CSI_DCM: &str = "Acquire HE20 DCM";
+    pub const ACQUIRE_CSI_BEAMFORMED: &str = "Acquire HE20 Beamformed";
+    pub const CSI_HE_STBC: &str = "STBC HE";
+    pub const VAL_SCALE_CFG: &str = "Scale Value";
+    // WiFi
+    pub const WIFI_MODE: &str = "WiFi Operation Mode";
+    /// String values are wrapped in single quotes.
+    pub const STA_SSID: &str = "Station SSID";
+    pub const STA_PASSWORD: &str = "Station Password";
+    pub const AP_SSID: &str = "AP SSID";
+    pub const AP_PASSWORD: &str = "AP Password";
+    pub const AP_SSID_HIDDEN: &str = "AP SSID Hidden";
+    pub const AP_MAX_CONNECTIONS: &str = "AP Max Connections";
+    /// Channel number, or `auto` when left to the driver.
+    pub const WIFI_CHANNEL: &str = "WiFi Channel";
+    pub const WIFI_SECONDARY_CHANNEL: &str = "WiFi Secondary Channel";
+    pub const WIFI_COUNTRY: &str = "WiFi Country";
+    /// Comma separated channels, or `none` when not hopping.
+    pub const WIFI_HOP: &str = "WiFi Hop Channels";
+    /// Value carries an `ms` suffix.
+    pub const WIFI_DWELL: &str = "WiFi Dwell";
+    // Output
+    pub const OUTPUT_FORMAT: &str = "Output Format";
+    // Trigger, conditions that are not set show `none`
+    pub const TRIGGER_ARMED: &str = "Trigger Armed";
+    pub const TRIGGER_RSSI: &str = "Trigger RSSI";
+    pub const TRIGGER_MAC: &str = "Trigger MAC";
+    pub const TRIGGER_VARIANCE: &str = "Trigger Variance";
+    pub const TRIGGER_PRE: &str = "Trigger Pre Records";
+    /// Value carries an `s` suffix.
+    pub const TRIGGER_POST: &str = "Trigger Post Duration";
+    // GPIO, pins that are not set show `none`
+    pub const GPIO_START_PIN: &str = "GPIO Start Pin";
+    pub const GPIO_EVENT_PIN: &str = "GPIO Event Pin";
+    pub const GPIO_POLARITY: &str = "GPIO Polarity";
+    /// Value carries an `ms` suffix.
+    pub const GPIO_DEBOUNCE: &str = "GPIO Debounce";
+    // Headless, as stored in flash
+    pub const HEADLESS_ENABLED: &str = "Headless Enabled";
+    pub const HEADLESS_SINK: &str = "Headless Sink";
+    /// Value carries an `s` suffix, or is `none`.
+    pub const HEADLESS_DURATION: &str = "Headless Duration";
+    // Filter, empty lists and unset bounds show `none`
+    /// Comma separated MAC patterns.
+    pub const FILTER_ALLOW_MAC: &str = "Filter Allow MAC";
+    pub const FILTER_DENY_MAC: &str = "Filter Deny MAC";
+    /// RSSI bounds in dBm, or `none`.
+    pub const FILTER_MIN_RSSI: &str = "Filter Min RSSI";
+    pub const FILTER_MAX_RSSI: &str = "Filter Max RSSI";
+    /// Comma separated frame types, or `all`.
+    pub const FILTER_FRAME_TYPES: &str = "Filter Frame Types";
+    /// Value carries an `fps` suffix, or is `none`.
+    pub const FILTER_MAX_RATE: &str = "Filter Max Rate";
+    /// Value carries an `fps` suffix, or is `none`.
+    pub const FILTER_MAX_RATE_PER_MAC: &str = "Filter Max Rate per MAC";
+    /// Comma separated PHYs, or `any`.
+    pub const FILTER_SIG_MODE: &str = "Filter Sig Mode";
+    /// Comma separated bandwidths in MHz, or `any`.
+    pub const FILTER_BANDWIDTH: &str = "Filter Bandwidth";
+    /// `any`, `on` or `off`.
+    pub const FILTER_STBC: &str = "Filter STBC";
+    /// Frames remembered by the duplicate check, or `none`.
+    pub const FILTER_DEDUP: &str = "Filter Dedup";
+    /// BSSID, or `none`.
+    pub const FILTER_BSSID: &str = "Filter BSSID";
+    /// Wrapped in single quotes, or `none`.
+    pub const FILTER_SSID: &str = "Filter SSID";
+    /// Whether only replies to the generated traffic are kept.
+    pub const FILTER_REPLIES: &str = "Filter Replies";
+}
+
+impl CsiFlag {
+    /// Dump key reflecting this flag and the value it shows once the flag
+    /// has been applied.
+    pub fn dump_entry(&self) -> (&'static str, &'static str) {
+        match self {
+            CsiFlag::DisableLltf => (key::LLTF_ENABLED, "false"),
+            CsiFlag::DisableHtltf => (key::HTLTF_ENABLED, "false"),
+            CsiFlag::DisableStbcHtltf => (key::STBC_HTLTF_ENABLED, "false"),
+            CsiFlag::DisableLtfMerge => (key::LTF_MERGE_ENABLED, "false"),
+            CsiFlag::DisableCsi => (key::ACQUIRE_CSI, "0"),
+            CsiFlag::DisableCsiLegacy => (key::ACQUIRE_CSI_LEGACY, "0"),
+            CsiFlag::DisableCsiHt20 => (key::ACQUIRE_CSI_HT20, "0"),
+            CsiFlag::DisableCsiHt40 => (key::ACQUIRE_CSI_HT40, "0"),
+            CsiFlag::DisableCsiSu => (key::ACQUIRE_CSI_SU, "0"),
+            CsiFlag::DisableCsiMu => (key::ACQUIRE_CSI_MU, "0"),
+            CsiFlag::DisableCsiDcm => (key::ACQUIRE_CSI_DCM, "0"),
+            CsiFlag::DisableCsiBeamformed => (key::ACQUIRE_CSI_BEAMFORMED, "0"),
+        }
+    }
+}
+
+/// Splits a dump line into its key and value, or returns `None` for section
+/// headings and unrelated output.
+pub fn parse_dump_line(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(": ")?;
+    let value = value.trim();
+    let value = value
+        .strip_prefix('\'')
+        .and_then(|v| v.strip_suffix('\''))
+        .unwrap_or(value);
+    Some((key.trim(), value))
+}
@@ -0,0 +1,206 @@
+//! Out-of-band events interleaved with the CSI records.
+//!
+//! Every event is encoded as its [`Event::kind`] followed by `key=value`
+//! fields. Values never contain whitespace, `,` or `=`, so the same field list
+//! works for every [`OutputFormat`](crate::OutputFormat).
+
+use core::fmt;
+use core::str::FromStr;
+
+use heapless::String;
+
+use crate::gpio::Edge;
+use crate::trigger::Cause;
+
+/// Longest label accepted by `mark` and `label set`.
+pub const MAX_LABEL_LEN: usize = 32;
+
+pub type Label = String<MAX_LABEL_LEN>;
+
+/// Whether `label` can be carried by [`Event::Mark`] and [`Event::Label`]:
+/// printable ASCII without `,`, `=` or `"`, so it never breaks a field list,
+/// and not starting with `-`, so it is not taken for an option.
+pub fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= MAX_LABEL_LEN
+        && !label.starts_with('-')
+        && label
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !matches!(b, b',' | b'=' | b'"'))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A collection run started and will last for `duration_s` seconds.
+    Started { duration_s: u64 },
+    /// A collection run ended after emitting `records` CSI records over
+    /// `elapsed_ms` milliseconds.
+    Stopped { records: u32, elapsed_ms: u64 },
+    /// Scheduled window `index` (counted from 0) is about to start.
+    Window { index: u32 },
+    /// CSI output was paused after `records` records, together with traffic
+    /// generation if `traffic` is set.
+    Paused { records: u32, traffic: bool },
+    /// CSI output resumed after a pause of `paused_ms` milliseconds.
+    Resumed { paused_ms: u64 },
+    /// A trigger fired, releasing `buffered` records from before it.
+    Triggered { cause: Cause, buffered: u32 },
+    /// An edge on the event pin, seen `timestamp_us` microseconds after boot.
+    Gpio {
+        pin: u8,
+        edge: Edge,
+        timestamp_us: u64,
+    },
+    /// A one-off annotation, inserted `timestamp_us` microseconds after boot.
+    Mark { label: Label, timestamp_us: u64 },
+    /// Records from here on belong to `label`, until the next label event.
+    /// An empty label ends the labelled segment. Repeated after `started`
+    /// while a label is set.
+    Label { label: Label, timestamp_us: u64 },
+    /// A hopping sniffer switched to `channel`, `timestamp_us` microseconds
+    /// after boot.
+    Hop { channel: u8, timestamp_us: u64 },
+}
+
+impl Event {
+    /// Name identifying the event in the stream.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::Started { .. } => "started",
+            Event::Stopped { .. } => "stopped",
+            Event::Window { .. } => "window",
+            Event::Paused { .. } => "paused",
+            Event::Resumed { .. } => "resumed",
+            Event::Triggered { .. } => "triggered",
+            Event::Gpio { .. } => "gpio",
+            Event::Mark { .. } => "mark",
+            Event::Label { .. } => "label",
+            Event::Hop { .. } => "hop",
+        }
+    }
+
+    /// Calls `f` with the name and value of every field, in encoding order.
+    pub(crate) fn for_each_field(
+        &self,
+        f: &mut dyn FnMut(&str, &dyn fmt::Display) -> fmt::Result,
+    ) -> fmt::Result {
+        match self {
+            Event::Started { duration_s } => f("duration", duration_s),
+            Event::Stopped {
+                records,
+                elapsed_ms,
+            } => {
+                f("records", records)?;
+                f("elapsed_ms", elapsed_ms)
+            }
+            Event::Window { index } => f("index", index),
+            Event::Paused { records, traffic } => {
+                f("records", records)?;
+                f("traffic", traffic)
+            }
+            Event::Resumed { paused_ms } => f("paused_ms", paused_ms),
+            Event::Triggered { cause, buffered } => {
+                f("cause", cause)?;
+                f("buffered", buffered)
+            }
+            Event::Gpio {
+                pin,
+                edge,
+                timestamp_us,
+            } => {
+                f("pin", pin)?;
+                f("edge", edge)?;
+                f("timestamp_us", timestamp_us)
+            }
+            Event::Mark {
+                label,
+                timestamp_us,
+            }
+            | Event::Label {
+                label,
+                timestamp_us,
+            } => {
+                f("label", label)?;
+                f("timestamp_us", timestamp_us)
+            }
+            Event::Hop {
+                channel,
+                timestamp_us,
+            } => {
+                f("channel", channel)?;
+                f("timestamp_us", timestamp_us)
+            }
+        }
+    }
+
+    /// Rebuilds an event from its kind and fields. Returns `None` for unknown
+    /// kinds or missing fields.
+    pub(crate) fn from_fields(kind: &str, fields: Fields<'_>) -> Option<Event> {
+        match kind {
+            "started" => Some(Event::Started {
+                duration_s: fields.get("duration")?,
+            }),
+            "stopped" => Some(Event::Stopped {
+                records: fields.get("records")?,
+                elapsed_ms: fields.get("elapsed_ms")?,
+            }),
+            "window" => Some(Event::Window {
+                index: fields.get("index")?,
+            }),
+            "paused" => Some(Event::Paused {
+                records: fields.get("records")?,
+                traffic: fields.get("traffic")?,
+            }),
+            "resumed" => Some(Event::Resumed {
+                paused_ms: fields.get("paused_ms")?,
+            }),
+            "triggered" => Some(Event::Triggered {
+                cause: fields.get("cause")?,
+                buffered: fields.get("buffered")?,
+            }),
+            "gpio" => Some(Event::Gpio {
+                pin: fields.get("pin")?,
+                edge: fields.get("edge")?,
+                timestamp_us: fields.get("timestamp_us")?,
+            }),
+            "mark" => Some(Event::Mark {
+                label: fields.get("label")?,
+                timestamp_us: fields.get("timestamp_us")?,
+            }),
+            "label" => Some(Event::Label {
+                label: fields.get("label")?,
+                timestamp_us: fields.get("timestamp_us")?,
+            }),
+            "hop" => Some(Event::Hop {
+                channel: fields.get("channel")?,
+                timestamp_us: fields.get("timestamp_us")?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// `key=value` tokens separated by `sep`.
+#[derive(Clone, Copy)]
+pub(crate) struct Fields<'a> {
+    raw: &'a str,
+    sep: char,
+}
+
+impl<'a> Fields<'a> {
+    pub(crate) fn new(raw: &'a str, sep: char) -> Self {
+        Self { raw, sep }
+    }
+
+    pub(crate) fn get_str(&self, key: &str) -> Option<&'a str> {
+        self.raw
+            .split(self.sep)
+            .filter_map(|token| token.trim().split_once('='))
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub(crate) fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get_str(key)?.parse().ok()
+    }
+}
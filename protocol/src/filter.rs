@@ -0,0 +1,595 @@
+//! Filters applied to received frames before they are turned into output.
+//!
+//! [`Filter::check`] runs every stage in turn and counts why frames were
+//! dropped in [`FilterStats`], which `status` reports using the keys in
+//! [`crate::state::key`].
+
+use core::fmt;
+use core::str::FromStr;
+
+use heapless::{String, Vec};
+
+use crate::frame::{
+    advertised_ssid, FrameHeader, FrameTypes, SequenceControl, MAX_SSID_LEN, PROMISCUOUS_MGMT,
+};
+use crate::ppdu::{Bandwidth, Bandwidths, SigMode, SigModes, Stbc};
+use crate::record::{CsiRecord, MacAddr};
+
+/// Patterns accepted in each of the allow and deny lists.
+pub const MAX_MAC_PATTERNS: usize = 8;
+/// Transmitters tracked by the rate limits. Once full, the one heard from
+/// least recently makes room for a new one.
+pub const MAX_SOURCES: usize = 16;
+/// Largest number of frames the duplicate check remembers.
+pub const MAX_DEDUP_WINDOW: usize = 32;
+
+/// A MAC address pattern such as `24:0A:C4:*:*:01`, where `*` matches any
+/// byte. A pattern with fewer than six bytes matches by prefix, so
+/// `24:0A:C4` matches every address with that OUI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MacPattern {
+    bytes: [u8; 6],
+    /// Bytes that must match.
+    fixed: [bool; 6],
+}
+
+impl MacPattern {
+    pub fn matches(&self, mac: &MacAddr) -> bool {
+        (0..6).all(|i| !self.fixed[i] || self.bytes[i] == mac.0[i])
+    }
+}
+
+impl From<MacAddr> for MacPattern {
+    fn from(mac: MacAddr) -> Self {
+        Self {
+            bytes: mac.0,
+            fixed: [true; 6],
+        }
+    }
+}
+
+impl fmt::Display for MacPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Trailing wildcards are written as a prefix.
+        let len = self.fixed.iter().rposition(|f| *f).map_or(1, |i| i + 1);
+        for i in 0..len {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            match self.fixed[i] {
+                true => write!(f, "{:02X}", self.bytes[i])?,
+                false => f.write_str("*")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MacPattern {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pattern = MacPattern::default();
+        let mut parts = s.split(':');
+        for i in 0..6 {
+            let Some(part) = parts.next() else {
+                // Only a pattern with at least one byte can be a prefix.
+                return match i {
+                    0 => Err(()),
+                    _ => Ok(pattern),
+                };
+            };
+            if part == "*" {
+                continue;
+            }
+            if part.len() != 2 {
+                return Err(());
+            }
+            pattern.bytes[i] = u8::from_str_radix(part, 16).map_err(|_| ())?;
+            pattern.fixed[i] = true;
+        }
+        match parts.next() {
+            Some(_) => Err(()),
+            None => Ok(pattern),
+        }
+    }
+}
+
+/// Comma separated [`MacPattern`]s, written as `none` when empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MacList(pub Vec<MacPattern, MAX_MAC_PATTERNS>);
+
+impl MacList {
+    pub fn matches(&self, mac: &MacAddr) -> bool {
+        self.0.iter().any(|p| p.matches(mac))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for MacList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("none");
+        }
+        for (i, pattern) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", pattern)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MacList {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut list = MacList::default();
+        if s == "none" {
+            return Ok(list);
+        }
+        for part in s.split(',') {
+            list.0.push(part.parse()?).map_err(|_| ())?;
+        }
+        Ok(list)
+    }
+}
+
+/// An RSSI bound in dBm, written as `none` when unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RssiBound(pub Option<i8>);
+
+impl fmt::Display for RssiBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(rssi) => write!(f, "{}", rssi),
+            None => f.write_str("none"),
+        }
+    }
+}
+
+impl FromStr for RssiBound {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(RssiBound(None));
+        }
+        s.parse().map(|rssi| RssiBound(Some(rssi))).map_err(|_| ())
+    }
+}
+
+/// Filter settings. Stages left at their defaults let every frame through.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterConfig {
+    /// If not empty, only transmitters matching one of these pass.
+    pub allow_mac: MacList,
+    /// Transmitters matching one of these are dropped, even if allowed.
+    pub deny_mac: MacList,
+    /// Frames received below this RSSI in dBm are dropped.
+    pub min_rssi: Option<i8>,
+    /// Frames received above this RSSI in dBm are dropped.
+    pub max_rssi: Option<i8>,
+    /// If not empty, only frames of these types or subtypes pass.
+    pub frame_types: FrameTypes,
+    /// If set, only frames of this BSS pass.
+    pub bssid: Option<MacAddr>,
+    /// If set, only frames of the first BSS heard advertising this SSID pass.
+    pub ssid: Option<String<MAX_SSID_LEN>>,
+    /// If not empty, only frames sent with one of these PHYs pass.
+    pub sig_mode: SigModes,
+    /// If not empty, only frames sent with one of these bandwidths pass.
+    pub bandwidth: Bandwidths,
+    pub stbc: Stbc,
+    /// Frames per second let through in total.
+    pub max_rate: Option<u32>,
+    /// Frames per second let through from each transmitter.
+    pub max_rate_per_mac: Option<u32>,
+    /// Frames remembered by the duplicate check, which is off if unset.
+    pub dedup: Option<u8>,
+    /// If set, only frames sent to this device by the traffic target pass.
+    /// Without a MAC header, frames pass if the record shows the target as
+    /// their transmitter.
+    pub replies: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterError {
+    /// The RSSI window is empty.
+    RssiRange,
+    /// Rate limits must let at least a frame per second through.
+    ZeroRate,
+    /// The duplicate check must remember 1 to [`MAX_DEDUP_WINDOW`] frames.
+    DedupWindow,
+    /// A BSS is selected by either its BSSID or its SSID.
+    BssidAndSsid,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::RssiRange => f.write_str("min RSSI must not exceed max RSSI"),
+            FilterError::ZeroRate => f.write_str("max rate must be at least 1 fps"),
+            FilterError::DedupWindow => {
+                write!(f, "dedup window must be 1 to {} frames", MAX_DEDUP_WINDOW)
+            }
+            FilterError::BssidAndSsid => f.write_str("set either a BSSID or an SSID"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FilterError {}
+
+impl FilterConfig {
+    pub fn validate(&self) -> Result<(), FilterError> {
+        if let (Some(min), Some(max)) = (self.min_rssi, self.max_rssi) {
+            if min > max {
+                return Err(FilterError::RssiRange);
+            }
+        }
+        if self.max_rate == Some(0) || self.max_rate_per_mac == Some(0) {
+            return Err(FilterError::ZeroRate);
+        }
+        if self
+            .dedup
+            .is_some_and(|n| n == 0 || n as usize > MAX_DEDUP_WINDOW)
+        {
+            return Err(FilterError::DedupWindow);
+        }
+        if self.bssid.is_some() && self.ssid.is_some() {
+            return Err(FilterError::BssidAndSsid);
+        }
+        Ok(())
+    }
+
+    /// Promiscuous filter mask letting through the frames the filters need,
+    /// beacons included while an SSID is looked for.
+    pub fn promiscuous_mask(&self) -> u32 {
+        match self.ssid {
+            Some(_) => self.frame_types.promiscuous_mask() | PROMISCUOUS_MGMT,
+            None => self.frame_types.promiscuous_mask(),
+        }
+    }
+
+    /// Whether any stage looks at the MAC header, which is only tapped then.
+    pub fn needs_header(&self) -> bool {
+        !self.frame_types.is_empty()
+            || self.bssid.is_some()
+            || self.ssid.is_some()
+            || self.dedup.is_some()
+    }
+}
+
+/// Frames seen by each filter stage since the counters were last reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterStats {
+    /// Frames whose transmitter matched the allow list.
+    pub allowed: u32,
+    /// Frames dropped by the deny list.
+    pub denied: u32,
+    /// Frames dropped for not matching a non-empty allow list.
+    pub not_allowed: u32,
+    /// Frames dropped for an RSSI outside the window.
+    pub rssi_rejected: u32,
+    /// Frames dropped for their type, or for a header that was not seen.
+    pub frame_type_rejected: u32,
+    /// Frames dropped for belonging to another BSS, or to none.
+    pub bss_rejected: u32,
+    /// Frames dropped for not being sent to this device by the traffic
+    /// target, or while the target is not known.
+    pub not_reply: u32,
+    /// Frames dropped for their PHY, bandwidth or STBC.
+    pub ppdu_rejected: u32,
+    /// Retransmissions of frames already let through.
+    pub duplicates: u32,
+    /// Frames the duplicate check let through unchecked, for lack of a
+    /// header or sequence number.
+    pub unchecked: u32,
+    /// Frames shed by the rate limits.
+    pub rate_shed: u32,
+    /// Frames among `rate_shed` shed by the global limit while their
+    /// transmitter was within its own.
+    pub global_shed: u32,
+}
+
+/// Lets through `rate` frames per second on average, in bursts of up to a
+/// second's worth. Time is taken from the receive timestamps of the frames.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBucket {
+    rate: u32,
+    /// In millionths of a frame, so a microsecond adds `rate` of them.
+    tokens: u64,
+    last_us: Option<u32>,
+}
+
+const TOKEN: u64 = 1_000_000;
+
+impl TokenBucket {
+    /// A bucket that starts out full.
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate,
+            tokens: rate.max(1) as u64 * TOKEN,
+            last_us: None,
+        }
+    }
+
+    /// Takes a token for a frame received at `now_us`, returning `false` if
+    /// the frame must be shed.
+    pub fn take(&mut self, now_us: u32) -> bool {
+        if !self.ready(now_us) {
+            return false;
+        }
+        self.tokens -= TOKEN;
+        true
+    }
+
+    /// Adds the tokens earned up to `now_us`, returning whether a frame
+    /// received then could take one. Nothing is taken.
+    pub fn ready(&mut self, now_us: u32) -> bool {
+        if let Some(last_us) = self.last_us {
+            // Frames delivered out of order add nothing
+            let elapsed = now_us.wrapping_sub(last_us);
+            if elapsed < u32::MAX / 2 {
+                let capacity = self.rate.max(1) as u64 * TOKEN;
+                self.tokens = (self.tokens + elapsed as u64 * self.rate as u64).min(capacity);
+                self.last_us = Some(now_us);
+            }
+        } else {
+            self.last_us = Some(now_us);
+        }
+        self.tokens >= TOKEN
+    }
+}
+
+/// A transmitter seen by the rate limits.
+#[derive(Debug, Clone, Copy)]
+struct Source {
+    mac: MacAddr,
+    bucket: Option<TokenBucket>,
+    shed: u32,
+    last_us: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Chip the records come from, which decides how `sig_mode` is read.
+    chip: &'static str,
+    config: FilterConfig,
+    stats: FilterStats,
+    global: Option<TokenBucket>,
+    sources: Vec<Source, MAX_SOURCES>,
+    /// Transmitter and sequence control of the frames seen last, the most
+    /// recent at the end.
+    recent: Vec<(MacAddr, SequenceControl), MAX_DEDUP_WINDOW>,
+    /// BSSID found for the SSID looked for.
+    resolved: Option<MacAddr>,
+    /// Channel the radio was last set to, if known.
+    channel: Option<u8>,
+    /// Address of this device and of the traffic target, once known.
+    traffic: Option<(MacAddr, MacAddr)>,
+}
+
+impl Filter {
+    pub fn new(config: FilterConfig) -> Self {
+        let mut filter = Self::default();
+        filter.set_config(config);
+        filter
+    }
+
+    /// A filter letting every frame through, for records received on `chip`.
+    pub fn for_chip(chip: &'static str) -> Self {
+        Self {
+            chip,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> &FilterConfig {
+        &self.config
+    }
+
+    /// Replaces the settings, keeping the counters. Rate limits start over
+    /// with full buckets, and the duplicate check forgets the frames seen.
+    /// An SSID is looked for again.
+    pub fn set_config(&mut self, config: FilterConfig) {
+        self.recent.clear();
+        self.resolved = None;
+        self.global = config.max_rate.map(TokenBucket::new);
+        for source in self.sources.iter_mut() {
+            source.bucket = config.max_rate_per_mac.map(TokenBucket::new);
+        }
+        self.config = config;
+    }
+
+    pub fn stats(&self) -> &FilterStats {
+        &self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = FilterStats::default();
+        self.sources.clear();
+        self.recent.clear();
+    }
+
+    /// Tells the filter the radio was set to `channel`. A BSS found for the
+    /// SSID on another channel is forgotten, as the SSID may belong to another
+    /// BSS here, and is looked for again.
+    pub fn set_channel(&mut self, channel: u8) {
+        if self.channel.is_some_and(|c| c != channel) {
+            self.resolved = None;
+        }
+        self.channel = Some(channel);
+    }
+
+    /// Tells the filter the address of this device and of the target of the
+    /// generated traffic, `None` while there is no target.
+    pub fn set_traffic(&mut self, traffic: Option<(MacAddr, MacAddr)>) {
+        self.traffic = traffic;
+    }
+
+    /// The BSS frames must belong to, `None` if any will do or while the SSID
+    /// has not been heard.
+    pub fn bssid(&self) -> Option<MacAddr> {
+        self.config.bssid.or(self.resolved)
+    }
+
+    /// Looks for the SSID in a received frame, keeping the BSS of the first
+    /// beacon or probe response advertising it.
+    pub fn watch_ssid(&mut self, frame: &[u8]) {
+        let Some(ssid) = &self.config.ssid else {
+            return;
+        };
+        if self.resolved.is_some() || advertised_ssid(frame) != Some(ssid.as_bytes()) {
+            return;
+        }
+        self.resolved = FrameHeader::parse(frame).and_then(|h| h.bssid());
+    }
+
+    /// Frames shed by the rate limits for each transmitter that lost any.
+    pub fn shed_by_source(&self) -> impl Iterator<Item = (MacAddr, u32)> + '_ {
+        self.sources
+            .iter()
+            .filter(|s| s.shed > 0)
+            .map(|s| (s.mac, s.shed))
+    }
+
+    /// Whether `record` passes every stage, counting the frame in the stats.
+    /// `header` is the MAC header of the frame, if it was seen.
+    pub fn check(&mut self, record: &CsiRecord, header: Option<&FrameHeader>) -> bool {
+        let mac = &record.mac;
+        if self.config.deny_mac.matches(mac) {
+            self.stats.denied += 1;
+            return false;
+        }
+        if !self.config.allow_mac.is_empty() {
+            if !self.config.allow_mac.matches(mac) {
+                self.stats.not_allowed += 1;
+                return false;
+            }
+            self.stats.allowed += 1;
+        }
+        let rssi = record.rssi;
+        if self.config.min_rssi.is_some_and(|min| rssi < min)
+            || self.config.max_rssi.is_some_and(|max| rssi > max)
+        {
+            self.stats.rssi_rejected += 1;
+            return false;
+        }
+        let types = &self.config.frame_types;
+        if !types.is_empty() && !header.is_some_and(|h| types.matches(h.frame_control)) {
+            self.stats.frame_type_rejected += 1;
+            return false;
+        }
+        if self.config.bssid.is_some() || self.config.ssid.is_some() {
+            let bssid = self.bssid();
+            if bssid.is_none() || header.and_then(|h| h.bssid()) != bssid {
+                self.stats.bss_rejected += 1;
+                return false;
+            }
+        }
+        if self.config.replies {
+            let reply = self.traffic.is_some_and(|(own, target)| match header {
+                Some(h) => h.addr1 == Some(own) && h.addr2 == Some(target),
+                None => record.mac == target,
+            });
+            if !reply {
+                self.stats.not_reply += 1;
+                return false;
+            }
+        }
+        let sig_mode = SigMode::from_rx_ctrl(record.sig_mode, self.chip);
+        let bandwidth = Bandwidth::from_rx_ctrl(record.bandwidth);
+        if !self.config.sig_mode.matches(sig_mode)
+            || !self.config.bandwidth.matches(Some(bandwidth))
+            || !self.config.stbc.matches(record.stbc)
+        {
+            self.stats.ppdu_rejected += 1;
+            return false;
+        }
+        if let Some(window) = self.config.dedup {
+            // Without a sequence number a retransmission cannot be told from a
+            // new frame, and dropping would lose every frame whose header was
+            // missed, so such frames pass and are counted
+            match header.and_then(|h| Some((h.sequence?, h.frame_control.retry()))) {
+                Some((sequence, retry)) => {
+                    if self.seen(record.mac, sequence, window as usize) && retry {
+                        self.stats.duplicates += 1;
+                        return false;
+                    }
+                }
+                None => self.stats.unchecked += 1,
+            }
+        }
+        if self.config.max_rate.is_some() || self.config.max_rate_per_mac.is_some() {
+            let now_us = record.timestamp;
+            let source = self.source(record.mac, now_us);
+            let bucket = &mut self.sources[source].bucket;
+            // Both limits are checked before either is charged, and a frame
+            // only counts against its transmitter if its own limit shed it
+            if !bucket.as_mut().is_none_or(|b| b.ready(now_us)) {
+                self.sources[source].shed += 1;
+                self.stats.rate_shed += 1;
+                return false;
+            }
+            if !self.global.as_mut().is_none_or(|b| b.ready(now_us)) {
+                self.stats.global_shed += 1;
+                self.stats.rate_shed += 1;
+                return false;
+            }
+            for bucket in [bucket.as_mut(), self.global.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                bucket.take(now_us);
+            }
+        }
+        true
+    }
+
+    /// Whether the frame `sequence` from `mac` is among the last `window`
+    /// seen, remembering it as the most recent either way.
+    fn seen(&mut self, mac: MacAddr, sequence: SequenceControl, window: usize) -> bool {
+        let position = self.recent.iter().position(|&f| f == (mac, sequence));
+        if let Some(i) = position {
+            self.recent.remove(i);
+        }
+        while self.recent.len() >= window {
+            self.recent.remove(0);
+        }
+        let _ = self.recent.push((mac, sequence));
+        position.is_some()
+    }
+
+    /// Index of the entry of `mac`, which is added if needed.
+    fn source(&mut self, mac: MacAddr, now_us: u32) -> usize {
+        if let Some(i) = self.sources.iter().position(|s| s.mac == mac) {
+            self.sources[i].last_us = now_us;
+            return i;
+        }
+        let source = Source {
+            mac,
+            bucket: self.config.max_rate_per_mac.map(TokenBucket::new),
+            shed: 0,
+            last_us: now_us,
+        };
+        match self.sources.push(source) {
+            Ok(()) => self.sources.len() - 1,
+            Err(source) => {
+                let (i, _) = self
+                    .sources
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, s)| now_us.wrapping_sub(s.last_us))
+                    .unwrap();
+                self.sources[i] = source;
+                i
+            }
+        }
+    }
+}
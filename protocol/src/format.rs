@@ -0,0 +1,367 @@
+//! Encoders and the line decoder for every supported output format.
+//!
+//! - [`OutputFormat::Text`]: human readable, one `key: value` line per field,
+//!   followed by the raw CSI buffer as a bracketed list. Events are single
+//!   `event: <kind> key=value ...` lines.
+//! - [`OutputFormat::Csv`]: one line per record, laid out like the `CSI_DATA`
+//!   lines of the ESP-IDF `csi_recv` example so existing tooling can read it.
+//!   Events are `CSI_EVENT,<kind>,key=value,...` lines.
+//!
+//! [`Decoder`] accepts both formats on the same stream, so host tools do not
+//! need to know which one the device is configured for.
+
+use core::fmt::{self, Write};
+use core::str::FromStr;
+
+use crate::event::{Event, Fields};
+use crate::record::{CsiRecord, MacAddr, MAX_CSI_LEN};
+
+keyword_enum! {
+    /// Encoding used for records and events on the serial stream.
+    #[derive(Default)]
+    pub enum OutputFormat {
+        #[default]
+        Text => "text",
+        Csv => "csv",
+    }
+}
+
+/// Column header matching the CSV record layout.
+pub const CSV_HEADER: &str = "type,seq,mac,rssi,rate,sig_mode,mcs,bandwidth,smoothing,not_sounding,aggregation,stbc,fec_coding,sgi,noise_floor,ampdu_cnt,channel,secondary_channel,local_timestamp,ant,sig_len,rx_state,len,data";
+
+const CSV_RECORD_TAG: &str = "CSI_DATA";
+const CSV_EVENT_TAG: &str = "CSI_EVENT";
+const TEXT_EVENT_TAG: &str = "event";
+const TEXT_DATA_KEY: &str = "csi raw data";
+
+impl CsiRecord {
+    /// Encodes the record in `format`. The encoding spans one or more lines
+    /// and does not end with a newline.
+    pub fn display(&self, format: OutputFormat) -> RecordDisplay<'_> {
+        RecordDisplay {
+            record: self,
+            format,
+        }
+    }
+}
+
+impl Event {
+    /// Encodes the event in `format` as a single line without a trailing newline.
+    pub fn display(&self, format: OutputFormat) -> EventDisplay<'_> {
+        EventDisplay {
+            event: self,
+            format,
+        }
+    }
+}
+
+pub struct RecordDisplay<'a> {
+    record: &'a CsiRecord,
+    format: OutputFormat,
+}
+
+impl fmt::Display for RecordDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let r = self.record;
+        match self.format {
+            OutputFormat::Text => {
+                writeln!(f, "mac: {}", r.mac)?;
+                writeln!(f, "sequence number: {}", r.seq)?;
+                writeln!(f, "rssi: {}", r.rssi)?;
+                writeln!(f, "rate: {}", r.rate)?;
+                writeln!(f, "sig mode: {}", r.sig_mode)?;
+                writeln!(f, "mcs: {}", r.mcs)?;
+                writeln!(f, "bandwidth: {}", r.bandwidth)?;
+                writeln!(f, "smoothing: {}", r.smoothing)?;
+                writeln!(f, "not sounding: {}", r.not_sounding)?;
+                writeln!(f, "aggregation: {}", r.aggregation)?;
+                writeln!(f, "stbc: {}", r.stbc)?;
+                writeln!(f, "fec coding: {}", r.fec_coding)?;
+                writeln!(f, "sgi: {}", r.sgi)?;
+                writeln!(f, "noise floor: {}", r.noise_floor)?;
+                writeln!(f, "ampdu cnt: {}", r.ampdu_cnt)?;
+                writeln!(f, "channel: {}", r.channel)?;
+                writeln!(f, "secondary channel: {}", r.secondary_channel)?;
+                writeln!(f, "timestamp: {}", r.timestamp)?;
+                writeln!(f, "ant: {}", r.ant)?;
+                writeln!(f, "sig len: {}", r.sig_len)?;
+                writeln!(f, "rx state: {}", r.rx_state)?;
+                writeln!(f, "data length: {}", r.csi.len())?;
+                writeln!(f, "{}:", TEXT_DATA_KEY)?;
+                write_csi(f, &r.csi, ", ")
+            }
+            OutputFormat::Csv => {
+                write!(
+                    f,
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},\"",
+                    CSV_RECORD_TAG,
+                    r.seq,
+                    r.mac,
+                    r.rssi,
+                    r.rate,
+                    r.sig_mode,
+                    r.mcs,
+                    r.bandwidth,
+                    r.smoothing,
+                    r.not_sounding,
+                    r.aggregation,
+                    r.stbc,
+                    r.fec_coding,
+                    r.sgi,
+                    r.noise_floor,
+                    r.ampdu_cnt,
+                    r.channel,
+                    r.secondary_channel,
+                    r.timestamp,
+                    r.ant,
+                    r.sig_len,
+                    r.rx_state,
+                    r.csi.len(),
+                )?;
+                write_csi(f, &r.csi, ",")?;
+                f.write_char('"')
+            }
+        }
+    }
+}
+
+fn write_csi(f: &mut fmt::Formatter<'_>, csi: &[i8], sep: &str) -> fmt::Result {
+    f.write_char('[')?;
+    for (i, v) in csi.iter().enumerate() {
+        if i > 0 {
+            f.write_str(sep)?;
+        }
+        write!(f, "{}", v)?;
+    }
+    f.write_char(']')
+}
+
+pub struct EventDisplay<'a> {
+    event: &'a Event,
+    format: OutputFormat,
+}
+
+impl fmt::Display for EventDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sep = match self.format {
+            OutputFormat::Text => {
+                write!(f, "{}: {}", TEXT_EVENT_TAG, self.event.kind())?;
+                ' '
+            }
+            OutputFormat::Csv => {
+                write!(f, "{},{}", CSV_EVENT_TAG, self.event.kind())?;
+                ','
+            }
+        };
+        self.event
+            .for_each_field(&mut |key, value| write!(f, "{}{}={}", sep, key, value))
+    }
+}
+
+/// Result of feeding one line to the [`Decoder`].
+// Boxing is not an option without an allocator on the firmware side.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded {
+    Record(CsiRecord),
+    Event(Event),
+    /// The line belongs to a text record that is still being assembled.
+    Pending,
+    /// A record or event was abandoned because the stream did not match the
+    /// expected layout, usually because bytes were lost on the serial link.
+    Malformed,
+    /// The line is not part of the CSI stream (CLI output, log messages, or
+    /// events unknown to this version of the crate).
+    Other,
+}
+
+/// Line decoder accepting every [`OutputFormat`].
+#[derive(Default)]
+pub struct Decoder {
+    partial: Option<CsiRecord>,
+    expect_data: bool,
+    data_len: Option<usize>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a single line, without its line terminator.
+    pub fn push_line(&mut self, line: &str) -> Decoded {
+        let line = line.trim();
+
+        if self.expect_data {
+            self.expect_data = false;
+            let record = self.partial.take();
+            let data_len = self.data_len.take();
+            return match (record, parse_csi(line)) {
+                (Some(mut record), Some(csi)) if data_len.unwrap_or(csi.len()) == csi.len() => {
+                    record.csi = csi;
+                    Decoded::Record(record)
+                }
+                _ => Decoded::Malformed,
+            };
+        }
+
+        if let Some(rest) = line.strip_prefix(CSV_RECORD_TAG) {
+            let interrupted = self.reset();
+            return match (interrupted, rest.strip_prefix(',').and_then(decode_csv)) {
+                (false, Some(record)) => Decoded::Record(record),
+                _ => Decoded::Malformed,
+            };
+        }
+        if let Some(rest) = line.strip_prefix(CSV_EVENT_TAG) {
+            let interrupted = self.reset();
+            return decode_event(interrupted, rest.strip_prefix(','), ',');
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            return match self.reset() {
+                true => Decoded::Malformed,
+                false => Decoded::Other,
+            };
+        };
+        let (key, value) = (key.trim(), value.trim());
+
+        if key == TEXT_EVENT_TAG {
+            let interrupted = self.reset();
+            return decode_event(interrupted, Some(value), ' ');
+        }
+
+        if key == "mac" {
+            // A new header while a record is still open means the tail of the
+            // previous record was lost.
+            let interrupted = self.reset();
+            self.partial = value.parse().ok().map(|mac| CsiRecord {
+                mac,
+                ..CsiRecord::default()
+            });
+            return match (interrupted, &self.partial) {
+                (false, Some(_)) => Decoded::Pending,
+                _ => Decoded::Malformed,
+            };
+        }
+
+        let Some(r) = self.partial.as_mut() else {
+            return Decoded::Other;
+        };
+
+        let ok = match key {
+            "sequence number" => set(&mut r.seq, value),
+            "rssi" => set(&mut r.rssi, value),
+            "rate" => set(&mut r.rate, value),
+            "sig mode" => set(&mut r.sig_mode, value),
+            "mcs" => set(&mut r.mcs, value),
+            "bandwidth" => set(&mut r.bandwidth, value),
+            "smoothing" => set(&mut r.smoothing, value),
+            "not sounding" => set(&mut r.not_sounding, value),
+            "aggregation" => set(&mut r.aggregation, value),
+            "stbc" => set(&mut r.stbc, value),
+            "fec coding" => set(&mut r.fec_coding, value),
+            "sgi" => set(&mut r.sgi, value),
+            "noise floor" => set(&mut r.noise_floor, value),
+            "ampdu cnt" => set(&mut r.ampdu_cnt, value),
+            "channel" => set(&mut r.channel, value),
+            "secondary channel" => set(&mut r.secondary_channel, value),
+            "timestamp" => set(&mut r.timestamp, value),
+            "ant" => set(&mut r.ant, value),
+            "sig len" => set(&mut r.sig_len, value),
+            "rx state" => set(&mut r.rx_state, value),
+            "data length" => value.parse().map(|n| self.data_len = Some(n)).is_ok(),
+            TEXT_DATA_KEY => {
+                self.expect_data = true;
+                true
+            }
+            // Fields added by newer firmware are skipped.
+            _ => true,
+        };
+
+        if ok {
+            Decoded::Pending
+        } else {
+            self.reset();
+            Decoded::Malformed
+        }
+    }
+
+    /// Drops any partially assembled record, returning whether there was one.
+    fn reset(&mut self) -> bool {
+        self.expect_data = false;
+        self.data_len = None;
+        self.partial.take().is_some()
+    }
+}
+
+fn set<T: FromStr>(field: &mut T, value: &str) -> bool {
+    match value.parse() {
+        Ok(v) => {
+            *field = v;
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+fn decode_event(interrupted: bool, body: Option<&str>, sep: char) -> Decoded {
+    let Some(body) = body else {
+        return Decoded::Malformed;
+    };
+    let (kind, fields) = body.split_once(sep).unwrap_or((body, ""));
+    match Event::from_fields(kind, Fields::new(fields, sep)) {
+        _ if interrupted => Decoded::Malformed,
+        Some(event) => Decoded::Event(event),
+        None => Decoded::Other,
+    }
+}
+
+fn decode_csv(line: &str) -> Option<CsiRecord> {
+    let (head, data) = line.split_once(",\"")?;
+    let mut cols = head.split(',');
+    let mut next = || cols.next();
+
+    let mut r = CsiRecord {
+        seq: next()?.parse().ok()?,
+        mac: next()?.parse::<MacAddr>().ok()?,
+        rssi: next()?.parse().ok()?,
+        rate: next()?.parse().ok()?,
+        sig_mode: next()?.parse().ok()?,
+        mcs: next()?.parse().ok()?,
+        bandwidth: next()?.parse().ok()?,
+        smoothing: next()?.parse().ok()?,
+        not_sounding: next()?.parse().ok()?,
+        aggregation: next()?.parse().ok()?,
+        stbc: next()?.parse().ok()?,
+        fec_coding: next()?.parse().ok()?,
+        sgi: next()?.parse().ok()?,
+        noise_floor: next()?.parse().ok()?,
+        ampdu_cnt: next()?.parse().ok()?,
+        channel: next()?.parse().ok()?,
+        secondary_channel: next()?.parse().ok()?,
+        timestamp: next()?.parse().ok()?,
+        ant: next()?.parse().ok()?,
+        sig_len: next()?.parse().ok()?,
+        rx_state: next()?.parse().ok()?,
+        csi: heapless::Vec::new(),
+    };
+    let len: usize = next()?.parse().ok()?;
+    if next().is_some() {
+        return None;
+    }
+
+    r.csi = parse_csi(data.strip_suffix('"')?)?;
+    (r.csi.len() == len).then_some(r)
+}
+
+fn parse_csi(s: &str) -> Option<heapless::Vec<i8, MAX_CSI_LEN>> {
+    let inner = s.strip_prefix('[')?.strip_suffix(']')?;
+    let mut csi = heapless::Vec::new();
+    if inner.trim().is_empty() {
+        return Some(csi);
+    }
+    for v in inner.split(',') {
+        csi.push(v.trim().parse().ok()?).ok()?;
+    }
+    Some(csi)
+}
@@ -0,0 +1,381 @@
+//! 802.11 MAC headers of received frames.
+//!
+//! The CSI callback only reports the receive metadata of a frame, so the
+//! firmware also taps the promiscuous receive path for the MAC header and
+//! pairs the two by their receive timestamp with a [`Pairing`].
+//! [`FrameTypes`] selects frames by the type and subtype in their frame
+//! control field, and maps the selection onto the promiscuous filter of the
+//! WiFi driver. Beacons and probe responses are also read for the SSID they
+//! advertise, see [`advertised_ssid`].
+
+use core::fmt;
+use core::str::FromStr;
+
+use heapless::{Deque, Vec};
+
+use crate::record::MacAddr;
+
+/// Bytes at the start of each frame holding the header of a data or
+/// management frame.
+pub const HEADER_LEN: usize = 24;
+/// Longest SSID in bytes.
+pub const MAX_SSID_LEN: usize = 32;
+/// Bytes at the start of a beacon or probe response holding the header, the
+/// fixed fields and the SSID element, which always comes first.
+pub const BEACON_LEN: usize = HEADER_LEN + FIXED_FIELDS_LEN + 2 + MAX_SSID_LEN;
+/// Timestamp, beacon interval and capabilities.
+const FIXED_FIELDS_LEN: usize = 12;
+
+/// Entries accepted in a [`FrameTypes`] list.
+pub const MAX_FRAME_TYPES: usize = 16;
+
+// Promiscuous filter mask bits of the ESP-IDF WiFi driver.
+pub const PROMISCUOUS_MGMT: u32 = 1 << 0;
+pub const PROMISCUOUS_CTRL: u32 = 1 << 1;
+pub const PROMISCUOUS_DATA: u32 = 1 << 2;
+pub const PROMISCUOUS_ALL: u32 = u32::MAX;
+
+// Frame control type field values.
+const MGMT: u8 = 0;
+const CTRL: u8 = 1;
+const DATA: u8 = 2;
+
+/// The frame control field, as transmitted (little endian).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameControl(pub u16);
+
+impl FrameControl {
+    /// Type field: 0 for management, 1 for control and 2 for data.
+    pub fn kind(&self) -> u8 {
+        ((self.0 >> 2) & 0x3) as u8
+    }
+
+    pub fn subtype(&self) -> u8 {
+        ((self.0 >> 4) & 0xf) as u8
+    }
+
+    /// Set on frames sent to the distribution system, by a station to its AP.
+    pub fn to_ds(&self) -> bool {
+        self.0 & (1 << 8) != 0
+    }
+
+    /// Set on frames from the distribution system, by an AP to a station.
+    pub fn from_ds(&self) -> bool {
+        self.0 & (1 << 9) != 0
+    }
+
+    /// Set on frames sent again after an unacknowledged earlier attempt.
+    pub fn retry(&self) -> bool {
+        self.0 & (1 << 11) != 0
+    }
+}
+
+/// The sequence control field, as transmitted (little endian).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SequenceControl(pub u16);
+
+impl SequenceControl {
+    pub fn number(&self) -> u16 {
+        self.0 >> 4
+    }
+
+    pub fn fragment(&self) -> u8 {
+        (self.0 & 0xf) as u8
+    }
+}
+
+/// The part of a MAC header the filters look at. Fields the frame is too
+/// short for, or that its type does not carry, are `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub frame_control: FrameControl,
+    /// Receiver address.
+    pub addr1: Option<MacAddr>,
+    /// Transmitter address, missing from CTS and ACK frames.
+    pub addr2: Option<MacAddr>,
+    /// Not carried by control frames.
+    pub addr3: Option<MacAddr>,
+    /// Not carried by control frames.
+    pub sequence: Option<SequenceControl>,
+}
+
+impl FrameHeader {
+    /// Reads the header at the start of a received frame, `None` if the frame
+    /// is too short to hold one.
+    pub fn parse(frame: &[u8]) -> Option<Self> {
+        let frame_control = FrameControl(u16::from_le_bytes(frame.get(..2)?.try_into().ok()?));
+        let addr = |at: usize| {
+            frame
+                .get(at..at + 6)
+                .map(|b| MacAddr(b.try_into().unwrap()))
+        };
+        let mut header = Self {
+            frame_control,
+            addr1: addr(4),
+            addr2: addr(10),
+            ..Self::default()
+        };
+        // The rest of a control frame is its body
+        if frame_control.kind() != CTRL {
+            header.addr3 = addr(16);
+            header.sequence = frame
+                .get(22..24)
+                .map(|b| SequenceControl(u16::from_le_bytes(b.try_into().unwrap())));
+        }
+        Some(header)
+    }
+
+    /// The BSS the frame belongs to, which depends on the direction of the
+    /// frame. `None` for control frames and frames between two APs.
+    pub fn bssid(&self) -> Option<MacAddr> {
+        match (self.frame_control.to_ds(), self.frame_control.from_ds()) {
+            _ if self.frame_control.kind() == CTRL => None,
+            (false, false) => self.addr3,
+            (true, false) => self.addr1,
+            (false, true) => self.addr2,
+            (true, true) => None,
+        }
+    }
+}
+
+/// The SSID a beacon or probe response advertises, empty for a hidden
+/// network. `None` for other frames, or if the frame is cut short.
+pub fn advertised_ssid(frame: &[u8]) -> Option<&[u8]> {
+    let header = FrameHeader::parse(frame)?;
+    if !FrameType::Beacon.matches(header.frame_control)
+        && !FrameType::ProbeResp.matches(header.frame_control)
+    {
+        return None;
+    }
+    let element = frame.get(HEADER_LEN + FIXED_FIELDS_LEN..)?;
+    match element {
+        [0, len, ssid @ ..] if *len as usize <= MAX_SSID_LEN => ssid.get(..*len as usize),
+        _ => None,
+    }
+}
+
+/// Time, in microseconds, a record waits for the header of its frame.
+pub const PAIRING_WAIT_US: u32 = 2_000;
+
+/// Pairs records with the header of their frame by receive timestamp. The
+/// driver reports the two through separate callbacks and does not promise
+/// which runs first, so a record waits for its header until one arrives, or
+/// until [`PAIRING_WAIT_US`] passed since the record arrived. Arrival is
+/// timed by the caller's clock, which [`Pairing::next_deadline`] tells it
+/// when to check again, so a record without a header need not wait for the
+/// next frame. Records leave in the order they came in. Up to `N` records and
+/// `N` unclaimed headers are held, the oldest giving way.
+#[derive(Debug, Clone)]
+pub struct Pairing<T, const N: usize> {
+    headers: Deque<(u32, FrameHeader), N>,
+    /// Receive timestamp, arrival time, record and header.
+    records: Deque<(u32, u32, T, Option<FrameHeader>), N>,
+}
+
+impl<T, const N: usize> Pairing<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            headers: Deque::new(),
+            records: Deque::new(),
+        }
+    }
+
+    /// Adds the header of the frame received at `timestamp`.
+    pub fn push_header(&mut self, timestamp: u32, header: FrameHeader) {
+        let waiting = self
+            .records
+            .iter_mut()
+            .find(|(at, _, _, h)| *at == timestamp && h.is_none());
+        if let Some((_, _, _, h)) = waiting {
+            *h = Some(header);
+            return;
+        }
+        if self.headers.is_full() {
+            self.headers.pop_front();
+        }
+        let _ = self.headers.push_back((timestamp, header));
+    }
+
+    /// Adds the record of the frame received at `timestamp`, arriving at
+    /// `now_us`. Returns the oldest record if it has to make room, with its
+    /// header if it came.
+    pub fn push_record(
+        &mut self,
+        timestamp: u32,
+        now_us: u32,
+        record: T,
+    ) -> Option<(T, Option<FrameHeader>)> {
+        // Headers before this one belong to frames that produced no record
+        let header = match self.headers.iter().position(|(at, _)| *at == timestamp) {
+            Some(position) => {
+                for _ in 0..position {
+                    self.headers.pop_front();
+                }
+                self.headers.pop_front().map(|(_, header)| header)
+            }
+            None => None,
+        };
+        let evicted = match self.records.is_full() {
+            true => self.drain(),
+            false => None,
+        };
+        let _ = self.records.push_back((timestamp, now_us, record, header));
+        evicted
+    }
+
+    /// The oldest record, once it has its header or waited long enough by
+    /// `now_us`.
+    pub fn pop_ready(&mut self, now_us: u32) -> Option<(T, Option<FrameHeader>)> {
+        let (_, arrived, _, header) = self.records.front()?;
+        // The clock wraps
+        if header.is_none() && now_us.wrapping_sub(*arrived) <= PAIRING_WAIT_US {
+            return None;
+        }
+        self.drain()
+    }
+
+    /// When the oldest record is ready at the latest, if any is held.
+    pub fn next_deadline(&self) -> Option<u32> {
+        self.records
+            .front()
+            .map(|(_, arrived, _, _)| arrived.wrapping_add(PAIRING_WAIT_US + 1))
+    }
+
+    /// The oldest record, whether or not its header came.
+    pub fn drain(&mut self) -> Option<(T, Option<FrameHeader>)> {
+        self.records
+            .pop_front()
+            .map(|(_, _, record, header)| (record, header))
+    }
+
+    pub fn clear(&mut self) {
+        self.headers.clear();
+        self.records.clear();
+    }
+}
+
+impl<T, const N: usize> Default for Pairing<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+keyword_enum! {
+    /// A frame type, or a single subtype of one, to keep.
+    pub enum FrameType {
+        Mgmt => "mgmt",
+        Ctrl => "ctrl",
+        Data => "data",
+        AssocReq => "assoc-req",
+        AssocResp => "assoc-resp",
+        ProbeReq => "probe-req",
+        ProbeResp => "probe-resp",
+        Beacon => "beacon",
+        Disassoc => "disassoc",
+        Auth => "auth",
+        Deauth => "deauth",
+        Action => "action",
+        BlockAck => "block-ack",
+        Rts => "rts",
+        Cts => "cts",
+        Ack => "ack",
+        Null => "null",
+        QosData => "qos-data",
+        QosNull => "qos-null",
+    }
+}
+
+impl FrameType {
+    /// Type field value and, for a single subtype, the subtype field value.
+    fn code(self) -> (u8, Option<u8>) {
+        match self {
+            FrameType::Mgmt => (MGMT, None),
+            FrameType::Ctrl => (CTRL, None),
+            FrameType::Data => (DATA, None),
+            FrameType::AssocReq => (MGMT, Some(0)),
+            FrameType::AssocResp => (MGMT, Some(1)),
+            FrameType::ProbeReq => (MGMT, Some(4)),
+            FrameType::ProbeResp => (MGMT, Some(5)),
+            FrameType::Beacon => (MGMT, Some(8)),
+            FrameType::Disassoc => (MGMT, Some(10)),
+            FrameType::Auth => (MGMT, Some(11)),
+            FrameType::Deauth => (MGMT, Some(12)),
+            FrameType::Action => (MGMT, Some(13)),
+            FrameType::BlockAck => (CTRL, Some(9)),
+            FrameType::Rts => (CTRL, Some(11)),
+            FrameType::Cts => (CTRL, Some(12)),
+            FrameType::Ack => (CTRL, Some(13)),
+            FrameType::Null => (DATA, Some(4)),
+            FrameType::QosData => (DATA, Some(8)),
+            FrameType::QosNull => (DATA, Some(12)),
+        }
+    }
+
+    pub fn matches(&self, frame_control: FrameControl) -> bool {
+        let (kind, subtype) = self.code();
+        frame_control.kind() == kind && subtype.is_none_or(|s| frame_control.subtype() == s)
+    }
+
+    /// Promiscuous filter mask bit letting frames of this type through.
+    pub fn promiscuous_mask(&self) -> u32 {
+        match self.code().0 {
+            MGMT => PROMISCUOUS_MGMT,
+            CTRL => PROMISCUOUS_CTRL,
+            _ => PROMISCUOUS_DATA,
+        }
+    }
+}
+
+/// Comma separated [`FrameType`]s, written as `all` when empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameTypes(pub Vec<FrameType, MAX_FRAME_TYPES>);
+
+impl FrameTypes {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether a frame passes, always true for an empty list.
+    pub fn matches(&self, frame_control: FrameControl) -> bool {
+        self.is_empty() || self.0.iter().any(|t| t.matches(frame_control))
+    }
+
+    /// Promiscuous filter mask letting through every type in the list.
+    /// Subtypes are left to [`FrameTypes::matches`].
+    pub fn promiscuous_mask(&self) -> u32 {
+        match self.is_empty() {
+            true => PROMISCUOUS_ALL,
+            false => self.0.iter().fold(0, |mask, t| mask | t.promiscuous_mask()),
+        }
+    }
+}
+
+impl fmt::Display for FrameTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("all");
+        }
+        for (i, frame_type) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", frame_type)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FrameTypes {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut types = FrameTypes::default();
+        if s == "all" {
+            return Ok(types);
+        }
+        for part in s.split(',') {
+            types.0.push(part.parse()?).map_err(|_| ())?;
+        }
+        Ok(types)
+    }
+}
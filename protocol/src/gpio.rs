@@ -0,0 +1,137 @@
+//! GPIO inputs for synchronising with lab instruments.
+//!
+//! Active edges on the start pin start a collection when idle and stop it
+//! when collecting. Every edge on the event pin is reported as an
+//! [`Event::Gpio`](crate::Event::Gpio) timestamped in microseconds since boot.
+
+use core::fmt;
+
+keyword_enum! {
+    /// Level a pin is driven to when active. The opposite internal pull is
+    /// enabled so an unconnected pin stays inactive.
+    pub enum Polarity {
+        High => "high",
+        Low => "low",
+    }
+}
+
+keyword_enum! {
+    pub enum Edge {
+        Rising => "rising",
+        Falling => "falling",
+    }
+}
+
+impl Polarity {
+    /// Edge a pin goes active on.
+    pub fn active_edge(self) -> Edge {
+        match self {
+            Polarity::High => Edge::Rising,
+            Polarity::Low => Edge::Falling,
+        }
+    }
+}
+
+/// GPIO input settings. Inputs left out are not watched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpioConfig {
+    pub start_pin: Option<u8>,
+    pub event_pin: Option<u8>,
+    pub polarity: Polarity,
+    /// Edges closer than this to the last accepted edge on the same pin are
+    /// ignored.
+    pub debounce_ms: u64,
+}
+
+impl Default for GpioConfig {
+    fn default() -> Self {
+        Self {
+            start_pin: None,
+            event_pin: None,
+            polarity: Polarity::High,
+            debounce_ms: 20,
+        }
+    }
+}
+
+/// Why a [`GpioConfig`] cannot be used on a chip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpioError {
+    UnknownChip,
+    /// The pin does not exist, is input-only without pulls, or is taken by
+    /// flash, USB, the console UART or a strapping function.
+    UnusablePin(u8),
+    SamePin,
+}
+
+impl fmt::Display for GpioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpioError::UnknownChip => f.write_str("unknown chip"),
+            GpioError::UnusablePin(pin) => write!(f, "GPIO{} cannot be used as an input", pin),
+            GpioError::SamePin => f.write_str("start and event pins must differ"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GpioError {}
+
+/// Pins free for use as inputs on `chip`, named as in the `show-config` dump.
+pub fn input_pins(chip: &str) -> Option<&'static [u8]> {
+    Some(match chip {
+        "esp32" => &[4, 13, 14, 18, 19, 21, 22, 23, 25, 26, 27, 32, 33],
+        "esp32c3" => &[0, 1, 3, 4, 5, 6, 7, 10],
+        "esp32c6" => &[0, 1, 2, 3, 6, 7, 10, 11, 18, 19, 20, 21, 22, 23],
+        "esp32s3" => &[
+            1, 2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 21, 38, 39, 40, 41, 42, 47,
+            48,
+        ],
+        _ => return None,
+    })
+}
+
+impl GpioConfig {
+    /// Checks the pins against those usable on `chip`.
+    pub fn validate(&self, chip: &str) -> Result<(), GpioError> {
+        let pins = input_pins(chip).ok_or(GpioError::UnknownChip)?;
+        for pin in [self.start_pin, self.event_pin].into_iter().flatten() {
+            if !pins.contains(&pin) {
+                return Err(GpioError::UnusablePin(pin));
+            }
+        }
+        if self.start_pin.is_some() && self.start_pin == self.event_pin {
+            return Err(GpioError::SamePin);
+        }
+        Ok(())
+    }
+}
+
+/// Debounces the edges of one pin. The first edge of a burst is accepted
+/// right away, so event timestamps are not delayed by the debounce time.
+#[derive(Debug, Clone)]
+pub struct Debounce {
+    debounce_ms: u64,
+    last_ms: Option<u64>,
+}
+
+impl Debounce {
+    pub fn new(debounce_ms: u64) -> Self {
+        Self {
+            debounce_ms,
+            last_ms: None,
+        }
+    }
+
+    /// Whether an edge seen at `now_ms` is accepted.
+    pub fn accept(&mut self, now_ms: u64) -> bool {
+        if self
+            .last_ms
+            .is_some_and(|last| now_ms.saturating_sub(last) < self.debounce_ms)
+        {
+            return false;
+        }
+        self.last_ms = Some(now_ms);
+        true
+    }
+}
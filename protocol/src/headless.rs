@@ -0,0 +1,285 @@
+//! Headless operation with no host attached.
+//!
+//! The headless settings are kept in flash as [`HeadlessConfig::to_bytes`],
+//! next to the collector settings in effect when they were stored, as
+//! [`StoredSettings`]. When enabled, the firmware skips the CLI banner, sets
+//! the collector up again, starts collecting at boot and writes records and
+//! events to the configured [`Sink`]. [`BreakIn`] watches the serial input so
+//! a user who connects later can take over the CLI.
+
+use core::fmt::{self, Write};
+
+use heapless::String;
+
+use crate::command::Command;
+use crate::config::MAX_LINE_LEN;
+
+keyword_enum! {
+    /// Where records and events go while running headless.
+    pub enum Sink {
+        /// The serial console, for a logger left attached.
+        Serial => "serial",
+        /// The flash log, read back with `log show`.
+        Flash => "flash",
+    }
+}
+
+/// Marks flash holding headless settings.
+pub const CONFIG_MAGIC: [u8; 4] = *b"CSIH";
+/// Bumped when the stored layout changes; older settings are then ignored.
+pub const CONFIG_VERSION: u8 = 1;
+/// Stored size, a multiple of the 4 byte flash write size.
+pub const CONFIG_LEN: usize = 20;
+
+/// Number of Enter presses in a row that break into the CLI.
+pub const BREAK_IN_ENTERS: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadlessConfig {
+    pub enabled: bool,
+    pub sink: Sink,
+    /// Duration of the collection started at boot, a week if not set.
+    pub duration_s: Option<u64>,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sink: Sink::Flash,
+            duration_s: None,
+        }
+    }
+}
+
+impl HeadlessConfig {
+    /// Layout: magic, version, enabled, sink, duration present, duration
+    /// (little endian), checksum, then zero padding.
+    pub fn to_bytes(&self) -> [u8; CONFIG_LEN] {
+        let mut bytes = [0; CONFIG_LEN];
+        bytes[..4].copy_from_slice(&CONFIG_MAGIC);
+        bytes[4] = CONFIG_VERSION;
+        bytes[5] = self.enabled as u8;
+        bytes[6] = match self.sink {
+            Sink::Serial => 0,
+            Sink::Flash => 1,
+        };
+        bytes[7] = self.duration_s.is_some() as u8;
+        bytes[8..16].copy_from_slice(&self.duration_s.unwrap_or(0).to_le_bytes());
+        bytes[16] = checksum(&bytes[..16]);
+        bytes
+    }
+
+    /// Reads stored settings back. Returns `None` for erased or corrupted
+    /// flash and for settings stored by another version.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes: &[u8; CONFIG_LEN] = bytes.get(..CONFIG_LEN)?.try_into().ok()?;
+        if bytes[..4] != CONFIG_MAGIC
+            || bytes[4] != CONFIG_VERSION
+            || bytes[16] != checksum(&bytes[..16])
+        {
+            return None;
+        }
+        let sink = match bytes[6] {
+            0 => Sink::Serial,
+            1 => Sink::Flash,
+            _ => return None,
+        };
+        let duration_s = u64::from_le_bytes(bytes[8..16].try_into().ok()?);
+        Some(Self {
+            enabled: bytes[5] != 0,
+            sink,
+            duration_s: (bytes[7] != 0).then_some(duration_s),
+        })
+    }
+}
+
+/// Marks flash holding collector settings.
+pub const SETTINGS_MAGIC: [u8; 4] = *b"CSIS";
+/// Bumped when the stored layout changes; older settings are then ignored.
+pub const SETTINGS_VERSION: u8 = 1;
+/// Stored size, a multiple of the 4 byte flash write size.
+pub const SETTINGS_LEN: usize = 2048;
+// Magic, version, padding and text length, then the text and its checksum.
+const SETTINGS_HEADER: usize = 8;
+const SETTINGS_TEXT_LEN: usize = SETTINGS_LEN - SETTINGS_HEADER - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsError {
+    /// The settings do not fit in [`SETTINGS_LEN`].
+    Full,
+    /// A single argument does not fit in a CLI line.
+    LineTooLong,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Full => write!(f, "settings exceed {} bytes", SETTINGS_LEN),
+            SettingsError::LineTooLong => {
+                write!(f, "an argument exceeds the {} byte line", MAX_LINE_LEN)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SettingsError {}
+
+/// Collector settings kept with the headless settings, as the command lines
+/// that set them up again when run in order on a freshly booted board.
+///
+/// Commands too long for the CLI are stored as one line per argument, which
+/// works as arguments left out keep their previous values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoredSettings {
+    text: String<SETTINGS_TEXT_LEN>,
+}
+
+impl StoredSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `command`. Nothing is appended on error.
+    pub fn push(&mut self, command: &Command) -> Result<(), SettingsError> {
+        let start = self.text.len();
+        let result = match write!(self.text, "{}", command) {
+            Ok(()) if self.text.len() - start < MAX_LINE_LEN => Ok(()),
+            _ => {
+                self.text.truncate(start);
+                self.push_split(command)
+            }
+        };
+        match result.and_then(|_| self.text.push('\n').map_err(|_| SettingsError::Full)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.text.truncate(start);
+                Err(e)
+            }
+        }
+    }
+
+    // Writes `command` as one line per argument.
+    fn push_split(&mut self, command: &Command) -> Result<(), SettingsError> {
+        let start = self.text.len();
+        let mut split = SplitArgs {
+            text: &mut self.text,
+            name: command.name(),
+            start,
+            named: false,
+        };
+        write!(split, "{}", command).map_err(|_| SettingsError::Full)?;
+        match self.text[start..]
+            .split('\n')
+            .all(|l| l.len() < MAX_LINE_LEN)
+        {
+            true => Ok(()),
+            false => Err(SettingsError::LineTooLong),
+        }
+    }
+
+    /// The stored command lines, in the order they were pushed.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.text.lines()
+    }
+
+    /// Layout: magic, version, a zero byte, text length (little endian), the
+    /// newline separated lines, a checksum over everything before it, then
+    /// zero padding.
+    pub fn to_bytes(&self) -> [u8; SETTINGS_LEN] {
+        let mut bytes = [0; SETTINGS_LEN];
+        let end = SETTINGS_HEADER + self.text.len();
+        bytes[..4].copy_from_slice(&SETTINGS_MAGIC);
+        bytes[4] = SETTINGS_VERSION;
+        bytes[6..8].copy_from_slice(&(self.text.len() as u16).to_le_bytes());
+        bytes[SETTINGS_HEADER..end].copy_from_slice(self.text.as_bytes());
+        bytes[end] = checksum(&bytes[..end]);
+        bytes
+    }
+
+    /// Reads stored settings back. Returns `None` for erased or corrupted
+    /// flash and for settings stored by another version.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..SETTINGS_LEN)?;
+        let len = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+        if bytes[..4] != SETTINGS_MAGIC || bytes[4] != SETTINGS_VERSION || len > SETTINGS_TEXT_LEN {
+            return None;
+        }
+        let end = SETTINGS_HEADER + len;
+        if bytes[end] != checksum(&bytes[..end]) {
+            return None;
+        }
+        let text = core::str::from_utf8(&bytes[SETTINGS_HEADER..end]).ok()?;
+        Some(Self {
+            text: String::try_from(text).ok()?,
+        })
+    }
+}
+
+// Starts a new line with the command name before every argument, in place of
+// the name written once ahead of them.
+struct SplitArgs<'a> {
+    text: &'a mut String<SETTINGS_TEXT_LEN>,
+    name: &'static str,
+    start: usize,
+    named: bool,
+}
+
+impl Write for SplitArgs<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let s = match self.named {
+            true => s,
+            false => {
+                self.named = true;
+                s.strip_prefix(self.name).unwrap_or(s)
+            }
+        };
+        for (i, part) in s.split(' ').enumerate() {
+            if i > 0 {
+                if self.text.len() > self.start {
+                    self.text.push('\n').map_err(|_| fmt::Error)?;
+                }
+                self.text.push_str(self.name).map_err(|_| fmt::Error)?;
+                self.text.push(' ').map_err(|_| fmt::Error)?;
+            }
+            self.text.push_str(part).map_err(|_| fmt::Error)?;
+        }
+        Ok(())
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum: u8, b| sum.rotate_left(1) ^ b)
+}
+
+/// Detects a user taking over the CLI: Ctrl-C, or Enter pressed
+/// [`BREAK_IN_ENTERS`] times in a row.
+#[derive(Debug, Default)]
+pub struct BreakIn {
+    enters: u8,
+}
+
+impl BreakIn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the next byte received, returning `true` once the user broke in.
+    pub fn push(&mut self, byte: u8) -> bool {
+        match byte {
+            // Ctrl-C
+            0x03 => true,
+            b'\r' => {
+                self.enters = self.enters.saturating_add(1);
+                self.enters >= BREAK_IN_ENTERS
+            }
+            // Terminals may follow every carriage return with a line feed
+            b'\n' => false,
+            _ => {
+                self.enters = 0;
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Shared definitions for the esp-csi-cli-rs firmware and its host tools.
+//!
+//! The crate is `no_std` so the firmware can use it to encode records and to
+//! name its CLI commands, while host tools enable the `std` feature to decode
+//! the serial stream and to build command lines.
+//!
+//! - [`record`]: the CSI record emitted for every received frame.
+//! - [`event`]: out-of-band events interleaved with the records.
+//! - [`format`]: encoders and the line decoder for every [`OutputFormat`].
+//! - [`command`]: the CLI command and argument schema.
+//! - [`config`]: the keys of the `show-config` dump.
+//! - `capture` (`std` only): the capture container written by the host tools.
+//! - [`schedule`]: the state machine behind periodic collection windows.
+//! - [`state`]: the collection state reported by `status`.
+//! - [`trigger`]: trigger conditions and the pre-trigger ring buffer.
+//! - [`gpio`]: the GPIO start/stop and event inputs.
+//! - [`headless`]: stored headless settings and breaking into the CLI.
+//! - [`filter`]: the filters frames pass before output.
+//! - [`frame`]: the 802.11 MAC header of received frames.
+//! - [`channel`]: channel selection and the country it is checked against.
+//! - [`ppdu`]: the PHY format of received frames.
+
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+/// Declares a fieldless enum whose variants are spelled as fixed keywords on
+/// the command line and in the stream, together with `as_str`, `Display`,
+/// `FromStr` and an `ALL` list of variants.
+macro_rules! keyword_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident => $keyword:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)+
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant,)+];
+
+            /// Keyword used on the command line and in the stream.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $keyword,)+
+                }
+            }
+        }
+
+        impl core::fmt::Display for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl core::str::FromStr for $name {
+            type Err = ();
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::ALL.iter().copied().find(|v| v.as_str() == s).ok_or(())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+pub mod capture;
+pub mod channel;
+pub mod command;
+pub mod config;
+pub mod event;
+pub mod filter;
+pub mod format;
+pub mod frame;
+pub mod gpio;
+pub mod headless;
+pub mod ppdu;
+pub mod record;
+pub mod schedule;
+pub mod state;
+pub mod trigger;
+
+pub use command::Command;
+pub use event::Event;
+pub use format::{Decoded, Decoder, OutputFormat};
+pub use record::{CsiRecord, MacAddr};
@@ -0,0 +1,138 @@
+//! PPDU format of received frames, as reported in the receive metadata.
+//!
+//! The length and layout of a CSI buffer depend on the PHY the frame was sent
+//! with, its bandwidth and whether it used STBC. Selecting on these keeps the
+//! records of a run the same shape.
+
+use core::fmt;
+use core::str::FromStr;
+
+use heapless::Vec;
+
+keyword_enum! {
+    /// PHY a frame was sent with.
+    pub enum SigMode {
+        /// 802.11b/g.
+        Legacy => "legacy",
+        /// 802.11n.
+        Ht => "ht",
+        /// 802.11ax.
+        He => "he",
+    }
+}
+
+impl SigMode {
+    /// Reads the `sig_mode` of a record received on `chip`. The ESP32-C6
+    /// reports the baseband format there instead of the classic signal mode.
+    /// `None` for VHT, which no supported chip receives.
+    pub fn from_rx_ctrl(sig_mode: u8, chip: &str) -> Option<Self> {
+        match (chip, sig_mode) {
+            ("esp32c6", 0 | 1) => Some(SigMode::Legacy),
+            ("esp32c6", 2) => Some(SigMode::Ht),
+            ("esp32c6", 4..=7) => Some(SigMode::He),
+            ("esp32c6", _) => None,
+            (_, 0) => Some(SigMode::Legacy),
+            (_, 1) => Some(SigMode::Ht),
+            _ => None,
+        }
+    }
+}
+
+keyword_enum! {
+    /// Channel width a frame was sent with, in MHz.
+    pub enum Bandwidth {
+        Mhz20 => "20",
+        Mhz40 => "40",
+    }
+}
+
+impl Bandwidth {
+    /// Reads the `bandwidth` of a record.
+    pub fn from_rx_ctrl(bandwidth: u8) -> Self {
+        match bandwidth {
+            0 => Bandwidth::Mhz20,
+            _ => Bandwidth::Mhz40,
+        }
+    }
+}
+
+keyword_enum! {
+    /// Whether frames must, or must not, have been sent with STBC.
+    #[derive(Default)]
+    pub enum Stbc {
+        #[default]
+        Any => "any",
+        On => "on",
+        Off => "off",
+    }
+}
+
+impl Stbc {
+    /// Whether a record with the given `stbc` passes.
+    pub fn matches(&self, stbc: u8) -> bool {
+        match self {
+            Stbc::Any => true,
+            Stbc::On => stbc != 0,
+            Stbc::Off => stbc == 0,
+        }
+    }
+}
+
+/// Comma separated [`SigMode`]s, written as `any` when empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SigModes(pub Vec<SigMode, 3>);
+
+/// Comma separated [`Bandwidth`]s, written as `any` when empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bandwidths(pub Vec<Bandwidth, 2>);
+
+macro_rules! keyword_list {
+    ($list:ident, $item:ident) => {
+        impl $list {
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            /// Whether `item` passes, always true for an empty list.
+            pub fn matches(&self, item: Option<$item>) -> bool {
+                self.is_empty() || item.is_some_and(|i| self.0.contains(&i))
+            }
+        }
+
+        impl fmt::Display for $list {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if self.0.is_empty() {
+                    return f.write_str("any");
+                }
+                for (i, item) in self.0.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl FromStr for $list {
+            type Err = ();
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut list = $list::default();
+                if s == "any" {
+                    return Ok(list);
+                }
+                for part in s.split(',') {
+                    let item = part.parse()?;
+                    if !list.0.contains(&item) {
+                        list.0.push(item).map_err(|_| ())?;
+                    }
+                }
+                Ok(list)
+            }
+        }
+    };
+}
+
+keyword_list!(SigModes, SigMode);
+keyword_list!(Bandwidths, Bandwidth);
@@ -0,0 +1,98 @@
+//! The CSI record emitted for every received frame.
+
+use core::fmt;
+use core::str::FromStr;
+
+/// Largest CSI buffer reported by any supported chip (HT40 with STBC).
+pub const MAX_CSI_LEN: usize = 612;
+
+/// A 48-bit IEEE MAC address, formatted as `AA:BB:CC:DD:EE:FF`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = &self.0;
+        write!(
+            f,
+            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+            m[0], m[1], m[2], m[3], m[4], m[5]
+        )
+    }
+}
+
+impl FromStr for MacAddr {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mac = [0u8; 6];
+        let mut parts = s.split(':');
+        for byte in mac.iter_mut() {
+            let part = parts.next().ok_or(())?;
+            if part.len() != 2 {
+                return Err(());
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| ())?;
+        }
+        match parts.next() {
+            Some(_) => Err(()),
+            None => Ok(MacAddr(mac)),
+        }
+    }
+}
+
+/// One CSI measurement together with the receive metadata reported by the
+/// WiFi driver (`wifi_pkt_rx_ctrl_t`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsiRecord {
+    /// Position of the record within the current collection run, starting at 0.
+    /// Gaps indicate records lost between the device and the host.
+    pub seq: u32,
+    /// Transmitter address of the frame.
+    pub mac: MacAddr,
+    pub rssi: i8,
+    pub rate: u8,
+    pub sig_mode: u8,
+    pub mcs: u8,
+    pub bandwidth: u8,
+    pub smoothing: u8,
+    pub not_sounding: u8,
+    pub aggregation: u8,
+    pub stbc: u8,
+    pub fec_coding: u8,
+    pub sgi: u8,
+    pub noise_floor: i8,
+    pub ampdu_cnt: u8,
+    pub channel: u8,
+    pub secondary_channel: u8,
+    /// Local receive time in microseconds.
+    pub timestamp: u32,
+    pub ant: u8,
+    pub sig_len: u16,
+    pub rx_state: u8,
+    /// Raw CSI buffer: one (imaginary, real) byte pair per subcarrier.
+    pub csi: heapless::Vec<i8, MAX_CSI_LEN>,
+}
+
+impl CsiRecord {
+    /// Amplitude of each subcarrier.
+    pub fn amplitudes(&self) -> impl Iterator<Item = f32> + '_ {
+        self.csi.chunks_exact(2).map(|pair| {
+            let (im, re) = (pair[0] as f32, pair[1] as f32);
+            sqrt(im * im + re * re)
+        })
+    }
+}
+
+// `f32::sqrt` lives in `std`. An exponent-halving initial guess refined by a
+// few Newton steps is plenty for amplitudes.
+fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let mut y = f32::from_bits((x.to_bits() >> 1) + 0x1fbd_1df5);
+    for _ in 0..3 {
+        y = 0.5 * (y + x / y);
+    }
+    y
+}
@@ -0,0 +1,170 @@
+//! Periodic collection windows.
+//!
+//! [`Scheduler`] is a pure state machine driven by the caller's clock: every
+//! method takes the current time in milliseconds, so the firmware feeds it
+//! the embassy clock and tests feed it a fake one.
+
+use core::fmt;
+
+/// Longest delay, period or duration accepted, a year.
+pub const MAX_SCHEDULE_S: u64 = 365 * 24 * 3600;
+
+/// Collection windows of `duration_s` seconds, the first one starting
+/// `start_in_s` seconds after the schedule is set and the following ones
+/// every `every_s` seconds, `count` times or indefinitely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    pub start_in_s: u64,
+    pub every_s: u64,
+    pub duration_s: u64,
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// Windows must last at least a second.
+    ZeroDuration,
+    /// Windows would overlap.
+    DurationExceedsPeriod,
+    ZeroCount,
+    /// A delay, period or duration exceeds [`MAX_SCHEDULE_S`].
+    TooLong,
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::ZeroDuration => f.write_str("duration must be at least 1s"),
+            ScheduleError::DurationExceedsPeriod => {
+                f.write_str("duration must not exceed the period")
+            }
+            ScheduleError::ZeroCount => f.write_str("count must be at least 1"),
+            ScheduleError::TooLong => write!(f, "times must not exceed {}s", MAX_SCHEDULE_S),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScheduleError {}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<(), ScheduleError> {
+        if self.duration_s == 0 {
+            return Err(ScheduleError::ZeroDuration);
+        }
+        if self.duration_s > self.every_s {
+            return Err(ScheduleError::DurationExceedsPeriod);
+        }
+        if self.count == Some(0) {
+            return Err(ScheduleError::ZeroCount);
+        }
+        if [self.start_in_s, self.every_s, self.duration_s]
+            .iter()
+            .any(|s| *s > MAX_SCHEDULE_S)
+        {
+            return Err(ScheduleError::TooLong);
+        }
+        Ok(())
+    }
+}
+
+/// What the collector should do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Stay idle until `until_ms`, then ask again.
+    Wait { until_ms: u64 },
+    /// Collect window `window` (counted from 0) until `until_ms`.
+    Collect { window: u32, until_ms: u64 },
+    /// Every window has been served.
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheduler {
+    schedule: Schedule,
+    first_start_ms: u64,
+    /// Index of the next window to hand out.
+    next_window: u32,
+    /// Windows that passed entirely while the collector was busy.
+    missed: u32,
+}
+
+impl Scheduler {
+    /// Starts `schedule` at `now_ms`. The schedule must be valid. Times past
+    /// the range of the clock saturate, so such windows never open.
+    pub fn new(schedule: Schedule, now_ms: u64) -> Self {
+        Self {
+            schedule,
+            first_start_ms: now_ms.saturating_add(schedule.start_in_s.saturating_mul(1000)),
+            next_window: 0,
+            missed: 0,
+        }
+    }
+
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// Windows handed out so far.
+    pub fn started(&self) -> u32 {
+        self.next_window - self.missed
+    }
+
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    fn start_ms(&self, window: u32) -> u64 {
+        let period_ms = self.schedule.every_s.saturating_mul(1000);
+        self.first_start_ms
+            .saturating_add((window as u64).saturating_mul(period_ms))
+    }
+
+    fn end_ms(&self, window: u32) -> u64 {
+        self.start_ms(window)
+            .saturating_add(self.schedule.duration_s.saturating_mul(1000))
+    }
+
+    fn exhausted(&self) -> bool {
+        self.schedule.count.is_some_and(|c| self.next_window >= c)
+    }
+
+    /// Start time of the next window, or `None` once finished.
+    pub fn next_start_ms(&self) -> Option<u64> {
+        (!self.exhausted()).then(|| self.start_ms(self.next_window))
+    }
+
+    /// Returns the next step at `now_ms`. A window is handed out once; when
+    /// asked late, the remainder of the current window is collected and
+    /// windows that already ended are counted as missed.
+    pub fn next(&mut self, now_ms: u64) -> Step {
+        if !self.exhausted() && now_ms >= self.end_ms(self.next_window) {
+            // First window still open at `now_ms`.
+            let period_ms = self.schedule.every_s.saturating_mul(1000);
+            let elapsed = now_ms
+                .saturating_sub(self.first_start_ms)
+                .saturating_sub(self.schedule.duration_s.saturating_mul(1000));
+            let open = (elapsed / period_ms + 1).min(u32::MAX as u64) as u32;
+            let open = match self.schedule.count {
+                Some(count) => open.min(count),
+                None => open,
+            };
+            self.missed += open - self.next_window;
+            self.next_window = open;
+        }
+        if self.exhausted() {
+            return Step::Finished;
+        }
+
+        let window = self.next_window;
+        let start_ms = self.start_ms(window);
+        if now_ms < start_ms {
+            return Step::Wait { until_ms: start_ms };
+        }
+        self.next_window += 1;
+        Step::Collect {
+            window,
+            until_ms: self.end_ms(window),
+        }
+    }
+}
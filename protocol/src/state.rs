@@ -0,0 +1,136 @@
+//! Collection state machine.
+//!
+//! The firmware tracks what the collector is doing with a [`State`], checks
+//! operator commands against it with [`State::accepts`], and reports it on
+//! `status` as `key: value` lines using the keys in [`key`].
+
+use core::fmt;
+
+keyword_enum! {
+    pub enum State {
+        Idle => "idle",
+        /// A start was accepted and the collector has not picked it up yet.
+        Starting => "starting",
+        /// Setting up the WiFi stack.
+        Initialising => "initialising",
+        /// Started in a station mode and waiting for the first frame, which
+        /// arrives once the station is associated.
+        Connecting => "connecting",
+        Collecting => "collecting",
+        /// Started, with CSI output suppressed.
+        Paused => "paused",
+        /// Leaving a collection run.
+        Stopping => "stopping",
+        /// Setting up the WiFi stack failed. The next start retries it.
+        Error => "error",
+    }
+}
+
+keyword_enum! {
+    /// Operator commands that change the state.
+    pub enum Request {
+        Start => "start",
+        Stop => "stop",
+        Pause => "pause",
+        Resume => "resume",
+        Trigger => "trigger",
+    }
+}
+
+/// `request` is not allowed in `state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rejected {
+    pub state: State,
+    pub request: Request,
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot {} while {}", self.request, self.state)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Rejected {}
+
+impl State {
+    /// Whether a collection run is underway or being set up, in which case
+    /// settings used to set up WiFi cannot change.
+    pub fn is_active(self) -> bool {
+        !matches!(self, State::Idle | State::Error)
+    }
+
+    /// Whether the collector may move from `self` to `to`.
+    pub fn can_become(self, to: State) -> bool {
+        use State::*;
+        match self {
+            // Scheduled windows start without a start request
+            Idle | Error => matches!(to, Starting | Initialising | Connecting | Collecting),
+            Starting => matches!(to, Initialising | Connecting | Collecting),
+            Initialising => matches!(to, Connecting | Collecting | Error),
+            Connecting => matches!(to, Collecting | Stopping),
+            Collecting => matches!(to, Paused | Stopping),
+            Paused => matches!(to, Collecting | Stopping),
+            Stopping => to == Idle,
+        }
+    }
+
+    /// Checks an operator request against the current state.
+    pub fn accepts(self, request: Request) -> Result<(), Rejected> {
+        let allowed = match request {
+            Request::Start => matches!(self, State::Idle | State::Error),
+            Request::Stop => matches!(self, State::Connecting | State::Collecting | State::Paused),
+            Request::Pause => self == State::Collecting,
+            Request::Resume => self == State::Paused,
+            Request::Trigger => {
+                matches!(self, State::Connecting | State::Collecting | State::Paused)
+            }
+        };
+        match allowed {
+            true => Ok(()),
+            false => Err(Rejected {
+                state: self,
+                request,
+            }),
+        }
+    }
+}
+
+/// Keys of the `status` report.
+pub mod key {
+    pub const STATE: &str = "State";
+    /// Seconds since the run started, value carries an `s` suffix.
+    pub const ELAPSED: &str = "Elapsed";
+    /// Seconds until the run ends, value carries an `s` suffix.
+    pub const REMAINING: &str = "Remaining";
+    pub const RECORDS: &str = "Records";
+    /// Channel of the last received frame.
+    pub const CHANNEL: &str = "Channel";
+    pub const ASSOCIATION: &str = "Association";
+    pub const LAST_ERROR: &str = "Last Error";
+    /// `off`, `armed` or `fired` followed by the cause.
+    pub const TRIGGER: &str = "Trigger";
+    /// Label set with `label set`, or `none`.
+    pub const LABEL: &str = "Label";
+    // Filter counters of the current or last run
+    pub const FILTER_ALLOWED: &str = "Filter Allowed";
+    pub const FILTER_DENIED: &str = "Filter Denied";
+    pub const FILTER_NOT_ALLOWED: &str = "Filter Not Allowed";
+    pub const FILTER_RSSI_REJECTED: &str = "Filter RSSI Rejected";
+    pub const FILTER_FRAME_TYPE_REJECTED: &str = "Filter Frame Type Rejected";
+    pub const FILTER_BSS_REJECTED: &str = "Filter BSS Rejected";
+    pub const FILTER_NOT_REPLY: &str = "Filter Not Reply";
+    pub const FILTER_PPDU_REJECTED: &str = "Filter PPDU Rejected";
+    pub const FILTER_DUPLICATES: &str = "Filter Duplicates";
+    /// Frames the duplicate check let through without a header to check.
+    pub const FILTER_UNCHECKED: &str = "Filter Unchecked";
+    pub const FILTER_RATE_SHED: &str = "Filter Rate Shed";
+    /// Frames among the rate shed that the global limit shed.
+    pub const FILTER_GLOBAL_SHED: &str = "Filter Global Shed";
+    /// Followed by a space and the transmitter MAC, one line for each
+    /// transmitter that lost frames to the rate limits.
+    pub const FILTER_SHED_BY: &str = "Filter Shed";
+    /// BSSID frames are kept for, or `none` while the SSID has not been
+    /// heard or no BSS is selected.
+    pub const FILTER_BSSID: &str = "Filter BSSID";
+}
@@ -0,0 +1,180 @@
+//! Triggered capture.
+//!
+//! While armed, records are held back in a ring buffer of the most recent
+//! [`TriggerConfig::pre_records`] records. Once a condition fires, or a
+//! manual `trigger` is issued, the buffer is flushed and records pass
+//! straight through for the post-trigger duration.
+
+use heapless::Deque;
+
+use crate::record::{CsiRecord, MacAddr};
+
+/// Capacity of the pre-trigger ring buffer, in records.
+pub const MAX_PRE_RECORDS: usize = 32;
+
+/// Number of recent frames the amplitude variance is computed over.
+pub const VARIANCE_WINDOW: usize = 16;
+
+keyword_enum! {
+    /// What fired a trigger.
+    pub enum Cause {
+        Rssi => "rssi",
+        Mac => "mac",
+        Variance => "variance",
+        Manual => "manual",
+    }
+}
+
+/// Trigger conditions. Conditions left out never fire; with none set, only a
+/// manual trigger does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriggerConfig {
+    /// Fires on a frame at or above this RSSI, in dBm.
+    pub min_rssi: Option<i8>,
+    /// Fires on the first frame from this transmitter.
+    pub mac: Option<MacAddr>,
+    /// Fires once the variance of the mean frame amplitude over the last
+    /// [`VARIANCE_WINDOW`] frames reaches this level.
+    pub min_variance: Option<f32>,
+    /// Records emitted from before the trigger, at most [`MAX_PRE_RECORDS`].
+    pub pre_records: usize,
+    /// Seconds collection continues for after the trigger.
+    pub post_s: u64,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            min_rssi: None,
+            mac: None,
+            min_variance: None,
+            pre_records: 10,
+            post_s: 10,
+        }
+    }
+}
+
+/// What to do with a record fed to [`Trigger::push`].
+// The record is handed straight back to be emitted, boxing it would need an allocator.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq)]
+pub enum Feed {
+    /// Held back in the buffer.
+    Buffered,
+    /// Buffered, and the trigger fired. Flush the buffer.
+    Fired(Cause),
+    /// Emit the record as is.
+    Pass(CsiRecord),
+}
+
+/// Trigger state for one collection run.
+#[derive(Debug)]
+pub struct Trigger {
+    config: TriggerConfig,
+    buffer: Deque<CsiRecord, MAX_PRE_RECORDS>,
+    means: Deque<f32, VARIANCE_WINDOW>,
+    fired: Option<Cause>,
+}
+
+impl Trigger {
+    pub fn new(mut config: TriggerConfig) -> Self {
+        config.pre_records = config.pre_records.min(MAX_PRE_RECORDS);
+        Self {
+            config,
+            buffer: Deque::new(),
+            means: Deque::new(),
+            fired: None,
+        }
+    }
+
+    pub fn config(&self) -> &TriggerConfig {
+        &self.config
+    }
+
+    /// Cause of the trigger, once fired.
+    pub fn fired(&self) -> Option<Cause> {
+        self.fired
+    }
+
+    /// Records waiting in the buffer.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Feeds the next received record. Records keep being buffered after the
+    /// trigger until the buffer has been drained, so they are emitted in order.
+    pub fn push(&mut self, record: CsiRecord) -> Feed {
+        if self.fired.is_some() {
+            if self.buffer.is_empty() {
+                return Feed::Pass(record);
+            }
+            self.buffer_record(record);
+            return Feed::Buffered;
+        }
+
+        let cause = self.check(&record);
+        self.buffer_record(record);
+        if let Some(cause) = cause {
+            self.fired = Some(cause);
+            return Feed::Fired(cause);
+        }
+        // Only the requested pre-trigger depth is kept while armed.
+        while self.buffer.len() > self.config.pre_records {
+            self.buffer.pop_front();
+        }
+        Feed::Buffered
+    }
+
+    /// Fires the trigger by hand. Returns `false` if it already fired.
+    pub fn fire(&mut self) -> bool {
+        if self.fired.is_some() {
+            return false;
+        }
+        self.fired = Some(Cause::Manual);
+        true
+    }
+
+    /// Takes the oldest buffered record, to be emitted once the trigger fired.
+    pub fn pop(&mut self) -> Option<CsiRecord> {
+        self.fired?;
+        self.buffer.pop_front()
+    }
+
+    fn buffer_record(&mut self, record: CsiRecord) {
+        // A full buffer while draining loses its oldest record.
+        if self.buffer.is_full() {
+            self.buffer.pop_front();
+        }
+        let _ = self.buffer.push_back(record);
+    }
+
+    fn check(&mut self, record: &CsiRecord) -> Option<Cause> {
+        if self.config.mac == Some(record.mac) {
+            return Some(Cause::Mac);
+        }
+        if self.config.min_rssi.is_some_and(|min| record.rssi >= min) {
+            return Some(Cause::Rssi);
+        }
+        let min_variance = self.config.min_variance?;
+        let (sum, count) = record
+            .amplitudes()
+            .fold((0.0, 0), |(sum, count), a| (sum + a, count + 1));
+        if count == 0 {
+            return None;
+        }
+        if self.means.is_full() {
+            self.means.pop_front();
+        }
+        let _ = self.means.push_back(sum / count as f32);
+        match self.means.is_full() && variance(&self.means) >= min_variance {
+            true => Some(Cause::Variance),
+            false => None,
+        }
+    }
+}
+
+fn variance(values: &Deque<f32, VARIANCE_WINDOW>) -> f32 {
+    let n = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / n;
+    values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / n
+}
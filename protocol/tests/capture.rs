@@ -0,0 +1,148 @@
+use std::io::{BufRead, Cursor, Seek, SeekFrom};
+
+use esp_csi_protocol::capture::{
+    CaptureReader, CaptureWriter, Entry, Header, ReadError, Source, INDEX_INTERVAL,
+};
+use esp_csi_protocol::{CsiRecord, Decoded, MacAddr, OutputFormat};
+
+fn record(seq: u32) -> CsiRecord {
+    CsiRecord {
+        seq,
+        mac: MacAddr([0x24, 0x0a, 0xc4, 0, 0, seq as u8]),
+        rssi: -40,
+        csi: heapless::Vec::from_slice(&[1, -2, 3, -4]).unwrap(),
+        ..CsiRecord::default()
+    }
+}
+
+fn header() -> Header {
+    let mut ap = Source::new("ap");
+    ap.add_dump([
+        "",
+        "Traffic Settings:",
+        "Chip: esp32c3",
+        "Station SSID: 'my net'",
+    ]);
+    Header {
+        started_unix_ms: 1_760_000_000_123,
+        sources: vec![ap, Source::new("sniffer")],
+    }
+}
+
+#[test]
+fn capture_roundtrip() {
+    let mut writer = CaptureWriter::new(Vec::new(), &header()).unwrap();
+    let text = record(1).display(OutputFormat::Text).to_string();
+    let csv = record(2).display(OutputFormat::Csv).to_string();
+    let mut lines = text.lines();
+    // Lines of the two sources interleave.
+    writer.line(0, 10, lines.next().unwrap()).unwrap();
+    writer.line(1, 11, &csv).unwrap();
+    for line in lines {
+        writer.line(0, 12, line).unwrap();
+    }
+    writer.label(13, "walking past").unwrap();
+    let file = writer.finish().unwrap();
+
+    let mut reader = CaptureReader::new(Cursor::new(file)).unwrap();
+    assert_eq!(reader.header(), &header());
+    assert_eq!(
+        reader.header().sources[0].get("Station SSID"),
+        Some("my net")
+    );
+
+    let mut entries = Vec::new();
+    while let Some(entry) = reader.next_entry().unwrap() {
+        entries.push(entry);
+    }
+    let decoded: Vec<_> = entries
+        .iter()
+        .filter_map(|e| match e {
+            Entry::Line {
+                source, decoded, ..
+            } => Some((*source, decoded.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        decoded,
+        vec![
+            (1, Decoded::Record(record(2))),
+            (0, Decoded::Record(record(1)))
+        ]
+    );
+    assert!(entries.contains(&Entry::Label {
+        host_us: 13,
+        label: "walking past".into()
+    }));
+}
+
+#[test]
+fn index_chain_covers_every_record() {
+    let total = INDEX_INTERVAL * 2 + 17;
+    let mut writer = CaptureWriter::new(Vec::new(), &header()).unwrap();
+    for seq in 0..total {
+        let line = record(seq as u32).display(OutputFormat::Csv).to_string();
+        writer.line(1, seq, &line).unwrap();
+    }
+    let mut file = Cursor::new(writer.finish().unwrap());
+
+    // Walk the index blocks backwards from the trailer.
+    let trailer = String::from_utf8(file.get_ref().clone()).unwrap();
+    let end = trailer.lines().last().unwrap();
+    let mut next = end
+        .split_whitespace()
+        .find_map(|f| f.strip_prefix("index="))
+        .unwrap()
+        .parse::<u64>()
+        .unwrap();
+    let mut records = 0;
+    let mut blocks = 0;
+    loop {
+        file.seek(SeekFrom::Start(next)).unwrap();
+        let mut line = String::new();
+        file.read_line(&mut line).unwrap();
+        let fields: Vec<(&str, u64)> = line
+            .trim_start_matches("#!index")
+            .split_whitespace()
+            .map(|f| {
+                let (k, v) = f.split_once('=').unwrap();
+                (k, v.parse().unwrap())
+            })
+            .collect();
+        let get = |key| fields.iter().find(|(k, _)| *k == key).unwrap().1;
+        records += get("records");
+        blocks += 1;
+
+        // The block starts with a record line of this source.
+        file.seek(SeekFrom::Start(get("offset"))).unwrap();
+        let mut first = String::new();
+        file.read_line(&mut first).unwrap();
+        assert!(first.starts_with(&format!("sniffer,{},CSI_DATA", get("first_us"))));
+
+        if get("prev") == 0 {
+            break;
+        }
+        next = get("prev");
+    }
+    assert_eq!(records, total);
+    assert_eq!(blocks, 3);
+}
+
+#[test]
+fn rejects_foreign_and_newer_files() {
+    assert!(matches!(
+        CaptureReader::new(Cursor::new("mac: 00:00:00:00:00:00\n")),
+        Err(ReadError::NotACapture)
+    ));
+    assert!(matches!(
+        CaptureReader::new(Cursor::new("#!csi-capture 2\n#!end-header\n")),
+        Err(ReadError::UnsupportedVersion(2))
+    ));
+    // Unknown directives are skipped.
+    let mut reader = CaptureReader::new(Cursor::new(
+        "#!csi-capture 1\n#!future-thing x\n#!source a\n#!end-header\n#!other\n#!end\n",
+    ))
+    .unwrap();
+    assert!(reader.next_entry().unwrap().is_none());
+}
@@ -0,0 +1,102 @@
+use esp_csi_protocol::channel::{
+    ChannelConfig, ChannelError, ChannelList, Country, HopConfig, Secondary,
+};
+
+fn country(s: &str) -> Country {
+    s.parse().unwrap()
+}
+
+fn channel(channel: u8, secondary: Secondary) -> ChannelConfig {
+    ChannelConfig { channel, secondary }
+}
+
+#[test]
+fn parses_country_codes() {
+    assert_eq!(country("us"), country("US"));
+    assert_eq!(country("us").to_string(), "US");
+    assert_eq!(country("01").max_channel(), 11);
+    assert_eq!(Country::default().to_string(), "CN");
+    assert_eq!(country("jp").max_channel(), 14);
+    assert_eq!(country("de").max_channel(), 13);
+    // Codes the driver does not know are rejected, not given 13 channels.
+    for s in ["", "U", "USA", "U-", "ZZ", "XX", "00"] {
+        assert!(s.parse::<Country>().is_err(), "{s}");
+    }
+}
+
+#[test]
+fn channels_are_checked_against_the_country() {
+    assert_eq!(
+        channel(13, Secondary::None).validate(&country("DE")),
+        Ok(())
+    );
+    assert_eq!(
+        channel(13, Secondary::None).validate(&country("US")),
+        Err(ChannelError::NotAllowed {
+            channel: 13,
+            country: country("US"),
+        })
+    );
+    assert_eq!(
+        channel(14, Secondary::None).validate(&country("JP")),
+        Ok(())
+    );
+    assert!(channel(14, Secondary::None)
+        .validate(&country("CN"))
+        .is_err());
+    assert!(channel(0, Secondary::None)
+        .validate(&country("CN"))
+        .is_err());
+}
+
+#[test]
+fn secondary_channel_must_be_allowed_too() {
+    let us = country("US");
+    assert_eq!(channel(7, Secondary::Above).validate(&us), Ok(()));
+    assert_eq!(channel(7, Secondary::Above).secondary_channel(), Some(11));
+    assert!(channel(8, Secondary::Above).validate(&us).is_err());
+    assert_eq!(channel(5, Secondary::Below).validate(&us), Ok(()));
+    assert_eq!(
+        channel(4, Secondary::Below).validate(&us),
+        Err(ChannelError::NoSecondary {
+            channel: 4,
+            secondary: Secondary::Below,
+        })
+    );
+    // Channel 14 is 802.11b only.
+    assert!(channel(14, Secondary::Below)
+        .validate(&country("JP"))
+        .is_err());
+    assert_eq!(
+        channel(9, Secondary::Above).validate(&country("JP")),
+        Ok(())
+    );
+}
+
+#[test]
+fn hop_channels_are_checked_against_the_country() {
+    let hop = |channels: &str, dwell_ms| HopConfig {
+        channels: channels.parse().unwrap(),
+        dwell_ms,
+    };
+    assert_eq!(hop("1,6,11", 200).validate(&country("US")), Ok(()));
+    assert!(hop("1,6,13", 200).validate(&country("US")).is_err());
+    assert_eq!(
+        hop("1", 5).validate(&country("US")),
+        Err(ChannelError::ShortDwell)
+    );
+    assert_eq!(HopConfig::default().validate(&country("US")), Ok(()));
+    assert!(HopConfig::default().channels.is_empty());
+}
+
+#[test]
+fn channel_lists_roundtrip() {
+    let list: ChannelList = "1,6,11".parse().unwrap();
+    assert_eq!(list.0.as_slice(), [1, 6, 11]);
+    assert_eq!(list.to_string(), "1,6,11");
+    assert_eq!(ChannelList::default().to_string(), "none");
+    assert_eq!("none".parse::<ChannelList>(), Ok(ChannelList::default()));
+    for s in ["", "1,,6", "1,x", "256"] {
+        assert!(s.parse::<ChannelList>().is_err(), "{s}");
+    }
+}
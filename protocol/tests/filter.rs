@@ -0,0 +1,431 @@
+use esp_csi_protocol::filter::{
+    Filter, FilterConfig, FilterError, FilterStats, MacList, MacPattern, TokenBucket,
+    MAX_DEDUP_WINDOW, MAX_SOURCES,
+};
+use esp_csi_protocol::frame::{FrameControl, FrameHeader, SequenceControl};
+use esp_csi_protocol::ppdu::{Bandwidths, SigModes, Stbc};
+use esp_csi_protocol::{CsiRecord, MacAddr};
+
+fn from(mac: &str) -> CsiRecord {
+    CsiRecord {
+        mac: mac.parse().unwrap(),
+        ..Default::default()
+    }
+}
+
+fn pattern(s: &str) -> MacPattern {
+    s.parse().unwrap()
+}
+
+#[test]
+fn patterns_match_exactly_by_wildcard_or_prefix() {
+    let mac: MacAddr = "24:0A:C4:12:34:56".parse().unwrap();
+    assert!(pattern("24:0A:C4:12:34:56").matches(&mac));
+    assert!(pattern("24:0a:c4:12:34:56").matches(&mac));
+    assert!(!pattern("24:0A:C4:12:34:57").matches(&mac));
+    assert!(pattern("24:0A:C4:*:*:56").matches(&mac));
+    assert!(!pattern("24:0A:C5:*:*:56").matches(&mac));
+    assert!(pattern("24:0A:C4").matches(&mac), "OUI prefix");
+    assert!(!pattern("24:0A:C5").matches(&mac));
+    assert!(pattern("*").matches(&mac));
+    assert!(MacPattern::from(mac).matches(&mac));
+}
+
+#[test]
+fn rejects_malformed_patterns() {
+    for s in [
+        "",
+        "24:0A:C4:12:34:56:78",
+        "24:0A:C",
+        "24::C4",
+        "2G:0A",
+        "24:0A:**",
+    ] {
+        assert!(s.parse::<MacPattern>().is_err(), "{s}");
+    }
+    let too_many = ["AA"; 9].join(",");
+    assert!(too_many.parse::<MacList>().is_err());
+}
+
+#[test]
+fn patterns_display_canonically() {
+    for (input, shown) in [
+        ("24:0a:c4:12:34:56", "24:0A:C4:12:34:56"),
+        ("24:0a:c4:*:*:*", "24:0A:C4"),
+        ("24:*:c4:*:*:01", "24:*:C4:*:*:01"),
+        ("*:*", "*"),
+    ] {
+        assert_eq!(pattern(input).to_string(), shown);
+        assert_eq!(pattern(shown), pattern(input));
+    }
+    let list: MacList = "24:0A:C4,*:*:*:*:*:01".parse().unwrap();
+    assert_eq!(list.to_string(), "24:0A:C4,*:*:*:*:*:01");
+    assert_eq!("none".parse::<MacList>(), Ok(MacList::default()));
+    assert_eq!(MacList::default().to_string(), "none");
+}
+
+#[test]
+fn deny_wins_over_allow_and_counts_each_outcome() {
+    let mut filter = Filter::new(FilterConfig {
+        allow_mac: "24:0A:C4".parse().unwrap(),
+        deny_mac: "24:0A:C4:00:00:02".parse().unwrap(),
+        ..Default::default()
+    });
+    assert!(filter.check(&from("24:0A:C4:00:00:01"), None));
+    assert!(!filter.check(&from("24:0A:C4:00:00:02"), None));
+    assert!(!filter.check(&from("AA:BB:CC:00:00:01"), None));
+    assert!(!filter.check(&from("AA:BB:CC:00:00:02"), None));
+    assert_eq!(
+        *filter.stats(),
+        FilterStats {
+            allowed: 1,
+            denied: 1,
+            not_allowed: 2,
+            ..Default::default()
+        }
+    );
+
+    filter.reset_stats();
+    assert_eq!(*filter.stats(), FilterStats::default());
+}
+
+#[test]
+fn empty_lists_let_everything_through() {
+    let mut filter = Filter::default();
+    assert!(filter.check(&from("AA:BB:CC:DD:EE:FF"), None));
+    assert_eq!(*filter.stats(), FilterStats::default());
+
+    // A deny list alone keeps everything else.
+    filter.set_config(FilterConfig {
+        deny_mac: "AA:BB:CC".parse().unwrap(),
+        ..Default::default()
+    });
+    assert!(!filter.check(&from("AA:BB:CC:DD:EE:FF"), None));
+    assert!(filter.check(&from("11:22:33:44:55:66"), None));
+    assert_eq!(filter.stats().denied, 1);
+}
+
+#[test]
+fn rssi_window_is_inclusive() {
+    let mut filter = Filter::new(FilterConfig {
+        min_rssi: Some(-70),
+        max_rssi: Some(-30),
+        ..Default::default()
+    });
+    let rssi = |rssi| CsiRecord {
+        rssi,
+        ..Default::default()
+    };
+    assert!(filter.check(&rssi(-70), None));
+    assert!(filter.check(&rssi(-30), None));
+    assert!(!filter.check(&rssi(-71), None));
+    assert!(!filter.check(&rssi(-29), None));
+    assert_eq!(filter.stats().rssi_rejected, 2);
+
+    // Frames dropped by the MAC lists are not counted again.
+    filter.set_config(FilterConfig {
+        deny_mac: "AA:BB:CC".parse().unwrap(),
+        min_rssi: Some(-70),
+        ..Default::default()
+    });
+    assert!(!filter.check(&from("AA:BB:CC:DD:EE:FF"), None));
+    assert_eq!(filter.stats().rssi_rejected, 2);
+}
+
+#[test]
+fn rejects_an_empty_rssi_window() {
+    let mut config = FilterConfig {
+        min_rssi: Some(-40),
+        max_rssi: Some(-40),
+        ..Default::default()
+    };
+    assert_eq!(config.validate(), Ok(()));
+    config.max_rssi = Some(-41);
+    assert_eq!(config.validate(), Err(FilterError::RssiRange));
+}
+
+fn at(mac: &str, timestamp: u32) -> CsiRecord {
+    CsiRecord {
+        timestamp,
+        ..from(mac)
+    }
+}
+
+#[test]
+fn token_bucket_allows_bursts_then_the_rate() {
+    let mut bucket = TokenBucket::new(10);
+    // A full bucket lets a second's worth through at once.
+    assert_eq!((0..20).filter(|_| bucket.take(0)).count(), 10);
+    // A token every 100 ms after that.
+    assert!(!bucket.take(50_000));
+    assert!(bucket.take(100_000));
+    assert!(!bucket.take(100_000));
+    // Out of order timestamps add no tokens.
+    assert!(!bucket.take(90_000));
+    // Wrapping receive timestamps keep counting.
+    let mut bucket = TokenBucket::new(1);
+    assert!(bucket.take(u32::MAX - 10));
+    assert!(!bucket.take(u32::MAX));
+    assert!(bucket.take(999_990));
+}
+
+#[test]
+fn rate_limits_shed_per_source() {
+    let mut filter = Filter::new(FilterConfig {
+        max_rate_per_mac: Some(2),
+        ..Default::default()
+    });
+    for _ in 0..5 {
+        filter.check(&at("AA:BB:CC:00:00:01", 0), None);
+    }
+    assert!(filter.check(&at("AA:BB:CC:00:00:02", 0), None));
+    assert_eq!(filter.stats().rate_shed, 3);
+    let shed: Vec<_> = filter.shed_by_source().collect();
+    assert_eq!(shed, [("AA:BB:CC:00:00:01".parse().unwrap(), 3)]);
+
+    // The global limit sheds whichever source comes last, without charging it.
+    filter.set_config(FilterConfig {
+        max_rate: Some(1),
+        ..Default::default()
+    });
+    filter.reset_stats();
+    assert!(filter.check(&at("AA:BB:CC:00:00:01", 0), None));
+    assert!(!filter.check(&at("AA:BB:CC:00:00:02", 0), None));
+    assert!(filter.check(&at("AA:BB:CC:00:00:02", 1_000_000), None));
+    assert_eq!(filter.shed_by_source().count(), 0);
+    assert_eq!(filter.stats().global_shed, 1);
+}
+
+#[test]
+fn global_limit_leaves_the_source_budget_alone() {
+    let mut filter = Filter::new(FilterConfig {
+        max_rate: Some(2),
+        max_rate_per_mac: Some(2),
+        ..Default::default()
+    });
+    assert!(filter.check(&at("AA:BB:CC:00:00:01", 0), None));
+    assert!(filter.check(&at("AA:BB:CC:00:00:02", 0), None));
+    // The global bucket is empty while 02 still has a token.
+    assert!(!filter.check(&at("AA:BB:CC:00:00:02", 0), None));
+    assert_eq!(filter.stats().global_shed, 1);
+    assert_eq!(filter.stats().rate_shed, 1);
+    assert_eq!(filter.shed_by_source().count(), 0);
+    // Half a second later the global bucket has one token back, and 02 still
+    // has the token the shed frame did not take.
+    assert!(filter.check(&at("AA:BB:CC:00:00:02", 500_000), None));
+    assert!(!filter.check(&at("AA:BB:CC:00:00:01", 500_000), None));
+    assert_eq!(filter.stats().global_shed, 2);
+    assert_eq!(filter.shed_by_source().count(), 0);
+}
+
+#[test]
+fn least_recent_source_makes_room() {
+    let mut filter = Filter::new(FilterConfig {
+        max_rate_per_mac: Some(1),
+        ..Default::default()
+    });
+    for i in 0..MAX_SOURCES as u32 {
+        let mac = format!("AA:BB:CC:00:00:{:02X}", i);
+        filter.check(&at(&mac, i), None);
+        filter.check(&at(&mac, i), None);
+    }
+    assert_eq!(filter.shed_by_source().count(), MAX_SOURCES);
+    // Source 00 was heard from least recently.
+    filter.check(&at("11:22:33:44:55:66", 100), None);
+    assert!(!filter
+        .shed_by_source()
+        .any(|(mac, _)| mac == "AA:BB:CC:00:00:00".parse().unwrap()));
+    assert_eq!(filter.stats().rate_shed, MAX_SOURCES as u32);
+}
+
+#[test]
+fn rejects_a_zero_rate() {
+    let config = FilterConfig {
+        max_rate: Some(0),
+        ..Default::default()
+    };
+    assert_eq!(config.validate(), Err(FilterError::ZeroRate));
+}
+
+#[test]
+fn ppdu_stage_reads_sig_mode_by_chip() {
+    let config = FilterConfig {
+        sig_mode: "ht,he".parse().unwrap(),
+        bandwidth: "20".parse().unwrap(),
+        stbc: Stbc::Off,
+        ..Default::default()
+    };
+    let ppdu = |sig_mode, bandwidth, stbc| CsiRecord {
+        sig_mode,
+        bandwidth,
+        stbc,
+        ..Default::default()
+    };
+    let mut filter = Filter::new(config.clone());
+    assert!(filter.check(&ppdu(1, 0, 0), None));
+    assert!(!filter.check(&ppdu(0, 0, 0), None), "legacy");
+    assert!(!filter.check(&ppdu(1, 1, 0), None), "40 MHz");
+    assert!(!filter.check(&ppdu(1, 0, 1), None), "STBC");
+    assert_eq!(filter.stats().ppdu_rejected, 3);
+
+    // The ESP32-C6 reports the baseband format instead.
+    let mut filter = Filter::for_chip("esp32c6");
+    filter.set_config(config);
+    assert!(!filter.check(&ppdu(1, 0, 0), None), "11g");
+    assert!(filter.check(&ppdu(2, 0, 0), None));
+    assert!(filter.check(&ppdu(4, 0, 0), None));
+}
+
+#[test]
+fn ppdu_lists_parse_and_display() {
+    assert_eq!(SigModes::default().to_string(), "any");
+    assert_eq!("any".parse::<Bandwidths>(), Ok(Bandwidths::default()));
+    let modes: SigModes = "legacy,ht,ht".parse().unwrap();
+    assert_eq!(modes.to_string(), "legacy,ht");
+    assert_eq!("40,20".parse::<Bandwidths>().unwrap().to_string(), "40,20");
+    assert!("80".parse::<Bandwidths>().is_err());
+    assert!("vht".parse::<SigModes>().is_err());
+}
+
+fn sent(sequence: u16, retry: bool) -> FrameHeader {
+    FrameHeader {
+        frame_control: FrameControl(0x0088 | (retry as u16) << 11),
+        sequence: Some(SequenceControl(sequence)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn dedup_drops_retries_of_recent_frames() {
+    let mut filter = Filter::new(FilterConfig {
+        dedup: Some(2),
+        ..Default::default()
+    });
+    let a = from("AA:BB:CC:00:00:01");
+    let b = from("AA:BB:CC:00:00:02");
+    assert!(filter.check(&a, Some(&sent(0x10, false))));
+    assert!(!filter.check(&a, Some(&sent(0x10, true))));
+    // Same sequence control from another transmitter, or another fragment.
+    assert!(filter.check(&b, Some(&sent(0x10, true))));
+    assert!(filter.check(&a, Some(&sent(0x11, true))));
+    // A repeat without the retry bit is a new frame after the counter wrapped.
+    assert!(filter.check(&a, Some(&sent(0x11, false))));
+    assert_eq!(filter.stats().duplicates, 1);
+
+    // Only the last two frames are remembered, so b's is forgotten.
+    assert!(filter.check(&a, Some(&sent(0x12, false))));
+    assert!(filter.check(&b, Some(&sent(0x10, true))));
+    assert_eq!(filter.stats().duplicates, 1);
+    assert_eq!(filter.stats().unchecked, 0);
+}
+
+#[test]
+fn dedup_passes_frames_it_cannot_check() {
+    let mut filter = Filter::new(FilterConfig {
+        dedup: Some(4),
+        ..Default::default()
+    });
+    let a = from("AA:BB:CC:00:00:01");
+    // The header was missed, or it is a control frame without a sequence number.
+    assert!(filter.check(&a, None));
+    let ack = FrameHeader::parse(&[0xd4, 0x00, 0, 0, 1, 2, 3, 4, 5, 6]).unwrap();
+    assert!(filter.check(&a, Some(&ack)));
+    // A retry of a frame whose header was missed is not recognised either.
+    assert!(filter.check(&a, Some(&sent(0x10, true))));
+    assert_eq!(filter.stats().unchecked, 2);
+    assert_eq!(filter.stats().duplicates, 0);
+    // Frames are only counted while the check is on.
+    filter.set_config(FilterConfig::default());
+    filter.reset_stats();
+    assert!(filter.check(&a, None));
+    assert_eq!(filter.stats().unchecked, 0);
+}
+
+#[test]
+fn rejects_a_dedup_window_out_of_range() {
+    let mut config = FilterConfig {
+        dedup: Some(MAX_DEDUP_WINDOW as u8),
+        ..Default::default()
+    };
+    assert_eq!(config.validate(), Ok(()));
+    for dedup in [0, MAX_DEDUP_WINDOW as u8 + 1] {
+        config.dedup = Some(dedup);
+        assert_eq!(config.validate(), Err(FilterError::DedupWindow));
+    }
+}
+
+#[test]
+fn selects_a_bss_by_bssid_or_ssid_but_not_both() {
+    let config = FilterConfig {
+        bssid: Some("24:0A:C4:00:00:01".parse().unwrap()),
+        ssid: Some("lab".try_into().unwrap()),
+        ..Default::default()
+    };
+    assert_eq!(config.validate(), Err(FilterError::BssidAndSsid));
+}
+
+// A QoS data frame from the AP to a station.
+fn to_station(receiver: &str, transmitter: &str) -> FrameHeader {
+    FrameHeader {
+        frame_control: FrameControl(0x0288),
+        addr1: Some(receiver.parse().unwrap()),
+        addr2: Some(transmitter.parse().unwrap()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn replies_keep_frames_from_the_traffic_target_to_this_device() {
+    let own: MacAddr = "24:0A:C4:00:00:10".parse().unwrap();
+    let ap: MacAddr = "24:0A:C4:00:00:01".parse().unwrap();
+    let mut filter = Filter::new(FilterConfig {
+        replies: true,
+        ..Default::default()
+    });
+    let record = from("24:0A:C4:00:00:01");
+    let reply = to_station("24:0A:C4:00:00:10", "24:0A:C4:00:00:01");
+    // Nothing passes until the target is known.
+    assert!(!filter.check(&record, Some(&reply)));
+    filter.set_traffic(Some((own, ap)));
+    assert!(filter.check(&record, Some(&reply)));
+    // Frames to another station or from another transmitter.
+    assert!(!filter.check(
+        &record,
+        Some(&to_station("24:0A:C4:00:00:11", "24:0A:C4:00:00:01"))
+    ));
+    assert!(!filter.check(
+        &record,
+        Some(&to_station("24:0A:C4:00:00:10", "24:0A:C4:00:00:02"))
+    ));
+    assert_eq!(filter.stats().not_reply, 3);
+
+    // The target outlives filter changes, but not the end of the run.
+    filter.set_config(FilterConfig {
+        replies: true,
+        min_rssi: Some(-90),
+        ..Default::default()
+    });
+    assert!(filter.check(&record, Some(&reply)));
+    filter.set_traffic(None);
+    assert!(!filter.check(&record, Some(&reply)));
+    filter.set_config(FilterConfig::default());
+    assert!(filter.check(&record, None));
+}
+
+#[test]
+fn replies_fall_back_to_the_transmitter_without_a_header() {
+    let own: MacAddr = "24:0A:C4:00:00:10".parse().unwrap();
+    let ap: MacAddr = "24:0A:C4:00:00:01".parse().unwrap();
+    let config = FilterConfig {
+        replies: true,
+        ..Default::default()
+    };
+    // The stage alone does not tap the header, as a station has none to tap.
+    assert!(!config.needs_header());
+    let mut filter = Filter::new(config);
+    assert!(!filter.check(&from("24:0A:C4:00:00:01"), None));
+    filter.set_traffic(Some((own, ap)));
+    assert!(filter.check(&from("24:0A:C4:00:00:01"), None));
+    assert!(!filter.check(&from("24:0A:C4:00:00:02"), None));
+    assert_eq!(filter.stats().not_reply, 2);
+}
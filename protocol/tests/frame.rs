@@ -0,0 +1,312 @@
+use esp_csi_protocol::filter::{Filter, FilterConfig};
+use esp_csi_protocol::frame::{
+    advertised_ssid, FrameControl, FrameHeader, FrameType, FrameTypes, Pairing, SequenceControl,
+    PAIRING_WAIT_US, PROMISCUOUS_ALL, PROMISCUOUS_DATA, PROMISCUOUS_MGMT,
+};
+use esp_csi_protocol::{CsiRecord, MacAddr};
+
+const BEACON: FrameControl = FrameControl(0x0080);
+const PROBE_RESP: FrameControl = FrameControl(0x0050);
+const QOS_DATA: FrameControl = FrameControl(0x0188);
+const ACK: FrameControl = FrameControl(0x00d4);
+
+fn types(s: &str) -> FrameTypes {
+    s.parse().unwrap()
+}
+
+#[test]
+fn reads_type_and_subtype_from_frame_control() {
+    // A beacon as captured, frame control first.
+    let header = FrameHeader::parse(&[0x80, 0x00, 0x00, 0x00]).unwrap();
+    assert_eq!(header.frame_control, BEACON);
+    assert_eq!((BEACON.kind(), BEACON.subtype()), (0, 8));
+    assert_eq!((QOS_DATA.kind(), QOS_DATA.subtype()), (2, 8));
+    assert_eq!((ACK.kind(), ACK.subtype()), (1, 13));
+    assert_eq!(FrameHeader::parse(&[0x80]), None);
+}
+
+fn mac(s: &str) -> Option<MacAddr> {
+    Some(s.parse().unwrap())
+}
+
+#[test]
+fn reads_addresses_and_sequence_control() {
+    // A retried QoS data frame, sequence 291 fragment 2.
+    let mut frame = vec![0x88, 0x09, 0x00, 0x00];
+    frame.extend([0x24, 0x0a, 0xc4, 0x00, 0x00, 0x01]);
+    frame.extend([0x24, 0x0a, 0xc4, 0x00, 0x00, 0x02]);
+    frame.extend([0x24, 0x0a, 0xc4, 0x00, 0x00, 0x03]);
+    frame.extend([0x32, 0x12]);
+    let header = FrameHeader::parse(&frame).unwrap();
+    assert!(header.frame_control.retry());
+    assert_eq!(header.addr1, mac("24:0A:C4:00:00:01"));
+    assert_eq!(header.addr2, mac("24:0A:C4:00:00:02"));
+    assert_eq!(header.addr3, mac("24:0A:C4:00:00:03"));
+    let sequence = header.sequence.unwrap();
+    assert_eq!((sequence.number(), sequence.fragment()), (291, 2));
+    assert_eq!(sequence, SequenceControl(0x1232));
+
+    // A short frame only carries the addresses it has room for.
+    let header = FrameHeader::parse(&frame[..16]).unwrap();
+    assert_eq!(header.addr2, mac("24:0A:C4:00:00:02"));
+    assert_eq!((header.addr3, header.sequence), (None, None));
+
+    // The body of a control frame is not read as addresses.
+    frame[..2].copy_from_slice(&[0x84, 0x00]);
+    let header = FrameHeader::parse(&frame).unwrap();
+    assert_eq!(header.addr2, mac("24:0A:C4:00:00:02"));
+    assert_eq!((header.addr3, header.sequence), (None, None));
+}
+
+#[test]
+fn types_match_whole_types_or_single_subtypes() {
+    assert!(FrameType::Mgmt.matches(BEACON));
+    assert!(FrameType::Mgmt.matches(PROBE_RESP));
+    assert!(FrameType::Beacon.matches(BEACON));
+    assert!(!FrameType::Beacon.matches(PROBE_RESP));
+    assert!(FrameType::QosData.matches(QOS_DATA));
+    assert!(
+        !FrameType::QosData.matches(BEACON),
+        "same subtype, other type"
+    );
+    assert!(FrameType::Ack.matches(ACK));
+
+    let list = types("beacon,qos-data");
+    assert!(list.matches(BEACON) && list.matches(QOS_DATA));
+    assert!(!list.matches(PROBE_RESP) && !list.matches(ACK));
+    assert!(types("all").matches(ACK));
+}
+
+#[test]
+fn promiscuous_mask_covers_every_listed_type() {
+    assert_eq!(types("all").promiscuous_mask(), PROMISCUOUS_ALL);
+    assert_eq!(types("beacon").promiscuous_mask(), PROMISCUOUS_MGMT);
+    assert_eq!(
+        types("probe-resp,data").promiscuous_mask(),
+        PROMISCUOUS_MGMT | PROMISCUOUS_DATA
+    );
+    // Beacons are let through while an SSID is looked for.
+    let config = FilterConfig {
+        frame_types: types("qos-data"),
+        ssid: Some("lab".try_into().unwrap()),
+        ..Default::default()
+    };
+    assert_eq!(
+        config.promiscuous_mask(),
+        PROMISCUOUS_MGMT | PROMISCUOUS_DATA
+    );
+}
+
+#[test]
+fn only_header_stages_need_the_header() {
+    assert!(!FilterConfig::default().needs_header());
+    let config = FilterConfig {
+        min_rssi: Some(-70),
+        ..Default::default()
+    };
+    assert!(!config.needs_header());
+    let config = FilterConfig {
+        dedup: Some(8),
+        ..Default::default()
+    };
+    assert!(config.needs_header());
+    let config = FilterConfig {
+        frame_types: types("beacon"),
+        ..Default::default()
+    };
+    assert!(config.needs_header());
+}
+
+#[test]
+fn lists_roundtrip_and_reject_unknown_types() {
+    assert_eq!(types("beacon,qos-data").to_string(), "beacon,qos-data");
+    assert_eq!(FrameTypes::default().to_string(), "all");
+    assert!("beacons".parse::<FrameTypes>().is_err());
+    assert!("".parse::<FrameTypes>().is_err());
+}
+
+#[test]
+fn filter_drops_other_types_and_unseen_headers() {
+    let mut filter = Filter::new(FilterConfig {
+        frame_types: types("beacon"),
+        ..Default::default()
+    });
+    let record = CsiRecord::default();
+    let header = |frame_control| FrameHeader {
+        frame_control,
+        ..Default::default()
+    };
+    assert!(filter.check(&record, Some(&header(BEACON))));
+    assert!(!filter.check(&record, Some(&header(QOS_DATA))));
+    assert!(!filter.check(&record, None));
+    assert_eq!(filter.stats().frame_type_rejected, 2);
+
+    // Without a type list the header is not needed.
+    let mut filter = Filter::default();
+    assert!(filter.check(&record, None));
+}
+
+// A beacon from 24:0A:C4:00:00:01 advertising `ssid`.
+fn beacon(ssid: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80, 0x00, 0x00, 0x00];
+    frame.extend([0xff; 6]);
+    frame.extend([0x24, 0x0a, 0xc4, 0x00, 0x00, 0x01]);
+    frame.extend([0x24, 0x0a, 0xc4, 0x00, 0x00, 0x01]);
+    frame.extend([0x00; 2 + 12]);
+    frame.extend([0x00, ssid.len() as u8]);
+    frame.extend(ssid);
+    frame
+}
+
+#[test]
+fn bssid_depends_on_direction() {
+    let header = |frame_control, addr1, addr2, addr3| FrameHeader {
+        frame_control: FrameControl(frame_control),
+        addr1: mac(addr1),
+        addr2: mac(addr2),
+        addr3: mac(addr3),
+        sequence: None,
+    };
+    let (ap, sta, other) = (
+        "02:00:00:00:00:01",
+        "02:00:00:00:00:02",
+        "02:00:00:00:00:03",
+    );
+    assert_eq!(header(0x0080, other, ap, ap).bssid(), mac(ap), "beacon");
+    assert_eq!(header(0x0188, ap, sta, other).bssid(), mac(ap), "to DS");
+    assert_eq!(header(0x0288, sta, ap, other).bssid(), mac(ap), "from DS");
+    assert_eq!(header(0x0388, ap, ap, other).bssid(), None, "between APs");
+    assert_eq!(header(0x00b4, ap, sta, other).bssid(), None, "RTS");
+}
+
+#[test]
+fn reads_the_ssid_of_beacons_only() {
+    assert_eq!(advertised_ssid(&beacon(b"lab net")), Some(&b"lab net"[..]));
+    assert_eq!(advertised_ssid(&beacon(b"")), Some(&b""[..]), "hidden");
+    let cut = beacon(b"lab net");
+    assert_eq!(advertised_ssid(&cut[..cut.len() - 1]), None);
+    let mut data = beacon(b"lab net");
+    data[0] = 0x88;
+    assert_eq!(advertised_ssid(&data), None);
+}
+
+#[test]
+fn filter_keeps_the_bss_of_the_ssid_once_heard() {
+    let mut filter = Filter::new(FilterConfig {
+        ssid: Some("lab net".try_into().unwrap()),
+        ..Default::default()
+    });
+    let record = CsiRecord::default();
+    let from_ap = FrameHeader::parse(&beacon(b"")).unwrap();
+    // Nothing passes until the SSID is heard.
+    assert!(!filter.check(&record, Some(&from_ap)));
+    filter.watch_ssid(&beacon(b"other"));
+    assert_eq!(filter.bssid(), None);
+    filter.watch_ssid(&beacon(b"lab net"));
+    assert_eq!(filter.bssid(), mac("24:0A:C4:00:00:01"));
+    assert!(filter.check(&record, Some(&from_ap)));
+    assert!(!filter.check(&record, None));
+    assert_eq!(filter.stats().bss_rejected, 2);
+
+    // A BSSID needs no beacon.
+    filter.set_config(FilterConfig {
+        bssid: mac("24:0A:C4:00:00:01"),
+        ..Default::default()
+    });
+    assert!(filter.check(&record, Some(&from_ap)));
+}
+
+#[test]
+fn filter_looks_for_the_ssid_again_when_it_or_the_channel_changes() {
+    let lab = FilterConfig {
+        ssid: Some("lab net".try_into().unwrap()),
+        ..Default::default()
+    };
+    let mut filter = Filter::new(lab.clone());
+    filter.set_channel(6);
+    filter.watch_ssid(&beacon(b"lab net"));
+    assert_eq!(filter.bssid(), mac("24:0A:C4:00:00:01"));
+    // Setting the same channel again keeps the BSS.
+    filter.set_channel(6);
+    assert_eq!(filter.bssid(), mac("24:0A:C4:00:00:01"));
+    filter.set_channel(11);
+    assert_eq!(filter.bssid(), None);
+    filter.watch_ssid(&beacon(b"lab net"));
+    assert_eq!(filter.bssid(), mac("24:0A:C4:00:00:01"));
+
+    filter.set_config(FilterConfig {
+        ssid: Some("other".try_into().unwrap()),
+        ..Default::default()
+    });
+    assert_eq!(filter.bssid(), None);
+    filter.watch_ssid(&beacon(b"lab net"));
+    assert_eq!(filter.bssid(), None);
+    // A BSSID given outright does not depend on the channel.
+    filter.set_config(FilterConfig {
+        bssid: mac("24:0A:C4:00:00:02"),
+        ..Default::default()
+    });
+    filter.set_channel(1);
+    assert_eq!(filter.bssid(), mac("24:0A:C4:00:00:02"));
+}
+
+fn header(frame_control: u16) -> FrameHeader {
+    FrameHeader {
+        frame_control: FrameControl(frame_control),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn pairs_records_with_headers_in_either_order() {
+    let mut pairing = Pairing::<&str, 4>::new();
+    // Header first, as well as an ACK that produced no record.
+    pairing.push_header(100, header(0x00d4));
+    pairing.push_header(110, header(0x0080));
+    assert_eq!(pairing.push_record(110, 0, "a"), None);
+    assert_eq!(pairing.pop_ready(0), Some(("a", Some(header(0x0080)))));
+    // Record first, it waits for its header.
+    assert_eq!(pairing.push_record(200, 0, "b"), None);
+    assert_eq!(pairing.pop_ready(0), None);
+    pairing.push_header(200, header(0x0188));
+    assert_eq!(pairing.pop_ready(0), Some(("b", Some(header(0x0188)))));
+    assert_eq!(pairing.pop_ready(0), None);
+}
+
+#[test]
+fn records_without_a_header_leave_once_waited_for() {
+    let mut pairing = Pairing::<&str, 4>::new();
+    assert_eq!(pairing.next_deadline(), None);
+    pairing.push_record(1_000, 50, "a");
+    pairing.push_record(1_010, 60, "b");
+    pairing.push_header(1_010, header(0x0188));
+    // b has its header but leaves after a, which is still waiting. No other
+    // frame needs to arrive for a to leave, only the time to pass.
+    let deadline = pairing.next_deadline().unwrap();
+    assert_eq!(deadline, 51 + PAIRING_WAIT_US);
+    assert_eq!(pairing.pop_ready(deadline - 1), None);
+    assert_eq!(pairing.pop_ready(deadline), Some(("a", None)));
+    assert_eq!(
+        pairing.pop_ready(deadline),
+        Some(("b", Some(header(0x0188))))
+    );
+    assert_eq!(pairing.next_deadline(), None);
+
+    // Records make room for newer ones, and the rest leave on drain.
+    let mut pairing = Pairing::<u32, 2>::new();
+    assert_eq!(pairing.push_record(10, 0, 1), None);
+    assert_eq!(pairing.push_record(20, 0, 2), None);
+    assert_eq!(pairing.push_record(30, 0, 3), Some((1, None)));
+    assert_eq!(pairing.drain(), Some((2, None)));
+    assert_eq!(pairing.drain(), Some((3, None)));
+    assert_eq!(pairing.drain(), None);
+}
+
+#[test]
+fn pairing_survives_the_clock_wrapping() {
+    let mut pairing = Pairing::<&str, 4>::new();
+    pairing.push_record(u32::MAX - 10, u32::MAX - 10, "a");
+    assert_eq!(pairing.pop_ready(5), None, "waited 16us");
+    assert_eq!(pairing.next_deadline(), Some(PAIRING_WAIT_US - 10));
+    assert_eq!(pairing.pop_ready(PAIRING_WAIT_US - 10), Some(("a", None)));
+}
@@ -0,0 +1,66 @@
+use esp_csi_protocol::gpio::{input_pins, Debounce, Edge, GpioConfig, GpioError, Polarity};
+
+#[test]
+fn polarity_picks_the_active_edge() {
+    assert_eq!(Polarity::High.active_edge(), Edge::Rising);
+    assert_eq!(Polarity::Low.active_edge(), Edge::Falling);
+}
+
+#[test]
+fn debounce_drops_edges_within_the_window() {
+    let mut debounce = Debounce::new(20);
+    assert!(debounce.accept(1000));
+    assert!(!debounce.accept(1005));
+    assert!(
+        !debounce.accept(1019),
+        "rejected edges do not move the window"
+    );
+    assert!(debounce.accept(1020));
+    assert!(debounce.accept(5000));
+
+    let mut off = Debounce::new(0);
+    assert!(off.accept(7) && off.accept(7));
+}
+
+#[test]
+fn validates_pins_per_chip() {
+    let config = |start_pin, event_pin| GpioConfig {
+        start_pin,
+        event_pin,
+        ..Default::default()
+    };
+    assert_eq!(config(None, None).validate("esp32"), Ok(()));
+    assert_eq!(config(Some(4), Some(5)).validate("esp32c3"), Ok(()));
+    // GPIO9 is the boot strapping pin of the ESP32-C3.
+    assert_eq!(
+        config(Some(9), None).validate("esp32c3"),
+        Err(GpioError::UnusablePin(9))
+    );
+    // The ESP32 console UART uses GPIO1 and GPIO3.
+    assert_eq!(
+        config(None, Some(3)).validate("esp32"),
+        Err(GpioError::UnusablePin(3))
+    );
+    assert_eq!(
+        config(Some(4), Some(4)).validate("esp32s3"),
+        Err(GpioError::SamePin)
+    );
+    assert_eq!(
+        config(Some(4), None).validate("esp8266"),
+        Err(GpioError::UnknownChip)
+    );
+}
+
+#[test]
+fn input_pins_leave_out_reserved_pins() {
+    for (chip, reserved) in [
+        ("esp32", &[0, 1, 2, 3, 5, 6, 7, 8, 9, 10, 11, 12, 15][..]),
+        ("esp32c3", &[2, 8, 9, 18, 19, 20, 21]),
+        ("esp32c6", &[8, 9, 12, 13, 15, 16, 17]),
+        ("esp32s3", &[0, 3, 19, 20, 43, 44, 45, 46]),
+    ] {
+        let pins = input_pins(chip).unwrap();
+        assert!(pins.windows(2).all(|w| w[0] < w[1]), "{chip} is sorted");
+        assert!(reserved.iter().all(|p| !pins.contains(p)), "{chip}");
+    }
+}
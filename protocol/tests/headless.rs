@@ -0,0 +1,155 @@
+use esp_csi_protocol::command::{SetFilter, SetTrigger};
+use esp_csi_protocol::config::MAX_LINE_LEN;
+use esp_csi_protocol::filter::FilterConfig;
+use esp_csi_protocol::headless::{
+    BreakIn, HeadlessConfig, SettingsError, Sink, StoredSettings, CONFIG_LEN, SETTINGS_LEN,
+};
+use esp_csi_protocol::trigger::TriggerConfig;
+use esp_csi_protocol::Command;
+
+#[test]
+fn stored_settings_roundtrip() {
+    for config in [
+        HeadlessConfig::default(),
+        HeadlessConfig {
+            enabled: true,
+            sink: Sink::Serial,
+            duration_s: Some(3600),
+        },
+        HeadlessConfig {
+            enabled: true,
+            sink: Sink::Flash,
+            duration_s: Some(0),
+        },
+    ] {
+        let bytes = config.to_bytes();
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(HeadlessConfig::from_bytes(&bytes), Some(config));
+    }
+}
+
+#[test]
+fn erased_or_corrupted_flash_is_ignored() {
+    assert_eq!(HeadlessConfig::from_bytes(&[0xff; CONFIG_LEN]), None);
+    assert_eq!(HeadlessConfig::from_bytes(&[0; CONFIG_LEN]), None);
+    assert_eq!(HeadlessConfig::from_bytes(&[]), None);
+
+    let stored = HeadlessConfig {
+        enabled: true,
+        ..Default::default()
+    }
+    .to_bytes();
+    for i in 4..17 {
+        let mut bytes = stored;
+        bytes[i] ^= 0x10;
+        assert_eq!(HeadlessConfig::from_bytes(&bytes), None, "byte {i}");
+    }
+}
+
+#[test]
+fn breaks_in_on_ctrl_c_or_repeated_enter() {
+    let mut break_in = BreakIn::new();
+    assert!(break_in.push(0x03));
+
+    let mut break_in = BreakIn::new();
+    assert!(!b"\r\n\r\n".iter().any(|b| break_in.push(*b)));
+    assert!(break_in.push(b'\r'));
+
+    // Typing in between starts the count over.
+    let mut break_in = BreakIn::new();
+    assert!(!b"\r\rx\r\r".iter().any(|b| break_in.push(*b)));
+    assert!(break_in.push(b'\r'));
+}
+
+fn long_filter() -> FilterConfig {
+    FilterConfig {
+        allow_mac: "24:0A:C4:00:00:01,24:0A:C4:00:00:02,24:0A:C4:00:00:03,24:0A:C4"
+            .parse()
+            .unwrap(),
+        deny_mac: "24:0A:C4:00:00:09".parse().unwrap(),
+        min_rssi: Some(-80),
+        max_rate: Some(100),
+        ssid: Some("Lab Network".try_into().unwrap()),
+        replies: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn collector_settings_roundtrip() {
+    let mut settings = StoredSettings::new();
+    let trigger = Command::SetTrigger(SetTrigger::from(&TriggerConfig {
+        min_rssi: Some(-40),
+        ..Default::default()
+    }));
+    settings.push(&trigger).unwrap();
+    settings
+        .push(&Command::SetFilter(SetFilter::from(&long_filter())))
+        .unwrap();
+    let bytes = settings.to_bytes();
+    assert_eq!(bytes.len() % 4, 0);
+    let stored = StoredSettings::from_bytes(&bytes).unwrap();
+    assert_eq!(stored, settings);
+
+    let mut lines = stored.lines();
+    assert_eq!(
+        lines.next(),
+        Some("set-trigger --rssi=-40 --pre=10 --post=10")
+    );
+    // The filter is too long for one line, so it is split per argument.
+    let rest: Vec<_> = lines.collect();
+    assert_eq!(
+        rest,
+        [
+            "set-filter --allow-mac=24:0A:C4:00:00:01,24:0A:C4:00:00:02,24:0A:C4:00:00:03,24:0A:C4",
+            "set-filter --deny-mac=24:0A:C4:00:00:09",
+            "set-filter --min-rssi=-80",
+            "set-filter --max-rate=100",
+            "set-filter --ssid=Lab_Network",
+            "set-filter --replies=on",
+        ]
+    );
+    assert!(rest.iter().all(|line| line.len() < MAX_LINE_LEN));
+    assert!(rest.iter().all(|line| line.parse::<Command>().is_ok()));
+}
+
+#[test]
+fn default_settings_take_no_arguments() {
+    assert_eq!(
+        SetFilter::from(&FilterConfig::default()),
+        SetFilter::default()
+    );
+}
+
+#[test]
+fn stored_settings_stay_whole_when_full() {
+    let mut settings = StoredSettings::new();
+    let filter = Command::SetFilter(SetFilter::from(&long_filter()));
+    let mut pushed = 0;
+    while settings.push(&filter).is_ok() {
+        pushed += 1;
+    }
+    assert_eq!(settings.push(&filter), Err(SettingsError::Full));
+    assert_eq!(settings.lines().count(), pushed * 6);
+    assert_eq!(
+        StoredSettings::from_bytes(&settings.to_bytes()),
+        Some(settings)
+    );
+}
+
+#[test]
+fn erased_or_corrupted_settings_are_ignored() {
+    assert_eq!(StoredSettings::from_bytes(&[0xff; SETTINGS_LEN]), None);
+    assert_eq!(StoredSettings::from_bytes(&[0; SETTINGS_LEN]), None);
+    assert_eq!(StoredSettings::from_bytes(&[]), None);
+
+    let mut settings = StoredSettings::new();
+    settings.push(&Command::SetOutput { format: None }).unwrap();
+    let stored = settings.to_bytes();
+    // Version, padding, text length, text and checksum.
+    for i in 4..19 {
+        let mut bytes = stored;
+        bytes[i] ^= 0x10;
+        assert_eq!(StoredSettings::from_bytes(&bytes), None, "byte {i}");
+    }
+}
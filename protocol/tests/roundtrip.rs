@@ -0,0 +1,565 @@
+use esp_csi_protocol::channel::{ChannelList, Country, Secondary, MAX_HOP_CHANNELS};
+use esp_csi_protocol::command::{
+    CsiFlag, LabelAction, LogAction, NetArch, ScheduleAction, ScheduleArgs, SetCsi, SetFilter,
+    SetGpio, SetHeadless, SetTraffic, SetTrigger, SetWifi, Switch, TrafficKind, WifiMode,
+};
+use esp_csi_protocol::event::Label;
+use esp_csi_protocol::filter::{MacList, MacPattern, RssiBound, MAX_MAC_PATTERNS};
+use esp_csi_protocol::frame::{FrameType, FrameTypes, MAX_FRAME_TYPES};
+use esp_csi_protocol::gpio::{Edge, Polarity};
+use esp_csi_protocol::headless::Sink;
+use esp_csi_protocol::ppdu::{Bandwidth, Bandwidths, SigMode, SigModes, Stbc};
+use esp_csi_protocol::record::MAX_CSI_LEN;
+use esp_csi_protocol::trigger::Cause;
+use esp_csi_protocol::{Command, CsiRecord, Decoded, Decoder, Event, MacAddr, OutputFormat};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::{select, subsequence};
+
+fn record() -> impl Strategy<Value = CsiRecord> {
+    (
+        (
+            any::<u32>(),
+            any::<[u8; 6]>(),
+            any::<i8>(),
+            any::<u8>(),
+            any::<u8>(),
+        ),
+        (
+            any::<u8>(),
+            any::<u8>(),
+            any::<u8>(),
+            any::<u8>(),
+            any::<u8>(),
+        ),
+        (
+            any::<u8>(),
+            any::<u8>(),
+            any::<u8>(),
+            any::<i8>(),
+            any::<u8>(),
+        ),
+        (
+            any::<u8>(),
+            any::<u8>(),
+            any::<u32>(),
+            any::<u8>(),
+            any::<u16>(),
+        ),
+        (any::<u8>(), vec(any::<i8>(), 0..=MAX_CSI_LEN)),
+    )
+        .prop_map(|(a, b, c, d, e)| CsiRecord {
+            seq: a.0,
+            mac: MacAddr(a.1),
+            rssi: a.2,
+            rate: a.3,
+            sig_mode: a.4,
+            mcs: b.0,
+            bandwidth: b.1,
+            smoothing: b.2,
+            not_sounding: b.3,
+            aggregation: b.4,
+            stbc: c.0,
+            fec_coding: c.1,
+            sgi: c.2,
+            noise_floor: c.3,
+            ampdu_cnt: c.4,
+            channel: d.0,
+            secondary_channel: d.1,
+            timestamp: d.2,
+            ant: d.3,
+            sig_len: d.4,
+            rx_state: e.0,
+            csi: heapless::Vec::from_slice(&e.1).unwrap(),
+        })
+}
+
+fn label() -> impl Strategy<Value = Label> {
+    "[a-zA-Z0-9_.:/][a-zA-Z0-9_.:/-]{0,31}".prop_map(|s| Label::try_from(s.as_str()).unwrap())
+}
+
+fn event() -> impl Strategy<Value = Event> {
+    prop_oneof![
+        any::<u64>().prop_map(|duration_s| Event::Started { duration_s }),
+        (any::<u32>(), any::<u64>()).prop_map(|(records, elapsed_ms)| Event::Stopped {
+            records,
+            elapsed_ms
+        }),
+        any::<u32>().prop_map(|index| Event::Window { index }),
+        (any::<u32>(), any::<bool>())
+            .prop_map(|(records, traffic)| Event::Paused { records, traffic }),
+        any::<u64>().prop_map(|paused_ms| Event::Resumed { paused_ms }),
+        (select(Cause::ALL), any::<u32>())
+            .prop_map(|(cause, buffered)| Event::Triggered { cause, buffered }),
+        (any::<u8>(), select(Edge::ALL), any::<u64>()).prop_map(|(pin, edge, timestamp_us)| {
+            Event::Gpio {
+                pin,
+                edge,
+                timestamp_us,
+            }
+        }),
+        (label(), any::<u64>()).prop_map(|(label, timestamp_us)| Event::Mark {
+            label,
+            timestamp_us
+        }),
+        (any::<u8>(), any::<u64>()).prop_map(|(channel, timestamp_us)| Event::Hop {
+            channel,
+            timestamp_us
+        }),
+        // An empty label clears it.
+        (proptest::option::of(label()), any::<u64>()).prop_map(|(label, timestamp_us)| {
+            Event::Label {
+                label: label.unwrap_or_default(),
+                timestamp_us,
+            }
+        }),
+    ]
+}
+
+fn format() -> impl Strategy<Value = OutputFormat> {
+    select(OutputFormat::ALL)
+}
+
+// SSIDs and passwords travel with spaces replaced by underscores, so they
+// cannot contain underscores themselves.
+fn text<const N: usize>() -> impl Strategy<Value = heapless::String<N>> {
+    proptest::string::string_regex(&format!("[a-zA-Z0-9 .!-]{{0,{N}}}"))
+        .unwrap()
+        .prop_map(|s| heapless::String::try_from(s.as_str()).unwrap())
+}
+
+// Exact addresses, OUI prefixes and wildcards.
+fn mac_list() -> impl Strategy<Value = MacList> {
+    vec(
+        prop_oneof![
+            any::<[u8; 6]>().prop_map(|m| MacPattern::from(MacAddr(m))),
+            any::<[u8; 3]>().prop_map(|m| {
+                format!("{:02x}:{:02x}:{:02x}", m[0], m[1], m[2])
+                    .parse()
+                    .unwrap()
+            }),
+            any::<u8>().prop_map(|b| format!("*:*:*:*:*:{:02x}", b).parse().unwrap()),
+        ],
+        0..=MAX_MAC_PATTERNS,
+    )
+    .prop_map(|patterns| MacList(heapless::Vec::from_slice(&patterns).unwrap()))
+}
+
+fn rssi_bound() -> impl Strategy<Value = RssiBound> {
+    proptest::option::of(any::<i8>()).prop_map(RssiBound)
+}
+
+fn frame_types() -> impl Strategy<Value = FrameTypes> {
+    vec(select(FrameType::ALL), 0..=MAX_FRAME_TYPES)
+        .prop_map(|types| FrameTypes(heapless::Vec::from_slice(&types).unwrap()))
+}
+
+fn sig_modes() -> impl Strategy<Value = SigModes> {
+    subsequence(SigMode::ALL, 0..=SigMode::ALL.len())
+        .prop_map(|modes| SigModes(heapless::Vec::from_slice(&modes).unwrap()))
+}
+
+fn bandwidths() -> impl Strategy<Value = Bandwidths> {
+    subsequence(Bandwidth::ALL, 0..=Bandwidth::ALL.len())
+        .prop_map(|widths| Bandwidths(heapless::Vec::from_slice(&widths).unwrap()))
+}
+
+fn command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        (
+            any::<bool>(),
+            proptest::option::of(select(TrafficKind::ALL)),
+            any::<Option<u64>>()
+        )
+            .prop_map(
+                |(enable, kind, interval_ms)| Command::SetTraffic(SetTraffic {
+                    enable,
+                    kind,
+                    interval_ms,
+                })
+            ),
+        proptest::option::of(select(NetArch::ALL)).prop_map(|arch| Command::SetNetwork { arch }),
+        (
+            vec(select(CsiFlag::ALL), 0..=12),
+            any::<Option<u32>>(),
+            any::<Option<u32>>()
+        )
+            .prop_map(|(disable, he_stbc, val_scale_cfg)| Command::SetCsi(SetCsi {
+                disable: heapless::Vec::from_slice(&disable).unwrap(),
+                he_stbc,
+                val_scale_cfg,
+            })),
+        (
+            proptest::option::of(select(WifiMode::ALL)),
+            any::<Option<u16>>(),
+            any::<bool>(),
+            proptest::option::of(text::<32>()),
+            proptest::option::of(text::<64>()),
+            proptest::option::of(text::<32>()),
+            proptest::option::of(text::<64>()),
+            any::<Option<u8>>(),
+            proptest::option::of(select(Secondary::ALL)),
+            proptest::option::of(
+                select(&["01", "DE", "JP", "US"][..]).prop_map(|s| s.parse::<Country>().unwrap())
+            ),
+            proptest::option::of(
+                vec(any::<u8>(), 0..=MAX_HOP_CHANNELS)
+                    .prop_map(|c| ChannelList(heapless::Vec::from_slice(&c).unwrap())),
+            ),
+            any::<Option<u64>>(),
+        )
+            .prop_map(
+                |(
+                    mode,
+                    max_connections,
+                    hide_ssid,
+                    ap_ssid,
+                    ap_password,
+                    sta_ssid,
+                    sta_password,
+                    channel,
+                    secondary,
+                    country,
+                    hop,
+                    dwell_ms,
+                )| {
+                    Command::SetWifi(SetWifi {
+                        mode,
+                        max_connections,
+                        hide_ssid,
+                        ap_ssid,
+                        ap_password,
+                        sta_ssid,
+                        sta_password,
+                        channel,
+                        secondary,
+                        country,
+                        hop,
+                        dwell_ms,
+                    })
+                }
+            ),
+        proptest::option::of(format()).prop_map(|format| Command::SetOutput { format }),
+        (any::<Option<u64>>(), any::<Option<u32>>())
+            .prop_map(|(duration_s, count)| Command::Start { duration_s, count }),
+        Just(Command::Stop),
+        (
+            proptest::option::of(select(ScheduleAction::ALL)),
+            any::<Option<u64>>(),
+            any::<Option<u64>>(),
+            any::<Option<u64>>(),
+            any::<Option<u32>>(),
+        )
+            .prop_map(|(action, start_in_s, every_s, duration_s, count)| {
+                Command::Schedule(ScheduleArgs {
+                    action,
+                    start_in_s,
+                    every_s,
+                    duration_s,
+                    count,
+                })
+            }),
+        any::<bool>().prop_map(|traffic| Command::Pause { traffic }),
+        Just(Command::Resume),
+        Just(Command::Status),
+        (
+            any::<bool>(),
+            any::<Option<i8>>(),
+            proptest::option::of(any::<[u8; 6]>().prop_map(MacAddr)),
+            proptest::option::of(0.0f32..1e6),
+            any::<Option<usize>>(),
+            any::<Option<u64>>(),
+        )
+            .prop_map(|(off, min_rssi, mac, min_variance, pre_records, post_s)| {
+                Command::SetTrigger(SetTrigger {
+                    off,
+                    min_rssi,
+                    mac,
+                    min_variance,
+                    pre_records,
+                    post_s,
+                })
+            }),
+        Just(Command::Trigger),
+        (
+            any::<bool>(),
+            any::<Option<u8>>(),
+            any::<Option<u8>>(),
+            proptest::option::of(select(Polarity::ALL)),
+            any::<Option<u64>>(),
+        )
+            .prop_map(|(off, start_pin, event_pin, polarity, debounce_ms)| {
+                Command::SetGpio(SetGpio {
+                    off,
+                    start_pin,
+                    event_pin,
+                    polarity,
+                    debounce_ms,
+                })
+            }),
+        label().prop_map(|label| Command::Mark { label }),
+        prop_oneof![
+            Just(Command::Label {
+                action: None,
+                label: None
+            }),
+            Just(Command::Label {
+                action: Some(LabelAction::Clear),
+                label: None
+            }),
+            label().prop_map(|label| Command::Label {
+                action: Some(LabelAction::Set),
+                label: Some(label)
+            }),
+        ],
+        (
+            any::<bool>(),
+            any::<bool>(),
+            proptest::option::of(select(Sink::ALL)),
+            any::<Option<u64>>(),
+        )
+            .prop_map(|(enable, disable, sink, duration_s)| {
+                Command::SetHeadless(SetHeadless {
+                    enable,
+                    disable,
+                    sink,
+                    duration_s,
+                })
+            }),
+        proptest::option::of(select(LogAction::ALL)).prop_map(|action| Command::Log { action }),
+        (
+            (
+                any::<bool>(),
+                proptest::option::of(mac_list()),
+                proptest::option::of(mac_list()),
+                proptest::option::of(rssi_bound()),
+                proptest::option::of(rssi_bound()),
+                proptest::option::of(frame_types()),
+                proptest::option::of(any::<u32>()),
+                proptest::option::of(any::<u32>()),
+            ),
+            (
+                proptest::option::of(sig_modes()),
+                proptest::option::of(bandwidths()),
+                proptest::option::of(select(Stbc::ALL)),
+                proptest::option::of(any::<u8>()),
+                proptest::option::of(any::<[u8; 6]>().prop_map(MacAddr)),
+                proptest::option::of(text::<32>()),
+                proptest::option::of(select(Switch::ALL)),
+            ),
+        )
+            .prop_map(
+                |(
+                    (
+                        off,
+                        allow_mac,
+                        deny_mac,
+                        min_rssi,
+                        max_rssi,
+                        frame_types,
+                        max_rate,
+                        max_rate_per_mac,
+                    ),
+                    (sig_mode, bandwidth, stbc, dedup, bssid, ssid, replies),
+                )| {
+                    Command::SetFilter(SetFilter {
+                        off,
+                        allow_mac,
+                        deny_mac,
+                        min_rssi,
+                        max_rssi,
+                        frame_types,
+                        max_rate,
+                        max_rate_per_mac,
+                        sig_mode,
+                        bandwidth,
+                        stbc,
+                        dedup,
+                        bssid,
+                        ssid,
+                        replies,
+                    })
+                }
+            ),
+        Just(Command::ShowConfig),
+        Just(Command::ResetConfig),
+    ]
+}
+
+// Feeds an encoded item line by line, returning everything the decoder produced.
+fn decode(decoder: &mut Decoder, encoded: &str) -> Vec<Decoded> {
+    encoded
+        .lines()
+        .map(|line| decoder.push_line(line))
+        .filter(|d| *d != Decoded::Pending)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Record(Box<CsiRecord>),
+    Event(Event),
+    Other(String),
+}
+
+proptest! {
+    #[test]
+    fn record_roundtrip(record in record(), format in format()) {
+        let encoded = record.display(format).to_string();
+        let decoded = decode(&mut Decoder::new(), &encoded);
+        prop_assert_eq!(decoded, vec![Decoded::Record(record)]);
+    }
+
+    #[test]
+    fn event_roundtrip(event in event(), format in format()) {
+        let encoded = event.display(format).to_string();
+        prop_assert!(!encoded.contains('\n'));
+        let decoded = decode(&mut Decoder::new(), &encoded);
+        prop_assert_eq!(decoded, vec![Decoded::Event(event)]);
+    }
+
+    #[test]
+    fn mixed_stream_roundtrip(
+        items in vec(
+            (
+                prop_oneof![
+                    record().prop_map(|r| Item::Record(Box::new(r))),
+                    event().prop_map(Item::Event),
+                    "[a-z >]{0,20}".prop_map(Item::Other),
+                ],
+                format(),
+            ),
+            0..16,
+        )
+    ) {
+        let mut decoder = Decoder::new();
+        for (item, format) in items {
+            match item {
+                Item::Record(r) => {
+                    let encoded = r.display(format).to_string();
+                    prop_assert_eq!(decode(&mut decoder, &encoded), vec![Decoded::Record(*r)]);
+                }
+                Item::Event(e) => {
+                    let encoded = e.display(format).to_string();
+                    prop_assert_eq!(decode(&mut decoder, &encoded), vec![Decoded::Event(e)]);
+                }
+                Item::Other(line) => {
+                    prop_assert_eq!(decoder.push_line(&line), Decoded::Other);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_text_record_is_malformed(record in record(), cut in 1usize..24) {
+        let encoded = record.display(OutputFormat::Text).to_string();
+        let lines: Vec<&str> = encoded.lines().collect();
+        let mut decoder = Decoder::new();
+        for line in &lines[..cut.min(lines.len() - 1)] {
+            decoder.push_line(line);
+        }
+        // The next record header reveals that the previous one was cut short.
+        prop_assert_eq!(decoder.push_line(lines[0]), Decoded::Malformed);
+    }
+
+    #[test]
+    fn command_roundtrip(command in command()) {
+        let line = command.to_string();
+        prop_assert_eq!(line.parse::<Command>(), Ok(command));
+    }
+}
+
+#[test]
+fn schema_names_are_unique() {
+    use esp_csi_protocol::command::COMMANDS;
+
+    for (i, command) in COMMANDS.iter().enumerate() {
+        assert!(COMMANDS[i + 1..].iter().all(|c| c.name != command.name));
+        for (j, arg) in command.args.iter().enumerate() {
+            assert!(command.args[j + 1..].iter().all(|a| a.name != arg.name));
+        }
+    }
+}
+
+#[test]
+fn rejects_invalid_arguments() {
+    use esp_csi_protocol::command::ParseError;
+
+    assert_eq!("".parse::<Command>(), Err(ParseError::Empty));
+    assert_eq!("launch".parse::<Command>(), Err(ParseError::UnknownCommand));
+    assert_eq!(
+        "start --forever".parse::<Command>(),
+        Err(ParseError::UnknownArgument)
+    );
+    assert_eq!(
+        "start now".parse::<Command>(),
+        Err(ParseError::UnknownArgument)
+    );
+    assert_eq!(
+        "start --duration".parse::<Command>(),
+        Err(ParseError::MissingValue("duration"))
+    );
+    assert_eq!(
+        "set-traffic --enable=yes".parse::<Command>(),
+        Err(ParseError::UnexpectedValue("enable"))
+    );
+    assert_eq!(
+        "set-traffic --type=tcp".parse::<Command>(),
+        Err(ParseError::InvalidValue("type"))
+    );
+    assert_eq!(
+        "mark a,b".parse::<Command>(),
+        Err(ParseError::InvalidValue("label"))
+    );
+    assert_eq!(
+        "label set walking fast".parse::<Command>(),
+        Err(ParseError::UnknownArgument)
+    );
+}
+
+#[test]
+fn replies_are_switched_on_and_off() {
+    use esp_csi_protocol::command::ParseError;
+
+    let parsed = |line: &str| match line.parse::<Command>() {
+        Ok(Command::SetFilter(c)) => c.replies,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(parsed("set-filter --replies=on"), Some(Switch::On));
+    assert_eq!(parsed("set-filter --replies=off"), Some(Switch::Off));
+    assert_eq!(parsed("set-filter --min-rssi=-70"), None);
+    assert_eq!(
+        "set-filter --replies".parse::<Command>(),
+        Err(ParseError::MissingValue("replies"))
+    );
+}
+
+#[test]
+fn rssi_bounds_are_set_and_cleared() {
+    use esp_csi_protocol::command::ParseError;
+
+    let parsed = |line: &str| match line.parse::<Command>() {
+        Ok(Command::SetFilter(c)) => (c.min_rssi, c.max_rssi),
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(
+        parsed("set-filter --min-rssi=-70 --max-rssi=none"),
+        (Some(RssiBound(Some(-70))), Some(RssiBound(None)))
+    );
+    assert_eq!(
+        parsed("set-filter --min-rssi=none"),
+        (Some(RssiBound(None)), None)
+    );
+    assert_eq!(
+        Command::SetFilter(SetFilter {
+            min_rssi: Some(RssiBound(None)),
+            ..Default::default()
+        })
+        .to_string(),
+        "set-filter --min-rssi=none"
+    );
+    assert_eq!(
+        "set-filter --max-rssi=off".parse::<Command>(),
+        Err(ParseError::InvalidValue("max-rssi"))
+    );
+}
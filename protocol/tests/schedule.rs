@@ -0,0 +1,151 @@
+use esp_csi_protocol::schedule::{Schedule, ScheduleError, Scheduler, Step, MAX_SCHEDULE_S};
+
+/// Clock the tests move by hand.
+struct FakeClock {
+    now_ms: u64,
+}
+
+impl FakeClock {
+    fn advance_to(&mut self, ms: u64) {
+        assert!(ms >= self.now_ms);
+        self.now_ms = ms;
+    }
+}
+
+const SCHEDULE: Schedule = Schedule {
+    start_in_s: 10,
+    every_s: 60,
+    duration_s: 20,
+    count: Some(3),
+};
+
+#[test]
+fn runs_every_window_in_turn() {
+    let mut clock = FakeClock { now_ms: 5_000 };
+    let mut scheduler = Scheduler::new(SCHEDULE, clock.now_ms);
+
+    let mut windows = Vec::new();
+    loop {
+        match scheduler.next(clock.now_ms) {
+            Step::Wait { until_ms } => clock.advance_to(until_ms),
+            Step::Collect { window, until_ms } => {
+                windows.push((window, clock.now_ms, until_ms));
+                clock.advance_to(until_ms);
+            }
+            Step::Finished => break,
+        }
+    }
+    assert_eq!(
+        windows,
+        vec![
+            (0, 15_000, 35_000),
+            (1, 75_000, 95_000),
+            (2, 135_000, 155_000)
+        ]
+    );
+    assert_eq!(scheduler.started(), 3);
+    assert_eq!(scheduler.missed(), 0);
+    assert_eq!(scheduler.next_start_ms(), None);
+}
+
+#[test]
+fn hands_out_each_window_once() {
+    let mut scheduler = Scheduler::new(SCHEDULE, 0);
+    assert_eq!(
+        scheduler.next(10_000),
+        Step::Collect {
+            window: 0,
+            until_ms: 30_000
+        }
+    );
+    // A manual stop in the middle of the window does not restart it.
+    assert_eq!(scheduler.next(12_000), Step::Wait { until_ms: 70_000 });
+}
+
+#[test]
+fn late_start_collects_the_remainder_and_skips_ended_windows() {
+    let mut scheduler = Scheduler::new(SCHEDULE, 0);
+    // Window 0 (10-30s) has ended and window 1 (70-90s) is under way.
+    assert_eq!(
+        scheduler.next(80_000),
+        Step::Collect {
+            window: 1,
+            until_ms: 90_000
+        }
+    );
+    assert_eq!(scheduler.missed(), 1);
+    // Past the last window.
+    assert_eq!(scheduler.next(200_000), Step::Finished);
+    assert_eq!(scheduler.missed(), 2);
+    assert_eq!(scheduler.started(), 1);
+}
+
+#[test]
+fn unlimited_schedule_keeps_going() {
+    let schedule = Schedule {
+        count: None,
+        ..SCHEDULE
+    };
+    let mut scheduler = Scheduler::new(schedule, 0);
+    let day_ms = 24 * 3600 * 1000;
+    match scheduler.next(day_ms) {
+        Step::Wait { until_ms } => assert!(until_ms > day_ms && until_ms - day_ms <= 60_000),
+        Step::Collect { until_ms, .. } => assert!(until_ms > day_ms),
+        Step::Finished => panic!("unlimited schedule finished"),
+    }
+}
+
+#[test]
+fn rejects_invalid_schedules() {
+    let zero = Schedule {
+        duration_s: 0,
+        ..SCHEDULE
+    };
+    assert_eq!(zero.validate(), Err(ScheduleError::ZeroDuration));
+    let overlapping = Schedule {
+        duration_s: 61,
+        ..SCHEDULE
+    };
+    assert_eq!(
+        overlapping.validate(),
+        Err(ScheduleError::DurationExceedsPeriod)
+    );
+    let none = Schedule {
+        count: Some(0),
+        ..SCHEDULE
+    };
+    assert_eq!(none.validate(), Err(ScheduleError::ZeroCount));
+    assert_eq!(SCHEDULE.validate(), Ok(()));
+}
+
+#[test]
+fn extreme_times_are_rejected_or_saturate() {
+    let huge = Schedule {
+        start_in_s: u64::MAX,
+        every_s: u64::MAX,
+        duration_s: u64::MAX,
+        count: None,
+    };
+    assert_eq!(huge.validate(), Err(ScheduleError::TooLong));
+    let longest = Schedule {
+        start_in_s: MAX_SCHEDULE_S,
+        every_s: MAX_SCHEDULE_S,
+        duration_s: MAX_SCHEDULE_S,
+        count: None,
+    };
+    assert_eq!(longest.validate(), Ok(()));
+
+    // Even unchecked, late windows saturate instead of overflowing.
+    let mut scheduler = Scheduler::new(huge, u64::MAX - 1);
+    assert_eq!(
+        scheduler.next(u64::MAX - 1),
+        Step::Wait { until_ms: u64::MAX }
+    );
+    let mut scheduler = Scheduler::new(longest, 0);
+    let late_ms = u64::MAX / 2;
+    match scheduler.next(late_ms) {
+        Step::Wait { until_ms } | Step::Collect { until_ms, .. } => assert!(until_ms > late_ms),
+        Step::Finished => panic!("unlimited schedule finished"),
+    }
+    assert!(scheduler.missed() > 0);
+}
@@ -0,0 +1,104 @@
+use esp_csi_protocol::state::{Rejected, Request, State};
+
+// Moves through `path`, checking every step against the transition rules.
+fn walk(path: &[State]) -> State {
+    path.windows(2).for_each(|step| {
+        assert!(
+            step[0].can_become(step[1]),
+            "{} -> {} should be allowed",
+            step[0],
+            step[1]
+        )
+    });
+    *path.last().unwrap()
+}
+
+#[test]
+fn station_run_with_pause() {
+    use State::*;
+    let state = walk(&[
+        Idle,
+        Initialising,
+        Connecting,
+        Collecting,
+        Paused,
+        Collecting,
+        Stopping,
+        Idle,
+    ]);
+    assert_eq!(state.accepts(Request::Start), Ok(()));
+}
+
+#[test]
+fn failed_setup_is_retried_by_start() {
+    use State::*;
+    let state = walk(&[Idle, Initialising, Error]);
+    assert!(!state.is_active());
+    assert_eq!(state.accepts(Request::Start), Ok(()));
+    walk(&[Error, Initialising, Collecting]);
+}
+
+#[test]
+fn accepted_start_blocks_another_start_and_setup_changes() {
+    use State::*;
+    let state = walk(&[Idle, Starting]);
+    assert!(state.is_active());
+    assert!(state.accepts(Request::Start).is_err());
+    assert!(state.accepts(Request::Stop).is_err());
+    walk(&[Starting, Initialising, Collecting]);
+    walk(&[Error, Starting, Connecting]);
+}
+
+#[test]
+fn rejects_commands_that_do_not_fit_the_state() {
+    assert_eq!(
+        State::Collecting.accepts(Request::Start),
+        Err(Rejected {
+            state: State::Collecting,
+            request: Request::Start,
+        })
+    );
+    assert!(State::Idle.accepts(Request::Stop).is_err());
+    assert!(State::Idle.accepts(Request::Pause).is_err());
+    assert!(State::Connecting.accepts(Request::Pause).is_err());
+    assert!(State::Collecting.accepts(Request::Resume).is_err());
+    assert!(State::Paused.accepts(Request::Pause).is_err());
+    assert_eq!(
+        State::Collecting
+            .accepts(Request::Start)
+            .unwrap_err()
+            .to_string(),
+        "cannot start while collecting"
+    );
+}
+
+#[test]
+fn accepted_requests_lead_to_allowed_states() {
+    // The state the firmware moves to once it acts on a request.
+    let target = |request| match request {
+        Request::Start => State::Starting,
+        Request::Stop => State::Stopping,
+        Request::Pause => State::Paused,
+        Request::Resume => State::Collecting,
+        // The run ends once the post-trigger duration has passed.
+        Request::Trigger => State::Stopping,
+    };
+    for &state in State::ALL {
+        for &request in Request::ALL {
+            if state.accepts(request).is_ok() {
+                assert!(
+                    state.can_become(target(request)),
+                    "{state} accepts {request}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn settings_are_locked_while_active() {
+    for &state in State::ALL {
+        let idle = matches!(state, State::Idle | State::Error);
+        assert_eq!(state.is_active(), !idle, "{state}");
+    }
+}
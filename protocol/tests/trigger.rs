@@ -0,0 +1,135 @@
+use esp_csi_protocol::trigger::{
+    Cause, Feed, Trigger, TriggerConfig, MAX_PRE_RECORDS, VARIANCE_WINDOW,
+};
+use esp_csi_protocol::{CsiRecord, MacAddr};
+
+// A frame whose subcarriers all have amplitude `amplitude`, tagged with `timestamp`.
+fn frame(timestamp: u32, rssi: i8, amplitude: i8) -> CsiRecord {
+    CsiRecord {
+        timestamp,
+        rssi,
+        mac: MacAddr([0x24, 0x0a, 0xc4, 0, 0, 1]),
+        csi: heapless::Vec::from_slice(&[0, amplitude].repeat(26)).unwrap(),
+        ..Default::default()
+    }
+}
+
+fn drain(trigger: &mut Trigger) -> Vec<u32> {
+    std::iter::from_fn(|| trigger.pop())
+        .map(|r| r.timestamp)
+        .collect()
+}
+
+#[test]
+fn keeps_only_the_pre_trigger_depth_until_fired() {
+    let mut trigger = Trigger::new(TriggerConfig {
+        min_rssi: Some(-40),
+        pre_records: 3,
+        ..Default::default()
+    });
+    for t in 0..10 {
+        assert_eq!(trigger.push(frame(t, -70, 10)), Feed::Buffered);
+    }
+    assert_eq!(trigger.buffered(), 3);
+    assert_eq!(trigger.pop(), None, "nothing is released before firing");
+
+    assert_eq!(trigger.push(frame(10, -35, 10)), Feed::Fired(Cause::Rssi));
+    assert_eq!(drain(&mut trigger), [7, 8, 9, 10]);
+    assert!(matches!(trigger.push(frame(11, -70, 10)), Feed::Pass(r) if r.timestamp == 11));
+}
+
+#[test]
+fn records_stay_in_order_while_draining() {
+    let mut trigger = Trigger::new(TriggerConfig {
+        pre_records: 2,
+        ..Default::default()
+    });
+    trigger.push(frame(0, -70, 10));
+    trigger.push(frame(1, -70, 10));
+    assert!(trigger.fire());
+    assert!(!trigger.fire());
+    assert_eq!(trigger.fired(), Some(Cause::Manual));
+
+    assert_eq!(trigger.pop().unwrap().timestamp, 0);
+    // Arrives before the buffer is empty, so it queues up behind it.
+    assert_eq!(trigger.push(frame(2, -70, 10)), Feed::Buffered);
+    assert_eq!(drain(&mut trigger), [1, 2]);
+    assert!(matches!(trigger.push(frame(3, -70, 10)), Feed::Pass(_)));
+}
+
+#[test]
+fn fires_on_mac() {
+    let mut trigger = Trigger::new(TriggerConfig {
+        mac: Some(MacAddr([0x24, 0x0a, 0xc4, 0, 0, 2])),
+        ..Default::default()
+    });
+    assert_eq!(trigger.push(frame(0, -70, 10)), Feed::Buffered);
+    let mut other = frame(1, -70, 10);
+    other.mac.0[5] = 2;
+    assert_eq!(trigger.push(other), Feed::Fired(Cause::Mac));
+}
+
+#[test]
+fn fires_on_rssi_at_the_threshold() {
+    let mut trigger = Trigger::new(TriggerConfig {
+        min_rssi: Some(-50),
+        ..Default::default()
+    });
+    assert_eq!(trigger.push(frame(0, -51, 10)), Feed::Buffered);
+    assert_eq!(trigger.fired(), None);
+    assert_eq!(trigger.push(frame(1, -50, 10)), Feed::Fired(Cause::Rssi));
+}
+
+#[test]
+fn rssi_wins_over_variance_in_the_same_frame() {
+    let run = |min_rssi| {
+        let mut trigger = Trigger::new(TriggerConfig {
+            min_rssi,
+            min_variance: Some(4.0),
+            pre_records: MAX_PRE_RECORDS,
+            ..Default::default()
+        });
+        // Fill the window so that the next frame trips the variance.
+        for t in 0..VARIANCE_WINDOW as u32 - 1 {
+            let amplitude = if t % 2 == 0 { 10 } else { 16 };
+            assert_eq!(trigger.push(frame(t, -70, amplitude)), Feed::Buffered);
+        }
+        trigger.push(frame(VARIANCE_WINDOW as u32 - 1, -40, 16))
+    };
+    assert_eq!(run(None), Feed::Fired(Cause::Variance));
+    assert_eq!(run(Some(-50)), Feed::Fired(Cause::Rssi));
+}
+
+#[test]
+fn fires_on_amplitude_variance() {
+    let mut trigger = Trigger::new(TriggerConfig {
+        min_variance: Some(4.0),
+        pre_records: MAX_PRE_RECORDS,
+        ..Default::default()
+    });
+    // A steady channel never fires, however long it runs.
+    for t in 0..50 {
+        assert_eq!(trigger.push(frame(t, -70, 10)), Feed::Buffered);
+    }
+    // Alternating between 10 and 16 has a variance of 9 once it fills the window.
+    let mut fired = None;
+    for t in 50..100 {
+        let amplitude = if t % 2 == 0 { 10 } else { 16 };
+        if let Feed::Fired(cause) = trigger.push(frame(t, -70, amplitude)) {
+            fired = Some((t, cause));
+            break;
+        }
+    }
+    let (t, cause) = fired.expect("variance trigger fired");
+    assert_eq!(cause, Cause::Variance);
+    assert!(t > 50 && t < 66, "fired at {t}");
+}
+
+#[test]
+fn clamps_pre_trigger_depth() {
+    let trigger = Trigger::new(TriggerConfig {
+        pre_records: 1000,
+        ..Default::default()
+    });
+    assert_eq!(trigger.config().pre_records, MAX_PRE_RECORDS);
+}
@@ -1,18 +1,42 @@
 #![no_std]
 #![no_main]
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::fmt::Write;
 use core::u64;
 use embassy_executor::Spawner;
+use embassy_futures::select::{select, select3, select4, Either, Either3, Either4};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::pipe::Pipe;
 use embassy_sync::signal::Signal;
-use embassy_time::Duration;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
 use esp_backtrace as _;
 use esp_backtrace as _;
+use esp_csi_protocol::channel::{ChannelConfig, ChannelList, Country, HopConfig, Secondary};
+use esp_csi_protocol::command::{
+    arg, name, Command, CsiFlag, LabelAction, LogAction, NetArch, ScheduleAction, SetCsi,
+    SetFilter, SetGpio, SetTraffic, SetTrigger, SetWifi, Switch, TrafficKind,
+    WifiMode as WifiModeArg,
+};
+use esp_csi_protocol::config::{key, MAX_LINE_LEN};
+use esp_csi_protocol::event::{is_valid_label, Label};
+use esp_csi_protocol::filter::{Filter, FilterConfig, MacList, RssiBound};
+use esp_csi_protocol::frame::{FrameHeader, FrameTypes, Pairing, BEACON_LEN};
+use esp_csi_protocol::gpio::{input_pins, Debounce, Edge, GpioConfig, Polarity};
+use esp_csi_protocol::headless::{
+    BreakIn, HeadlessConfig, SettingsError, Sink, StoredSettings, CONFIG_LEN, SETTINGS_LEN,
+};
+use esp_csi_protocol::ppdu::{Bandwidths, SigModes, Stbc};
+use esp_csi_protocol::record::MAX_CSI_LEN;
+use esp_csi_protocol::schedule::{Schedule, Scheduler, Step};
+use esp_csi_protocol::state::{self, Request, State};
+use esp_csi_protocol::trigger::{Cause, Feed, Trigger, TriggerConfig, MAX_PRE_RECORDS};
+use esp_csi_protocol::{CsiRecord, Event, MacAddr, OutputFormat};
 use esp_csi_rs::{config::TrafficType, NetworkArchitechture};
-use esp_csi_rs::{CSICollector, WiFiMode};
+use esp_csi_rs::{CSICollector, CSIDataPacket, WiFiMode};
+use esp_hal::gpio::{AnyPin, Input, Pull};
 use esp_hal::peripherals;
 use esp_hal::timer::timg::TimerGroup;
 #[cfg(feature = "esp32")]
@@ -21,14 +45,134 @@ use esp_hal::uart::{Config, Uart};
 use esp_hal::usb_serial_jtag::UsbSerialJtag;
 use esp_hal::Async;
 use esp_println::println;
+use esp_storage::FlashStorage;
 use esp_wifi::{init, EspWifiController};
+use esp_wifi_sys::include::{
+    esp_wifi_deinit, esp_wifi_get_config, esp_wifi_get_mac, esp_wifi_set_bandwidth,
+    esp_wifi_set_channel, esp_wifi_set_config, esp_wifi_set_country_code,
+    esp_wifi_set_promiscuous_filter, esp_wifi_set_promiscuous_rx_cb, esp_wifi_sta_get_ap_info,
+    esp_wifi_stop, wifi_ap_record_t, wifi_bandwidth_t_WIFI_BW_HT40, wifi_config_t,
+    wifi_interface_t_WIFI_IF_AP, wifi_interface_t_WIFI_IF_STA, wifi_promiscuous_filter_t,
+    wifi_promiscuous_pkt_t, wifi_promiscuous_pkt_type_t, wifi_second_chan_t_WIFI_SECOND_CHAN_ABOVE,
+    wifi_second_chan_t_WIFI_SECOND_CHAN_BELOW, wifi_second_chan_t_WIFI_SECOND_CHAN_NONE,
+};
 use menu::*;
 
 extern crate alloc;
 
 static CSI_COLLECTOR: Mutex<CriticalSectionRawMutex, RefCell<Option<CSICollector>>> =
     Mutex::new(RefCell::new(None));
-static START_SIGNAL: Signal<CriticalSectionRawMutex, u64> = Signal::new();
+// Collection duration in seconds and optional record count of a manual start
+static START_SIGNAL: Signal<CriticalSectionRawMutex, (u64, Option<u32>)> = Signal::new();
+static STOP_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// Periodic collection windows, if scheduled
+static SCHEDULER: Mutex<CriticalSectionRawMutex, RefCell<Option<Scheduler>>> =
+    Mutex::new(RefCell::new(None));
+// Wakes the collector when the schedule is set or cleared
+static SCHEDULE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static OUTPUT_FORMAT: Mutex<CriticalSectionRawMutex, Cell<OutputFormat>> =
+    Mutex::new(Cell::new(OutputFormat::Text));
+// Sequence number of the next CSI record, restarted with every collection run
+static RECORD_SEQ: Mutex<CriticalSectionRawMutex, Cell<u32>> = Mutex::new(Cell::new(0));
+// Number of records after which the current run stops, if limited
+static RECORD_LIMIT: Mutex<CriticalSectionRawMutex, Cell<Option<u32>>> =
+    Mutex::new(Cell::new(None));
+// What the collector is doing
+static STATE: Mutex<CriticalSectionRawMutex, Cell<State>> = Mutex::new(Cell::new(State::Idle));
+// Start and end of the current run, if running
+static RUN_WINDOW: Mutex<CriticalSectionRawMutex, Cell<Option<(Instant, Instant)>>> =
+    Mutex::new(Cell::new(None));
+// Channel of the last frame received in the current run
+static LAST_CHANNEL: Mutex<CriticalSectionRawMutex, Cell<Option<u8>>> = Mutex::new(Cell::new(None));
+static LAST_ERROR: Mutex<CriticalSectionRawMutex, Cell<Option<&'static str>>> =
+    Mutex::new(Cell::new(None));
+// Time the current run was paused at, if paused
+static PAUSED_AT: Mutex<CriticalSectionRawMutex, Cell<Option<Instant>>> =
+    Mutex::new(Cell::new(None));
+// Asks the collector to pause traffic generation until resumed
+static PAUSE_TRAFFIC_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static RESUME_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// Asks a running collection to pick up configuration changes
+static RECONFIGURE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// Wakes the collector when the end of the run moves
+static RUN_END_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// Trigger conditions for the next collection, if armed
+static TRIGGER_CONFIG: Mutex<CriticalSectionRawMutex, Cell<Option<TriggerConfig>>> =
+    Mutex::new(Cell::new(None));
+// Trigger and pre-trigger records of the current run, if armed
+static TRIGGER: Mutex<CriticalSectionRawMutex, RefCell<Option<Trigger>>> =
+    Mutex::new(RefCell::new(None));
+// GPIO start/stop and event inputs, if set
+static GPIO_CONFIG: Mutex<CriticalSectionRawMutex, Cell<Option<GpioConfig>>> =
+    Mutex::new(Cell::new(None));
+// Asks the GPIO task to pick up changed pin settings
+static GPIO_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// Label of the records that follow, empty if not set
+static LABEL: Mutex<CriticalSectionRawMutex, RefCell<Label>> =
+    Mutex::new(RefCell::new(Label::new()));
+// Filters frames pass before output, together with their counters
+static FILTER: Mutex<CriticalSectionRawMutex, RefCell<Option<Filter>>> =
+    Mutex::new(RefCell::new(None));
+// Records waiting for the MAC header of their frame, and headers waiting for their record
+static PAIRING: Mutex<CriticalSectionRawMutex, RefCell<Pairing<CsiRecord, 8>>> =
+    Mutex::new(RefCell::new(Pairing::new()));
+// Whether the promiscuous callback taps headers, which lasts until WiFi is set up again
+static TAPPING: Mutex<CriticalSectionRawMutex, Cell<bool>> = Mutex::new(Cell::new(false));
+// Signals that a record waits in PAIRING
+static PAIRING_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// Channel and country chosen with set-wifi, the channel is left to the driver if not set
+static CHANNEL_CONFIG: Mutex<CriticalSectionRawMutex, Cell<Option<ChannelConfig>>> =
+    Mutex::new(Cell::new(None));
+static COUNTRY: Mutex<CriticalSectionRawMutex, Cell<Country>> =
+    Mutex::new(Cell::new(Country::DEFAULT));
+// Channels a sniffer cycles through, and the signal starting (true) or ending the cycle
+static HOP: Mutex<CriticalSectionRawMutex, RefCell<Option<HopConfig>>> =
+    Mutex::new(RefCell::new(None));
+static HOP_SIGNAL: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+// Starts looking up the traffic target of a station run with true, ends with false
+static TRAFFIC_SIGNAL: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+// Headless settings as stored in flash
+static HEADLESS: Mutex<CriticalSectionRawMutex, Cell<HeadlessConfig>> =
+    Mutex::new(Cell::new(HeadlessConfig {
+        enabled: false,
+        sink: Sink::Flash,
+        duration_s: None,
+    }));
+// Set while running headless, until a user breaks into the CLI
+static HEADLESS_ACTIVE: Mutex<CriticalSectionRawMutex, Cell<bool>> = Mutex::new(Cell::new(false));
+// Lines on their way to the flash log
+static LOG_PIPE: Pipe<CriticalSectionRawMutex, LOG_PIPE_SIZE> = Pipe::new();
+// Keeps lines from different tasks from interleaving in the pipe
+static LOG_LOCK: Mutex<CriticalSectionRawMutex, ()> = Mutex::new(());
+// End of the flash log relative to its start, found on first use
+static LOG_END: Mutex<CriticalSectionRawMutex, Cell<Option<u32>>> = Mutex::new(Cell::new(None));
+// Lines lost to a full pipe or a full flash log
+static LOG_DROPPED: Mutex<CriticalSectionRawMutex, Cell<u32>> = Mutex::new(Cell::new(0));
+
+// Chip the firmware was built for, as selected by the cargo features
+#[cfg(feature = "esp32")]
+const CHIP: &str = "esp32";
+#[cfg(feature = "esp32c3")]
+const CHIP: &str = "esp32c3";
+#[cfg(feature = "esp32c6")]
+const CHIP: &str = "esp32c6";
+#[cfg(feature = "esp32s3")]
+const CHIP: &str = "esp32s3";
+
+// Flash regions, matching partitions.csv
+const CONFIG_OFFSET: u32 = 0x200000;
+// Collector settings stored with the headless settings, in the same sector
+const SETTINGS_OFFSET: u32 = 0x200800;
+const LOG_OFFSET: u32 = 0x201000;
+const LOG_SIZE: u32 = 0x1ff000;
+const SECTOR_SIZE: u32 = 4096;
+// Bytes written to the flash log at once
+const LOG_PAGE_SIZE: usize = 256;
+const LOG_PIPE_SIZE: usize = 8192;
+// Time between lookups of the AP replies to the generated traffic come from
+const TRAFFIC_LOOKUP: Duration = Duration::from_millis(500);
+// Time the tasks of a stopped WiFi stack get to exit before it is deinitialized
+const WIFI_TEARDOWN: Duration = Duration::from_millis(200);
 
 #[derive(Default)]
 struct Context {
@@ -49,22 +193,22 @@ const ROOT_MENU: Menu<SerialInterfaceType, Context> = Menu {
                 function: set_traffic,
                 parameters: &[
                     Parameter::Named {
-                        parameter_name: "enable",
+                        parameter_name: arg::ENABLE,
                         help: Some("Enables Traffic Generation"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "type",
+                        parameter_name: arg::TYPE,
                         argument_name: "type",
                         help: Some("Traffic Type"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "interval",
-                        argument_name: "interval",
+                        parameter_name: arg::INTERVAL_MS,
+                        argument_name: "interval-ms",
                         help: Some("Traffic Generation Interval"),
                     },
                 ],
             },
-            command: "set-traffic",
+            command: name::SET_TRAFFIC,
             help: Some(
                 "set-traffic - Configure traffic-related parameters.
 
@@ -83,19 +227,21 @@ Examples:
 Description:
   This command allows you to configure traffic parameters for the CSI collection process.
   You can enable traffic generation, set the traffic type, and specify the interval 
-  between generated packets.",
+  between generated packets.
+
+  `set-filter --replies=on` keeps only the CSI of the replies.",
             ),
         },
         &Item {
             item_type: ItemType::Callback {
                 function: set_network,
                 parameters: &[Parameter::NamedValue {
-                    parameter_name: "arch",
+                    parameter_name: arg::ARCH,
                     argument_name: "arch",
                     help: Some("Desired Network Architecture"),
                 }],
             },
-            command: "set-network",
+            command: name::SET_NETWORK,
             help: Some("set-network - Configure network architecture settings.
 
 NOTE: Setting the network architechture is only necessary if NTP synchronization is desired
@@ -125,24 +271,24 @@ Description:
                 function: set_csi,
                 parameters: &[
                     Parameter::Named {
-                        parameter_name: "disable-lltf",
+                        parameter_name: arg::DISABLE_LLTF,
                         help: Some("Disable LLTF"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-htltf",
+                        parameter_name: arg::DISABLE_HTLTF,
                         help: Some("Disable HTLTF"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-stbc-htltf",
+                        parameter_name: arg::DISABLE_STBC_HTLTF,
                         help: Some("Disable STBC HTLTF"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-ltf-merge",
+                        parameter_name: arg::DISABLE_LTF_MERGE,
                         help: Some("Disable LTF Merge"),
                     },
                 ],
             },
-            command: "set-csi",
+            command: name::SET_CSI,
             help: Some("set-csi - Configure CSI feature flags.
 
 Usage:
@@ -169,50 +315,50 @@ configurations if necessary."),
                 function: set_csi,
                 parameters: &[
                     Parameter::Named {
-                        parameter_name: "disable-csi",
+                        parameter_name: arg::DISABLE_CSI,
                         help: Some("Disable acquisition of CSI"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-csi-legacy",
+                        parameter_name: arg::DISABLE_CSI_LEGACY,
                         help: Some("Disable acquisition of L-LTF when receiving a 11g PPDU"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-csi-ht20",
+                        parameter_name: arg::DISABLE_CSI_HT20,
                         help: Some("Disable acquisition of HT-LTF when receiving an HT20 PPDU"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-csi-ht40",
+                        parameter_name: arg::DISABLE_CSI_HT40,
                         help: Some("Disable acquisition of HT-LTF when receiving an HT40 PPDU"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-csi-su",
+                        parameter_name: arg::DISABLE_CSI_SU,
                         help: Some("Disable acquisition of HE-LTF when receiving an HE20 SU PPDU"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-csi-mu",
+                        parameter_name: arg::DISABLE_CSI_MU,
                         help: Some("Disable acquisition of HE-LTF when receiving an HE20 MU PPDU"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-csi-dcm",
+                        parameter_name: arg::DISABLE_CSI_DCM,
                         help: Some("Disable acquisition of HE-LTF when receiving an HE20 DCM applied PPDU"),
                     },
                     Parameter::Named {
-                        parameter_name: "disable-csi-beamformed",
+                        parameter_name: arg::DISABLE_CSI_BEAMFORMED,
                         help: Some("Disable acquisition of HE-LTF when receiving an HE20 Beamformed applied PPDU"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "csi-he-stbc",
+                        parameter_name: arg::CSI_HE_STBC,
                         argument_name: "csihestbc",
                         help: Some("When receiving an STBC applied HE PPDU 0-3 value"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "val-scale-cfg",
+                        parameter_name: arg::VAL_SCALE_CFG,
                         argument_name: "valscalecfg",
                         help: Some("Value 0-3"),
                     },
                 ],
             },
-            command: "set-csi",
+            command: name::SET_CSI,
             help: Some("set-csi - Configure CSI feature flags.
 
 Usage:
@@ -248,42 +394,67 @@ configurations if necessary."),
                 function: set_wifi,
                 parameters: &[
                     Parameter::NamedValue {
-                        parameter_name: "mode",
+                        parameter_name: arg::MODE,
                         argument_name: "wifimode",
                         help: Some("Specify operation mode"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "max-connections",
+                        parameter_name: arg::MAX_CONNECTIONS,
                         argument_name: "wifimaxconn",
                         help: Some("Specify maxiumum number of allowed connections (if configured as AP)"),
                     },
                     Parameter::Named {
-                        parameter_name: "hide-ssid",
+                        parameter_name: arg::HIDE_SSID,
                         help: Some("Hide SSID (if configured as AP)"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "ap-ssid",
+                        parameter_name: arg::AP_SSID,
                         argument_name: "apssid",
                         help: Some("The SSID for the AP"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "ap-password",
+                        parameter_name: arg::AP_PASSWORD,
                         argument_name: "appassword",
                         help: Some("The password for the AP"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "sta-ssid",
+                        parameter_name: arg::STA_SSID,
                         argument_name: "stassid",
                         help: Some("The SSID for the station"),
                     },
                     Parameter::NamedValue {
-                        parameter_name: "sta-password",
+                        parameter_name: arg::STA_PASSWORD,
                         argument_name: "stapassword",
                         help: Some("The password for the station"),
                     },
+                    Parameter::NamedValue {
+                        parameter_name: arg::CHANNEL,
+                        argument_name: "channel",
+                        help: Some("Channel to use, 0 to leave it to the driver"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::SECONDARY,
+                        argument_name: "secondary",
+                        help: Some("Secondary channel of a 40 MHz channel"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::COUNTRY,
+                        argument_name: "country",
+                        help: Some("Country code the channel must be allowed in"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::HOP,
+                        argument_name: "hop",
+                        help: Some("Channels the sniffer cycles through"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::DWELL_MS,
+                        argument_name: "dwell-ms",
+                        help: Some("Time spent on each hop channel in milliseconds"),
+                    },
                 ],
             },
-            command: "set-wifi",
+            command: name::SET_WIFI,
             help: Some("set-wifi - Configure WiFi settings.
 
 Usage:
@@ -299,10 +470,18 @@ Options:
   --ap-password=<PASSWORD>                 Set the password for the AP (default: empty).
   --sta-ssid=<SSID>                        Set the SSID for the station (default: empty).
   --sta-password=<PASSWORD>                Set the password for the station (default: empty).
+  --channel=<1-14>                         Set the channel, 0 leaves it to the driver (default: 0).
+  --secondary=<none|above|below>           Set the secondary channel for 40 MHz (default: none).
+  --country=<CODE>                         Set the country the channel must be allowed in (default: CN).
+  --hop=<CHANNELS>                         Cycle a sniffer through these channels, or none (default: none).
+  --dwell-ms=<MILLISECONDS>                Set the time spent on each hop channel (default: 200).
 
 Examples:
   set-wifi --mode=ap --max-connections=5 --hide-ssid
   set-wifi --mode=station
+  set-wifi --mode=sniffer --channel=6
+  set-wifi --mode=ap --channel=3 --secondary=above --country=US
+  set-wifi --mode=sniffer --hop=1,6,11 --dwell-ms=200
 
 Description:
   Use this command to configure WiFi settings for the CSI collection process.
@@ -312,20 +491,63 @@ Description:
       - `sniffer`: Monitor WiFi traffic passively.
       - `ap-station`: Simultaneously act as an AP and connect to another network.
 
-  - Use `--hide-ssid` to make the SSID of an AP invisible to scanning devices."),
+  - Use `--hide-ssid` to make the SSID of an AP invisible to scanning devices.
+
+  - The channel is used in the `sniffer` and `ap` modes, and must be allowed in the country
+    along with its secondary channel. Channel 14 is only allowed in JP, and has no secondary
+    channel. An AP is set up on the channel when WiFi is set up, and the driver places its
+    secondary channel. A sniffer is tuned to it at the start of every collection. In
+    `ap-station` mode the AP follows the channel of the network the station joins, so no
+    channel can be set. Only the country codes the driver knows are accepted.
+
+  - A sniffer with hop channels cycles through them during a collection, on 20 MHz channels,
+    and prints a `hop` event with the channel and a timestamp at every switch. Every record
+    carries the channel it was captured on."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: set_output,
+                parameters: &[Parameter::NamedValue {
+                    parameter_name: arg::FORMAT,
+                    argument_name: "format",
+                    help: Some("Output format for CSI records"),
+                }],
+            },
+            command: name::SET_OUTPUT,
+            help: Some("set-output - Configure how CSI records are printed.
+
+Usage:
+  set-output [OPTIONS]
+
+Options:
+  --format=<text|csv>          Set the output format for CSI records and events (default: text).
+
+Examples:
+  set-output --format=csv
+  set-output --format=text
+
+Description:
+  This command selects how CSI records and collection events are printed:
+    - `text`: One `key: value` line per field followed by the raw CSI data.
+    - `csv`: One `CSI_DATA` line per record, following the ESP-IDF csi_recv layout."),
         },
         &Item {
             item_type: ItemType::Callback {
                 function: start_csi_collect,
                 parameters: &[
                     Parameter::NamedValue {
-                        parameter_name: "duration",
+                        parameter_name: arg::DURATION,
                         argument_name: "duration",
                         help: Some("Duration of Collection"),
                     },
+                    Parameter::NamedValue {
+                        parameter_name: arg::COUNT,
+                        argument_name: "count",
+                        help: Some("Number of CSI Records to Collect"),
+                    },
                 ],
             },
-            command: "start",
+            command: name::START,
             help: Some("start - Start the CSI collection process.
 
 NOTE: If configured as a Station, make sure there is already a running/started Access Point
@@ -335,11 +557,14 @@ Usage:
 
 Options:
   --duration=<SECONDS>         Specify the duration for the CSI collection process.
+  --count=<NUMBER>             Stop once this many CSI records have been collected.
 
 Examples:
   start
   start --duration=120
   start --duration=300
+  start --count=1000
+  start --count=1000 --duration=60
 
 Description:
   This command initiates the CSI collection process for a specified duration.
@@ -349,208 +574,1768 @@ Description:
   During the collection process:
   - Traffic generation will occur based on the configured parameters (if enabled).
   - CSI data will be collected and printed to the console.
-  - After the specified duration, the process will terminate automatically. Otherwise collection runs forever."),
+  - After the specified duration, the process will terminate automatically. Otherwise collection runs forever.
+  - With `--count`, the process terminates once that many records have been printed, or
+    when the duration elapses first. The stopped event reports the records collected and
+    the elapsed time."),
         },
-
         &Item {
             item_type: ItemType::Callback {
-                function: show_config,
+                function: stop_csi_collect,
                 parameters: &[],
             },
-            command: "show-config",
-            help: Some("show-config - Display the current configuration settings.
+            command: name::STOP,
+            help: Some("stop - Stop an ongoing CSI collection process.
 
 Usage:
-  show-config
+  stop
 
 Examples:
-  show-config
+  stop
 
 Description:
-  Use this command to display the current configuration for all parameters, including:
-  - Traffic settings (enabled/disabled, type, interval).
-  - Network architecture (star, mesh, or none).
-  - CSI feature flags (enabled/disabled for LLTF, HTLTF, STBC HTLTF, LTF Merge).
-  - WiFi settings (mode, maximum connections, SSID visibility).
+  This command ends the current CSI collection before its configured duration elapses.
+  The configuration is kept, so collection can be started again using `start`."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: pause_csi_collect,
+                parameters: &[Parameter::Named {
+                    parameter_name: arg::TRAFFIC,
+                    help: Some("Pause Traffic Generation as Well"),
+                }],
+            },
+            command: name::PAUSE,
+            help: Some("pause - Pause CSI output of the ongoing collection.
 
-  The output provides a summary of all settings, allowing you to review and verify configurations
-  before starting the CSI collection process."),
+Usage:
+  pause [OPTIONS]
+
+Options:
+  --traffic                    Pause traffic generation as well.
+
+Examples:
+  pause
+  pause --traffic
+
+Description:
+  This command suppresses CSI records until `resume` is issued, and prints a paused event.
+  The WiFi connection set up for the collection is kept and record sequence numbers continue
+  where they left off after resuming. The collection duration keeps running while paused."),
         },
         &Item {
             item_type: ItemType::Callback {
-                function: reset_config,
+                function: resume_csi_collect,
                 parameters: &[],
             },
-            command: "reset-config",
-            help: Some("reset-config - Reset all configurations to their default values.
+            command: name::RESUME,
+            help: Some("resume - Resume a paused collection.
 
 Usage:
-  reset-config
+  resume
 
 Examples:
-  reset-config
+  resume
 
 Description:
-  This command resets all configurations to their default values:
-  - Traffic settings: Disabled, type set to ICMP, interval set to 100ms.
-  - Network architecture: Sniffer.
-  - CSI feature flags: All enabled (LLTF, HTLTF, STBC HTLTF, LTF Merge).
-  - WiFi settings: Mode set to Sniffer, maximum AP connections set to 1, SSID visible.
-
-  Use this command if you want to start fresh with the default configuration."),
+  This command resumes CSI output, and traffic generation if it was paused, and prints a
+  resumed event with the time spent paused."),
         },
+        &Item {
+            item_type: ItemType::Callback {
+                function: show_status,
+                parameters: &[],
+            },
+            command: name::STATUS,
+            help: Some("status - Display what the collector is doing.
 
-    ],
-    entry: Some(enter_root),
-    exit: None,
-};
+Usage:
+  status
 
-fn enter_root(
-    _menu: &Menu<SerialInterfaceType, Context>,
-    interface: &mut SerialInterfaceType,
-    _context: &mut Context,
-) {
-    writeln!(
-        interface,
-        "
-    Welcome to the CSI Collection CLI utility!"
-    )
-    .unwrap();
-    writeln!(interface, "").unwrap();
-    writeln!(
-        interface,
-        "Available Commands:
-    set-traffic         Configure traffic-related parameters (e.g., type, interval).
-    set-network         Configure network architecture settings.
-    set-csi             Configure CSI feature flags (e.g., LLTF, HTLTF).
-    set-wifi            Configure WiFi settings (e.g., mode, SSID visibility).
-    start               Start the CSI collection process with a defined duration.
-    show-config         Display the current configuration settings.
-    reset-config        Reset all configurations to their default values.
-    help                Display this help menu or details for a specific command.
+Examples:
+  status
 
-    For more information on a specific command, type:
-    help <command>
+Description:
+  This command prints the collection state, one of idle, starting, initialising,
+  connecting, collecting, paused, stopping or error, followed by the elapsed and
+  remaining time of the current run, the records collected, the channel of the last
+  frame, the association state in station modes and the last error.
 
-    Example:
-    help set-traffic"
-    )
-    .unwrap();
-}
+  Commands that don't fit the state, such as `start` during a collection or `resume`
+  without a pause, are rejected with the reason."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: set_trigger,
+                parameters: &[
+                    Parameter::Named {
+                        parameter_name: arg::OFF,
+                        help: Some("Disarm the Trigger"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::RSSI,
+                        argument_name: "rssi",
+                        help: Some("Minimum RSSI in dBm"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::MAC,
+                        argument_name: "mac",
+                        help: Some("Transmitter MAC Address"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::VARIANCE,
+                        argument_name: "variance",
+                        help: Some("Minimum Amplitude Variance"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::PRE,
+                        argument_name: "pre",
+                        help: Some("Records Kept From Before the Trigger"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::POST,
+                        argument_name: "post",
+                        help: Some("Seconds Collected After the Trigger"),
+                    },
+                ],
+            },
+            command: name::SET_TRIGGER,
+            help: Some("set-trigger - Arm triggered capture for the next collection.
 
-// When you are okay with using a nightly compiler it's better to use https://docs.rs/static_cell/2.1.0/static_cell/macro.make_static.html
-macro_rules! mk_static {
-    ($t:ty,$val:expr) => {{
-        static STATIC_CELL: static_cell::StaticCell<$t> = static_cell::StaticCell::new();
-        #[deny(unused_attributes)]
-        let x = STATIC_CELL.uninit().write(($val));
-        x
-    }};
-}
+Usage:
+  set-trigger [OPTIONS]
 
-#[esp_hal_embassy::main]
-async fn main(spawner: Spawner) {
-    // Initalize ESP device and acquire peripherals
-    let config = esp_hal::Config::default().with_cpu_clock(esp_hal::clock::CpuClock::max());
-    let peripherals = esp_hal::init(config);
+Options:
+  --off                        Disarm the trigger.
+  --rssi=<DBM>                 Fire on a frame at or above this RSSI.
+  --mac=<MAC>                  Fire on the first frame from this transmitter, e.g. AA:BB:CC:DD:EE:FF.
+  --variance=<LEVEL>           Fire once the variance of the mean frame amplitude over the last
+                               16 frames reaches this level.
+  --pre=<RECORDS>              Records kept from before the trigger, at most 32 (default: 10).
+  --post=<SECONDS>             Duration collected after the trigger (default: 10).
 
-    // Allocate heap space
-    esp_alloc::heap_allocator!(72 * 1024);
+Examples:
+  set-trigger --rssi=-40 --pre=20 --post=30
+  set-trigger --mac=24:0A:C4:00:00:01
+  set-trigger --variance=4.5
+  set-trigger
+  set-trigger --off
 
-    // Initalize Embassy
-    let timg1 = TimerGroup::new(peripherals.TIMG1);
-    esp_hal_embassy::init(timg1.timer0);
+Description:
+  While armed, a collection holds records back in a ring buffer of the most recent `--pre`
+  records instead of printing them. Once any condition fires, or `trigger` is issued, a
+  triggered event is printed, the buffered records follow, and collection continues for
+  `--post` seconds before stopping. Without conditions, only `trigger` fires.
 
-    // Instantiate peripherals for EspWifiController
-    let timer = esp_hal::timer::timg::TimerGroup::new(peripherals.TIMG0);
-    let mut rng = esp_hal::rng::Rng::new(peripherals.RNG);
-    let seed = rng.random();
+  Arguments left out keep their previous values. Changes apply from the next collection."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: fire_trigger,
+                parameters: &[],
+            },
+            command: name::TRIGGER,
+            help: Some("trigger - Fire the trigger of the ongoing collection.
 
-    // Initialize ESP WiFi Controller
-    let init = &*mk_static!(
-        EspWifiController<'static>,
-        init(timer.timer0, rng, peripherals.RADIO_CLK).unwrap()
-    );
+Usage:
+  trigger
 
-    // Create an instance for the CSI Collector
-    let csi_config = CSICollector::new_with_defaults();
+Examples:
+  trigger
 
-    // Pass Collector Instance to Global Context
-    CSI_COLLECTOR.lock(|config| {
-        config.replace(Some(csi_config));
-    });
+Description:
+  This command fires the trigger by hand, as if one of its conditions had fired. The
+  collection must have been started with the trigger armed using `set-trigger`."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: set_gpio,
+                parameters: &[
+                    Parameter::Named {
+                        parameter_name: arg::OFF,
+                        help: Some("Stop Watching Both Pins"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::START_PIN,
+                        argument_name: "start-pin",
+                        help: Some("Start/Stop Input Pin"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::EVENT_PIN,
+                        argument_name: "event-pin",
+                        help: Some("Event Input Pin"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::POLARITY,
+                        argument_name: "polarity",
+                        help: Some("Active Level"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::DEBOUNCE_MS,
+                        argument_name: "debounce-ms",
+                        help: Some("Debounce Time"),
+                    },
+                ],
+            },
+            command: name::SET_GPIO,
+            help: Some("set-gpio - Configure the GPIO start/stop and event inputs.
 
-    // Spawn the CSI Collection Task
-    spawner
-        .spawn(csi_collector(peripherals.WIFI, init, seed as u64, spawner))
-        .unwrap();
+Usage:
+  set-gpio [OPTIONS]
 
-    // Instantiate Serial Interface for CLI host communication
-    #[cfg(not(feature = "esp32"))]
-    let serial = UsbSerialJtag::new(peripherals.USB_DEVICE).into_async();
+Options:
+  --off                        Stop watching both pins.
+  --start-pin=<GPIO>           Pin whose active edges start and stop collection.
+  --event-pin=<GPIO>           Pin whose edges are reported as gpio events.
+  --polarity=<high|low>        Level both pins are driven to when active (default: high).
+  --debounce-ms=<NUMBER>       Ignore edges this soon after the last one on a pin (default: 20).
 
-    #[cfg(feature = "esp32")]
-    let serial = {
-        let (tx_pin, rx_pin) = (peripherals.GPIO1, peripherals.GPIO3);
-        let config = Config::default();
-        Uart::new(peripherals.UART0, config)
-            .unwrap()
-            .with_tx(tx_pin)
-            .with_rx(rx_pin)
-            .into_async()
-    };
+Usable Pins:
+  esp32                        4, 13, 14, 18, 19, 21, 22, 23, 25, 26, 27, 32, 33
+  esp32c3                      0, 1, 3, 4, 5, 6, 7, 10
+  esp32c6                      0, 1, 2, 3, 6, 7, 10, 11, 18-23
+  esp32s3                      1, 2, 4-18, 21, 38-42, 47, 48
 
-    // Create a buffer to store CLI input
-    let mut clibuf = [0u8; 64];
-    // Instantiate Context placeholder
-    let mut context = Context::default();
-    // Instantiate CLI runner with root menu, buffer, and serial
-    let mut runner = Runner::new(ROOT_MENU, &mut clibuf, serial, &mut context);
+Examples:
+  set-gpio --start-pin=4 --polarity=low
+  set-gpio --event-pin=5 --debounce-ms=0
+  set-gpio --off
 
-    loop {
-        // Create single element buffer for serial characters
-        let mut buf = [0_u8; 1];
-        embedded_io_async::Read::read(&mut runner.interface, &mut buf)
-            .await
-            .unwrap();
-        // Pass read byte to CLI runner for processing
-        runner.input_byte(buf[0], &mut context);
-    }
-}
+Description:
+  An active edge on the start pin starts a collection when idle, as `start` without a
+  duration would, and stops it when collecting. Every edge on the event pin prints a gpio
+  event with the edge and a timestamp in microseconds since boot, interleaved with the
+  CSI records. Pins are pulled to their inactive level.
 
-#[embassy_executor::task]
-async fn csi_collector(
-    wifi: peripherals::WIFI,
-    wifi_hw: &'static EspWifiController<'static>,
-    seed: u64,
-    spawner: Spawner,
-) {
-    // Wait for first start signal to kick off collection activity
-    let mut interval = START_SIGNAL.wait().await;
+  Arguments left out keep their previous values. Changes apply right away."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: insert_mark,
+                parameters: &[Parameter::Mandatory {
+                    parameter_name: arg::LABEL,
+                    help: Some("Annotation"),
+                }],
+            },
+            command: name::MARK,
+            help: Some("mark - Insert an annotation into the stream.
 
-    // // Obtain copy from CSI collector in global context
-    let mut collector =
-        CSI_COLLECTOR.lock(|collector| (collector.borrow().as_ref().unwrap().clone()));
+Usage:
+  mark <LABEL>
 
-    // Initalize CSI collector
-    match collector.init(wifi, wifi_hw, seed, &spawner) {
-        Ok(_) => {}
-        Err(_e) => {
-            println!("Error Initializing CSI Collector");
-        }
-    }
+Examples:
+  mark door_opened
+  mark trial-3
 
-    loop {
-        // Start Collection
-        collector.start(interval).await;
-        // Reset Start Signal Once collection completes
-        START_SIGNAL.reset();
-        // Update Interval & Start Again when signalled
-        interval = START_SIGNAL.wait().await;
-        // Obtain new configuration before starting again
-        collector = CSI_COLLECTOR.lock(|collector| (collector.borrow().as_ref().unwrap().clone()));
-    }
-}
+Description:
+  This command prints a mark event carrying the label and a timestamp in microseconds
+  since boot, interleaved with the CSI records in the active output format. Labels are
+  up to 32 printable characters without spaces, `,`, `=` or `\"`, and cannot start with `-`."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: set_label,
+                parameters: &[
+                    Parameter::Optional {
+                        parameter_name: arg::ACTION,
+                        help: Some("set or clear"),
+                    },
+                    Parameter::Optional {
+                        parameter_name: arg::LABEL,
+                        help: Some("Label"),
+                    },
+                ],
+            },
+            command: name::LABEL,
+            help: Some("label - Set the label of the records that follow.
+
+Usage:
+  label [set <LABEL>|clear]
+
+Examples:
+  label set walking
+  label clear
+  label
+
+Description:
+  `label set` prints a label event, and the records that follow belong to the label until
+  it is changed or cleared. The label stays set across collection runs, and every run
+  repeats it right after the started event. `label clear` prints a label event with an
+  empty label. Without an action, the current label is shown. Labels follow the same
+  rules as for `mark`."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: set_headless,
+                parameters: &[
+                    Parameter::Named {
+                        parameter_name: arg::ENABLE,
+                        help: Some("Run Headless From the Next Boot"),
+                    },
+                    Parameter::Named {
+                        parameter_name: arg::DISABLE,
+                        help: Some("Start With the CLI From the Next Boot"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::SINK,
+                        argument_name: "sink",
+                        help: Some("Output While Headless"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::DURATION,
+                        argument_name: "duration",
+                        help: Some("Collection Duration"),
+                    },
+                ],
+            },
+            command: name::SET_HEADLESS,
+            help: Some("set-headless - Configure headless operation, stored in flash.
+
+Usage:
+  set-headless [OPTIONS]
+
+Options:
+  --enable                     Run headless from the next boot.
+  --disable                    Start with the CLI from the next boot.
+  --sink=<serial|flash>        Where records and events go while headless (default: flash).
+  --duration=<SECONDS>         Duration of the collection started at boot, 0 for a week
+                               (default: a week).
+
+Examples:
+  set-headless --enable --sink=flash --duration=3600
+  set-headless --disable
+
+Description:
+  A headless board skips the CLI banner, sets the collector up as it was when these
+  settings were stored, starts collecting at boot, and writes records and events to the
+  sink. There is no UDP sink. The flash log is read back with
+  `log show`. To take over a headless board, connect and press Enter three times or
+  Ctrl-C. This stops the collection and brings up the CLI.
+
+  The settings are stored in flash right away, together with the traffic, network, CSI,
+  WiFi, channel, output, trigger, GPIO and filter settings in effect, and apply from the
+  next boot. Run `set-headless` again after changing those. Arguments left out keep their
+  previous values. `reset-config` does not change them."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: flash_log_command,
+                parameters: &[Parameter::Optional {
+                    parameter_name: arg::ACTION,
+                    help: Some("show or clear"),
+                }],
+            },
+            command: name::LOG,
+            help: Some("log - Read back or clear the flash log.
+
+Usage:
+  log [show|clear]
+
+Examples:
+  log
+  log clear
+
+Description:
+  `log show`, or `log` alone, prints the records and events written to the flash log
+  while running headless, in the format they were written in, followed by the log size
+  and the number of lines dropped because the log or its queue was full. `log clear`
+  empties the log."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: set_filter,
+                parameters: &[
+                    Parameter::Named {
+                        parameter_name: arg::OFF,
+                        help: Some("Let Every Frame Through"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::ALLOW_MAC,
+                        argument_name: "allow-mac",
+                        help: Some("MAC Patterns to Keep"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::DENY_MAC,
+                        argument_name: "deny-mac",
+                        help: Some("MAC Patterns to Drop"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::MIN_RSSI,
+                        argument_name: "min-rssi",
+                        help: Some("Lowest RSSI Kept in dBm, or none"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::MAX_RSSI,
+                        argument_name: "max-rssi",
+                        help: Some("Highest RSSI Kept in dBm, or none"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::FRAME_TYPES,
+                        argument_name: "frame-types",
+                        help: Some("Frame Types or Subtypes to Keep"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::MAX_RATE,
+                        argument_name: "max-rate",
+                        help: Some("Frames per Second Let Through in Total"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::MAX_RATE_PER_MAC,
+                        argument_name: "max-rate-per-mac",
+                        help: Some("Frames per Second Let Through per Transmitter"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::SIG_MODE,
+                        argument_name: "sig-mode",
+                        help: Some("PHYs to Keep"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::BANDWIDTH,
+                        argument_name: "bandwidth",
+                        help: Some("Bandwidths to Keep in MHz"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::STBC,
+                        argument_name: "stbc",
+                        help: Some("Keep Frames Sent with STBC (any, on or off)"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::DEDUP,
+                        argument_name: "dedup",
+                        help: Some("Recent Frames Checked for Retransmissions"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::BSSID,
+                        argument_name: "bssid",
+                        help: Some("BSS to Keep Frames Of"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::SSID,
+                        argument_name: "ssid",
+                        help: Some("SSID of the BSS to Keep Frames Of"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::REPLIES,
+                        argument_name: "replies",
+                        help: Some("Only Keep Replies to the Generated Traffic (on/off)"),
+                    },
+                ],
+            },
+            command: name::SET_FILTER,
+            help: Some("set-filter - Filter frames before output.
+
+Usage:
+  set-filter [OPTIONS]
+
+Options:
+  --off                        Let every frame through.
+  --allow-mac=<PATTERNS>       Only keep frames from these transmitters, or none for all.
+  --deny-mac=<PATTERNS>        Drop frames from these transmitters, or none.
+  --min-rssi=<DBM>             Drop frames received below this RSSI, or none.
+  --max-rssi=<DBM>             Drop frames received above this RSSI, or none.
+  --frame-types=<TYPES>        Only keep these frame types or subtypes, or all.
+  --max-rate=<FPS>             Shed frames above this rate in total.
+  --max-rate-per-mac=<FPS>     Shed frames above this rate from each transmitter.
+  --sig-mode=<PHYS>            Only keep frames sent with these PHYs, or any.
+  --bandwidth=<MHZ>            Only keep frames sent with these bandwidths, or any.
+  --stbc=<any|on|off>          Keep frames sent with or without STBC, or any.
+  --dedup=<FRAMES>             Drop retransmissions of the last 1 to 32 frames.
+  --bssid=<MAC>                Only keep frames of this BSS.
+  --ssid=<SSID>                Only keep frames of the BSS advertising this SSID.
+  --replies=<on|off>           Only keep frames sent to this device by the traffic target.
+
+Examples:
+  set-filter --allow-mac=24:0A:C4:00:00:01,24:0A:C4:00:00:02
+  set-filter --allow-mac=24:0A:C4 --deny-mac=24:0A:C4:00:00:09
+  set-filter --deny-mac=*:*:*:*:*:01
+  set-filter --allow-mac=none
+  set-filter --min-rssi=-70
+  set-filter --min-rssi=-80 --max-rssi=-20
+  set-filter --max-rssi=none
+  set-filter --frame-types=beacon --allow-mac=24:0A:C4:00:00:01
+  set-filter --frame-types=mgmt,qos-data
+  set-filter --max-rate=100 --max-rate-per-mac=20
+  set-filter --sig-mode=ht --bandwidth=20 --stbc=off
+  set-filter --sig-mode=legacy,ht,he --bandwidth=any
+  set-filter --dedup=8
+  set-filter --bssid=24:0A:C4:00:00:01
+  set-filter --ssid=Lab_Network
+  set-filter --replies=on
+  set-filter --replies=off
+  set-filter --off
+
+Description:
+  Patterns are comma separated MAC addresses, up to 8 per list. A `*` matches any byte, and
+  a pattern with fewer than six bytes, such as the OUI `24:0A:C4`, matches by prefix. The
+  deny list wins over the allow list. The RSSI bounds are inclusive and compared against the
+  RSSI the radio reports for each frame. Dropped frames do not use up sequence numbers.
+
+  Frame types are mgmt, ctrl and data, or one of the subtypes assoc-req, assoc-resp,
+  probe-req, probe-resp, beacon, disassoc, auth, deauth, action, block-ack, rts, cts, ack,
+  null, qos-data and qos-null. They narrow the promiscuous filter of the driver and are
+  checked against the frame control field of every frame, so they need the sniffer mode,
+  where frames whose header was not seen are dropped.
+
+  The rate limits are token buckets that allow bursts of up to a second's worth of frames,
+  timed by the receive timestamps. They apply after every other stage, and `status` lists
+  the frames shed for each of the last 16 transmitters heard from.
+
+  The PHY is legacy (802.11b/g), ht (802.11n) or he (802.11ax, ESP32-C6 only), and the
+  bandwidth is 20 or 40. Together with `--stbc` they select frames by the receive metadata,
+  so every record of a run carries a CSI buffer of the same length and layout.
+
+  The duplicate check remembers the transmitter and sequence control field of the last
+  frames, and drops frames with the retry bit set that match one of them. It needs the
+  sniffer mode. Frames whose header or sequence number was not seen pass, and `status`
+  counts them as unchecked.
+
+  A BSS is selected by its BSSID, or by an SSID with underscores standing for spaces. The
+  BSSID is worked out from the addresses of every frame, so this needs the sniffer mode.
+  An SSID is looked for in beacons and probe responses, and the first BSS heard advertising
+  it is kept until the filter or the channel changes, hops included. No frames pass until
+  then, and `status` shows the BSSID found. Setting one replaces the other.
+
+  With traffic generation on in a station mode, the probes go to the AP, which sends the
+  replies on to this device. `--replies=on` keeps the frames whose receiver is this device
+  and whose transmitter is the AP, as read from the MAC header, which is not encrypted.
+  A station does not see the header unless another stage taps it, so then the frames
+  whose transmitter is the AP pass, beacons included. Nothing passes before the station
+  is associated. Records are not tagged with the sequence number of the probe they
+  answer, as the collector sends the probes and reports neither.
+
+  Frame types, the duplicate check and the BSS read the MAC header, which takes over the
+  promiscuous receive callback of the driver until WiFi is set up again. Each frame is
+  held for up to 2 ms until its header arrives.
+
+  `status` shows how many frames each stage matched in the current or last collection. Use
+  `--off` to clear the RSSI bounds, rate limits, PHY selection, duplicate check, BSS and
+  replies, or `--replies=off` to only stop keeping replies.
+  Arguments left out keep their previous values. Changes apply right away."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: schedule_csi_collect,
+                parameters: &[
+                    Parameter::Optional {
+                        parameter_name: arg::ACTION,
+                        help: Some("show or clear"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::START_IN,
+                        argument_name: "start-in",
+                        help: Some("Seconds Until the First Window"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::EVERY,
+                        argument_name: "every",
+                        help: Some("Seconds Between Window Starts"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::DURATION,
+                        argument_name: "duration",
+                        help: Some("Duration of Each Window"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: arg::COUNT,
+                        argument_name: "count",
+                        help: Some("Number of Windows"),
+                    },
+                ],
+            },
+            command: name::SCHEDULE,
+            help: Some("schedule - Schedule periodic CSI collection windows.
+
+Usage:
+  schedule --every=<SECONDS> --duration=<SECONDS> [OPTIONS]
+  schedule show
+  schedule clear
+
+Options:
+  --start-in=<SECONDS>         Delay before the first window (default: 0).
+  --every=<SECONDS>            Time between the starts of two windows.
+  --duration=<SECONDS>         Duration of each window, at most --every.
+  --count=<NUMBER>             Number of windows (default: unlimited).
+
+Examples:
+  schedule --start-in=60 --every=600 --duration=120 --count=6
+  schedule --every=3600 --duration=300
+  schedule show
+  schedule clear
+
+Description:
+  This command sets up repeated collection windows with idle periods between them,
+  replacing any previous schedule. Each window uses the configuration current at its
+  start and prints a window event before the started event.
+
+  `schedule show` displays the schedule and its progress, and `schedule clear` removes it.
+  A manual `start` is still possible while a schedule is set. Windows that pass entirely
+  while a manual collection is running are skipped."),
+        },
+
+        &Item {
+            item_type: ItemType::Callback {
+                function: show_config,
+                parameters: &[],
+            },
+            command: name::SHOW_CONFIG,
+            help: Some("show-config - Display the current configuration settings.
+
+Usage:
+  show-config
+
+Examples:
+  show-config
+
+Description:
+  Use this command to display the current configuration for all parameters, including:
+  - Traffic settings (enabled/disabled, type, interval).
+  - Network architecture (star, mesh, or none).
+  - CSI feature flags (enabled/disabled for LLTF, HTLTF, STBC HTLTF, LTF Merge).
+  - WiFi settings (mode, maximum connections, SSID visibility).
+
+  The output provides a summary of all settings, allowing you to review and verify configurations
+  before starting the CSI collection process."),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: reset_config,
+                parameters: &[],
+            },
+            command: name::RESET_CONFIG,
+            help: Some("reset-config - Reset all configurations to their default values.
+
+Usage:
+  reset-config
+
+Examples:
+  reset-config
+
+Description:
+  This command resets all configurations to their default values:
+  - Traffic settings: Disabled, type set to ICMP, interval set to 100ms.
+  - Network architecture: Sniffer.
+  - CSI feature flags: All enabled (LLTF, HTLTF, STBC HTLTF, LTF Merge).
+  - WiFi settings: Mode set to Sniffer, maximum AP connections set to 1, SSID visible,
+    channel left to the driver, country set to CN.
+
+  Use this command if you want to start fresh with the default configuration."),
+        },
+
+    ],
+    entry: Some(enter_root),
+    exit: None,
+};
+
+fn enter_root(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    interface: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    // Headless boards stay quiet until a user breaks into the CLI
+    if HEADLESS_ACTIVE.lock(|active| active.get()) {
+        return;
+    }
+    writeln!(
+        interface,
+        "
+    Welcome to the CSI Collection CLI utility!"
+    )
+    .unwrap();
+    writeln!(interface, "").unwrap();
+    writeln!(
+        interface,
+        "Available Commands:
+    set-traffic         Configure traffic-related parameters (e.g., type, interval).
+    set-network         Configure network architecture settings.
+    set-csi             Configure CSI feature flags (e.g., LLTF, HTLTF).
+    set-wifi            Configure WiFi settings (e.g., mode, SSID visibility).
+    set-output          Configure how CSI records are printed (text or csv).
+    start               Start the CSI collection process with a defined duration.
+    stop                Stop an ongoing CSI collection process.
+    pause               Pause CSI output of an ongoing collection.
+    resume              Resume a paused collection.
+    status              Display what the collector is doing.
+    set-trigger         Arm triggered capture for the next collection.
+    trigger             Fire the trigger of the ongoing collection.
+    set-gpio            Configure the GPIO start/stop and event inputs.
+    mark                Insert an annotation into the stream.
+    label               Set the label of the records that follow.
+    set-headless        Configure headless operation, stored in flash.
+    log                 Read back or clear the flash log.
+    set-filter          Filter frames before output.
+    schedule            Schedule periodic collection windows.
+    show-config         Display the current configuration settings.
+    reset-config        Reset all configurations to their default values.
+    help                Display this help menu or details for a specific command.
+
+    For more information on a specific command, type:
+    help <command>
+
+    Example:
+    help set-traffic"
+    )
+    .unwrap();
+}
+
+// When you are okay with using a nightly compiler it's better to use https://docs.rs/static_cell/2.1.0/static_cell/macro.make_static.html
+macro_rules! mk_static {
+    ($t:ty,$val:expr) => {{
+        static STATIC_CELL: static_cell::StaticCell<$t> = static_cell::StaticCell::new();
+        #[deny(unused_attributes)]
+        let x = STATIC_CELL.uninit().write(($val));
+        x
+    }};
+}
+
+#[esp_hal_embassy::main]
+async fn main(spawner: Spawner) {
+    // Initalize ESP device and acquire peripherals
+    let config = esp_hal::Config::default().with_cpu_clock(esp_hal::clock::CpuClock::max());
+    let peripherals = esp_hal::init(config);
+
+    // Allocate heap space
+    esp_alloc::heap_allocator!(72 * 1024);
+
+    // Initalize Embassy
+    let timg1 = TimerGroup::new(peripherals.TIMG1);
+    esp_hal_embassy::init(timg1.timer0);
+
+    // Instantiate peripherals for EspWifiController
+    let timer = esp_hal::timer::timg::TimerGroup::new(peripherals.TIMG0);
+    let mut rng = esp_hal::rng::Rng::new(peripherals.RNG);
+    let seed = rng.random();
+
+    // Initialize ESP WiFi Controller
+    let init = &*mk_static!(
+        EspWifiController<'static>,
+        init(timer.timer0, rng, peripherals.RADIO_CLK).unwrap()
+    );
+
+    // Create an instance for the CSI Collector
+    let csi_config = CSICollector::new_with_defaults();
+
+    // Pass Collector Instance to Global Context
+    CSI_COLLECTOR.lock(|config| {
+        config.replace(Some(csi_config));
+    });
+    FILTER.lock(|filter| filter.replace(Some(Filter::for_chip(CHIP))));
+    HOP.lock(|hop| hop.replace(Some(HopConfig::default())));
+
+    // Spawn the CSI Collection Task
+    spawner
+        .spawn(csi_collector(peripherals.WIFI, init, seed as u64, spawner))
+        .unwrap();
+
+    // Spawn the task watching the GPIO inputs
+    spawner.spawn(gpio_inputs()).unwrap();
+
+    // Spawn the task hopping channels while sniffing
+    spawner.spawn(channel_hop()).unwrap();
+
+    // Spawn the task looking up the traffic target
+    spawner.spawn(traffic_target()).unwrap();
+
+    // Spawn the task writing the flash log
+    spawner.spawn(flash_log()).unwrap();
+
+    // Instantiate Serial Interface for CLI host communication
+    #[cfg(not(feature = "esp32"))]
+    let serial = UsbSerialJtag::new(peripherals.USB_DEVICE).into_async();
+
+    #[cfg(feature = "esp32")]
+    let serial = {
+        let (tx_pin, rx_pin) = (peripherals.GPIO1, peripherals.GPIO3);
+        let config = Config::default();
+        Uart::new(peripherals.UART0, config)
+            .unwrap()
+            .with_tx(tx_pin)
+            .with_rx(rx_pin)
+            .into_async()
+    };
+
+    // Create a buffer to store CLI input
+    let mut clibuf = [0u8; MAX_LINE_LEN];
+    // Instantiate Context placeholder
+    let mut context = Context::default();
+    // Instantiate CLI runner with root menu, buffer, and serial
+    let mut runner = Runner::new(ROOT_MENU, &mut clibuf, serial, &mut context);
+    // Watches for a user connecting to a headless board
+    let mut break_in = BreakIn::new();
+
+    // Headless boards set the collector up as it was stored, then start collecting right away
+    let headless = load_headless();
+    HEADLESS.lock(|config| config.set(headless));
+    if headless.enabled {
+        for line in load_settings().lines() {
+            for byte in line.bytes().chain([b'\r']) {
+                runner.input_byte(byte, &mut context);
+            }
+        }
+        HEADLESS_ACTIVE.lock(|active| active.set(true));
+        set_state(State::Starting);
+        // Run for one week if no duration is stored, like `start`
+        START_SIGNAL.signal((headless.duration_s.unwrap_or(604800), None));
+    }
+
+    loop {
+        // Create single element buffer for serial characters
+        let mut buf = [0_u8; 1];
+        embedded_io_async::Read::read(&mut runner.interface, &mut buf)
+            .await
+            .unwrap();
+        if HEADLESS_ACTIVE.lock(|active| active.get()) {
+            if break_in.push(buf[0]) {
+                leave_headless();
+                enter_root(&ROOT_MENU, &mut runner.interface, &mut context);
+            }
+            continue;
+        }
+        // Pass read byte to CLI runner for processing
+        runner.input_byte(buf[0], &mut context);
+    }
+}
+
+// Hands the board over to the CLI, stopping the collection started at boot
+fn leave_headless() {
+    HEADLESS_ACTIVE.lock(|active| active.set(false));
+    if STATE.lock(|state| state.get().accepts(Request::Stop).is_ok()) {
+        STOP_SIGNAL.signal(());
+    }
+    println!("Headless Mode Left");
+}
+
+// Reads the stored headless settings, the defaults if none are stored
+fn load_headless() -> HeadlessConfig {
+    let mut bytes = [0u8; CONFIG_LEN];
+    match FlashStorage::new().read(CONFIG_OFFSET, &mut bytes) {
+        Ok(_) => HeadlessConfig::from_bytes(&bytes).unwrap_or_default(),
+        Err(_) => HeadlessConfig::default(),
+    }
+}
+
+// Reads the stored collector settings, none if none are stored
+fn load_settings() -> StoredSettings {
+    let mut bytes = [0u8; SETTINGS_LEN];
+    match FlashStorage::new().read(SETTINGS_OFFSET, &mut bytes) {
+        Ok(_) => StoredSettings::from_bytes(&bytes).unwrap_or_default(),
+        Err(_) => StoredSettings::default(),
+    }
+}
+
+fn store_headless(config: &HeadlessConfig, settings: &StoredSettings) -> bool {
+    let mut flash = FlashStorage::new();
+    flash
+        .erase(CONFIG_OFFSET, CONFIG_OFFSET + SECTOR_SIZE)
+        .and_then(|_| flash.write(CONFIG_OFFSET, &config.to_bytes()))
+        .and_then(|_| flash.write(SETTINGS_OFFSET, &settings.to_bytes()))
+        .is_ok()
+}
+
+// The commands setting the collector up as it is now on a freshly booted board
+fn collector_settings() -> Result<StoredSettings, SettingsError> {
+    let mut settings = StoredSettings::new();
+    CSI_COLLECTOR.lock(|config| {
+        let config = config.borrow();
+        let config = config.as_ref().unwrap();
+        settings.push(&Command::SetTraffic(SetTraffic {
+            enable: config.traffic_enabled,
+            kind: Some(traffic_kind(&config.traffic_config.traffic_type)),
+            interval_ms: Some(config.traffic_config.traffic_interval_ms),
+        }))?;
+        settings.push(&Command::SetNetwork {
+            arch: Some(net_arch(&config.net_arch)),
+        })?;
+        let csi = &config.csi_config;
+        let mut set_csi = SetCsi::default();
+        #[cfg(not(feature = "esp32c6"))]
+        let disabled = [
+            (CsiFlag::DisableLltf, !csi.lltf_enabled),
+            (CsiFlag::DisableHtltf, !csi.htltf_enabled),
+            (CsiFlag::DisableStbcHtltf, !csi.stbc_htltf2_enabled),
+            (CsiFlag::DisableLtfMerge, !csi.ltf_merge_enabled),
+        ];
+        #[cfg(feature = "esp32c6")]
+        let disabled = [
+            (CsiFlag::DisableCsi, csi.enable == 0),
+            (CsiFlag::DisableCsiLegacy, csi.acquire_csi_legacy == 0),
+            (CsiFlag::DisableCsiHt20, csi.acquire_csi_ht20 == 0),
+            (CsiFlag::DisableCsiHt40, csi.acquire_csi_ht40 == 0),
+            (CsiFlag::DisableCsiSu, csi.acquire_csi_su == 0),
+            (CsiFlag::DisableCsiMu, csi.acquire_csi_mu == 0),
+            (CsiFlag::DisableCsiDcm, csi.acquire_csi_dcm == 0),
+            (
+                CsiFlag::DisableCsiBeamformed,
+                csi.acquire_csi_beamformed == 0,
+            ),
+        ];
+        for (flag, _) in disabled.iter().filter(|(_, disabled)| *disabled) {
+            let _ = set_csi.disable.push(*flag);
+        }
+        #[cfg(feature = "esp32c6")]
+        {
+            set_csi.he_stbc = Some(csi.acquire_csi_he_stbc);
+            set_csi.val_scale_cfg = Some(csi.val_scale_cfg);
+        }
+        settings.push(&Command::SetCsi(set_csi))?;
+        // The mode goes first, as the other WiFi settings are checked against it
+        let wifi = &config.wifi_config;
+        settings.push(&Command::SetWifi(SetWifi {
+            mode: Some(wifi_mode(&config.op_mode)),
+            sta_ssid: stored_credential(&wifi.ssid),
+            sta_password: stored_credential(&wifi.password),
+            ..Default::default()
+        }))?;
+        settings.push(&Command::SetWifi(SetWifi {
+            max_connections: Some(wifi.max_connections),
+            hide_ssid: wifi.ssid_hidden,
+            ap_ssid: stored_credential(&wifi.ap_ssid),
+            ap_password: stored_credential(&wifi.ap_password),
+            ..Default::default()
+        }))
+    })?;
+    let channel = CHANNEL_CONFIG.lock(|config| config.get());
+    let hop = HOP.lock(|hop| hop.borrow().clone().unwrap_or_default());
+    settings.push(&Command::SetWifi(SetWifi {
+        country: Some(COUNTRY.lock(|country| country.get())),
+        channel: channel.map(|config| config.channel),
+        secondary: channel.map(|config| config.secondary),
+        hop: Some(hop.channels),
+        dwell_ms: Some(hop.dwell_ms),
+        ..Default::default()
+    }))?;
+    settings.push(&Command::SetOutput {
+        format: Some(OUTPUT_FORMAT.lock(|format| format.get())),
+    })?;
+    if let Some(config) = TRIGGER_CONFIG.lock(|config| config.get()) {
+        settings.push(&Command::SetTrigger(SetTrigger::from(&config)))?;
+    }
+    if let Some(config) = GPIO_CONFIG.lock(|config| config.get()) {
+        settings.push(&Command::SetGpio(SetGpio::from(&config)))?;
+    }
+    let filter = FILTER.lock(|filter| SetFilter::from(filter.borrow().as_ref().unwrap().config()));
+    settings.push(&Command::SetFilter(filter))?;
+    Ok(settings)
+}
+
+// An SSID or password as set-wifi takes it, none if empty
+fn stored_credential<const N: usize>(value: &str) -> Option<heapless::String<N>> {
+    heapless::String::try_from(value)
+        .ok()
+        .filter(|value: &heapless::String<N>| !value.is_empty())
+}
+
+// Prints a record or event, or queues it for the flash log while running headless
+fn output(line: core::fmt::Arguments) {
+    let to_flash = HEADLESS_ACTIVE.lock(|active| active.get())
+        && HEADLESS.lock(|config| config.get().sink) == Sink::Flash;
+    if !to_flash {
+        println!("{}", line);
+        return;
+    }
+    // Lines are queued whole or not at all, so the log never holds part of a line
+    let mut len = LineLen(0);
+    let _ = len.write_fmt(line);
+    let queued = LOG_LOCK.lock(|_| {
+        if LOG_PIPE.free_capacity() <= len.0 {
+            return false;
+        }
+        let _ = LogWriter.write_fmt(line);
+        let _ = LogWriter.write_str("\n");
+        true
+    });
+    if !queued {
+        LOG_DROPPED.lock(|dropped| dropped.set(dropped.get() + 1));
+    }
+}
+
+// Counts the bytes of a formatted line
+struct LineLen(usize);
+
+impl Write for LineLen {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+// Writes into the log pipe, which was checked to have room for the whole line
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            match LOG_PIPE.try_write(bytes) {
+                Ok(written) => bytes = &bytes[written..],
+                Err(_) => return Err(core::fmt::Error),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[embassy_executor::task]
+async fn flash_log() {
+    let mut flash = FlashStorage::new();
+    let mut page = [0u8; LOG_PAGE_SIZE];
+    let mut filled = 0;
+
+    loop {
+        // Full pages are written right away, the rest once the pipe stays empty for a second
+        match select(
+            LOG_PIPE.read(&mut page[filled..]),
+            Timer::after(Duration::from_secs(1)),
+        )
+        .await
+        {
+            Either::First(read) => {
+                filled += read;
+                if filled < page.len() {
+                    continue;
+                }
+            }
+            Either::Second(_) if filled == 0 => continue,
+            Either::Second(_) => {
+                // Writes are whole words, padded with line feeds the decoder skips
+                while filled % 4 != 0 {
+                    page[filled] = b'\n';
+                    filled += 1;
+                }
+            }
+        }
+        if !append_log(&mut flash, &page[..filled]) {
+            LOG_DROPPED.lock(|dropped| dropped.set(dropped.get() + 1));
+        }
+        filled = 0;
+    }
+}
+
+// Appends to the flash log. The sector after the one being written is kept erased,
+// so the log always ends on erased flash, which readers stop at.
+fn append_log(flash: &mut FlashStorage, bytes: &[u8]) -> bool {
+    let end = LOG_END
+        .lock(|end| end.get())
+        .unwrap_or_else(|| find_log_end(flash));
+    let new_end = end + bytes.len() as u32;
+    if new_end > LOG_SIZE || flash.write(LOG_OFFSET + end, bytes).is_err() {
+        LOG_END.lock(|e| e.set(Some(end)));
+        return false;
+    }
+    if new_end / SECTOR_SIZE != end / SECTOR_SIZE {
+        erase_log_sector(flash, new_end / SECTOR_SIZE + 1);
+    }
+    LOG_END.lock(|e| e.set(Some(new_end)));
+    true
+}
+
+// Finds the first erased byte of the flash log
+fn find_log_end(flash: &mut FlashStorage) -> u32 {
+    let mut chunk = [0u8; LOG_PAGE_SIZE];
+    let mut end = 0;
+    while end < LOG_SIZE {
+        if flash.read(LOG_OFFSET + end, &mut chunk).is_err() {
+            break;
+        }
+        if let Some(i) = chunk.iter().position(|b| *b == 0xff) {
+            end += i as u32;
+            break;
+        }
+        end += LOG_PAGE_SIZE as u32;
+    }
+    // Power may have been lost before the next sector was erased
+    erase_log_sector(flash, end / SECTOR_SIZE + 1);
+    end.min(LOG_SIZE)
+}
+
+fn erase_log_sector(flash: &mut FlashStorage, sector: u32) {
+    let from = sector * SECTOR_SIZE;
+    if from < LOG_SIZE {
+        let _ = flash.erase(LOG_OFFSET + from, LOG_OFFSET + from + SECTOR_SIZE);
+    }
+}
+
+#[embassy_executor::task]
+async fn csi_collector(
+    wifi: peripherals::WIFI,
+    wifi_hw: &'static EspWifiController<'static>,
+    seed: u64,
+    spawner: Spawner,
+) {
+    // WiFi settings the stack is currently set up with, if set up
+    let mut setup: Option<WifiSetup> = None;
+    // Peripheral for the first init, later ones take it back after deinit_wifi
+    let mut wifi = Some(wifi);
+
+    loop {
+        // Wait for a manual or scheduled start to kick off collection activity
+        let (interval, count) = next_collection().await;
+        // Reset Start Signal so starts issued during the run are discarded
+        START_SIGNAL.reset();
+
+        // Obtain copy from CSI collector in global context
+        let mut collector = configured_collector();
+
+        // Changed WiFi settings only take effect once the WiFi stack is set up again
+        let requested = WifiSetup::of(&collector);
+        if setup.as_ref() != Some(&requested) {
+            if let Some(previous) = &setup {
+                println!(
+                    "WiFi Settings Changed, Reinitializing: {} ({}) -> {} ({})",
+                    previous.mode, previous.arch, requested.mode, requested.arch
+                );
+            }
+            set_state(State::Initialising);
+            // A failed init may have set up part of the stack, so it is torn down as well
+            let wifi = match wifi.take() {
+                Some(wifi) => wifi,
+                None => deinit_wifi().await,
+            };
+            if !init_collector(&mut collector, wifi, wifi_hw, seed, spawner) {
+                setup = None;
+                LAST_ERROR.lock(|e| e.set(Some("WiFi initialization failed")));
+                set_state(State::Error);
+                continue;
+            }
+            if setup.is_some() {
+                println!("WiFi Reinitialized");
+            }
+            apply_wifi_setup(&requested);
+            setup = Some(requested);
+        }
+        let station = matches!(
+            setup.as_ref().map(|s| s.mode),
+            Some(WifiModeArg::Station | WifiModeArg::AccessPointStation)
+        );
+
+        // Discard stop requests issued while no collection was running
+        STOP_SIGNAL.reset();
+        PAUSE_TRAFFIC_SIGNAL.reset();
+        RESUME_SIGNAL.reset();
+        RECORD_SEQ.lock(|seq| seq.set(0));
+        RECORD_LIMIT.lock(|limit| limit.set(count));
+        LAST_CHANNEL.lock(|channel| channel.set(None));
+        FILTER.lock(|filter| filter.borrow_mut().as_mut().map(|f| f.reset_stats()));
+        PAIRING.lock(|pairing| pairing.borrow_mut().clear());
+        apply_promiscuous_filter();
+        if let Some(setup) = &setup {
+            apply_channel(setup.mode);
+            let hopping = HOP.lock(|hop| {
+                hop.borrow()
+                    .as_ref()
+                    .is_some_and(|h| !h.channels.is_empty())
+            });
+            if hopping && matches!(setup.mode, WifiModeArg::Sniffer) {
+                HOP_SIGNAL.signal(true);
+            }
+        }
+        TRAFFIC_SIGNAL.signal(station);
+        TRIGGER.lock(|trigger| trigger.replace(TRIGGER_CONFIG.lock(|c| c.get()).map(Trigger::new)));
+        print_event(&Event::Started {
+            duration_s: interval,
+        });
+        // Every run starts with the label still in effect
+        if LABEL.lock(|label| !label.borrow().is_empty()) {
+            print_label();
+        }
+        // Start Collection, ending early if a stop is requested or the record count is reached
+        let started = Instant::now();
+        RUN_WINDOW.lock(|run| run.set(Some((started, started + Duration::from_secs(interval)))));
+        // Stations wait for their first frame, which arrives once associated
+        set_state(match station {
+            true => State::Connecting,
+            false => State::Collecting,
+        });
+        loop {
+            // Pick up configuration changes made while traffic was paused
+            if RECONFIGURE_SIGNAL.try_take().is_some() {
+                collector = configured_collector();
+            }
+            // Rounded up, the run itself ends on time through run_end
+            let remaining = run_end_at()
+                .saturating_duration_since(Instant::now())
+                .as_millis()
+                .div_ceil(1000);
+            let given_until = Instant::now() + Duration::from_secs(remaining);
+            match select4(
+                select(collector.start(remaining), release_waiting()),
+                run_end(),
+                PAUSE_TRAFFIC_SIGNAL.wait(),
+                RECONFIGURE_SIGNAL.wait(),
+            )
+            .await
+            {
+                // A trigger moved the end of the run past the duration start was given
+                Either4::First(_)
+                    if Instant::now() >= given_until && Instant::now() < run_end_at() => {}
+                // Restarting a collector that gave up early would only spin
+                Either4::First(_) if Instant::now() < given_until => {
+                    println!("Collection Ended Early");
+                    LAST_ERROR.lock(|e| e.set(Some("Collection ended early")));
+                    break;
+                }
+                // Leaving start ends traffic generation but keeps the connection set up by init
+                Either4::Third(_) => match select(RESUME_SIGNAL.wait(), run_end()).await {
+                    Either::First(_) => {}
+                    Either::Second(_) => break,
+                },
+                // Restart with the updated configuration for the rest of the run
+                Either4::Fourth(_) => collector = configured_collector(),
+                _ => break,
+            }
+        }
+        set_state(State::Stopping);
+        HOP_SIGNAL.signal(false);
+        TRAFFIC_SIGNAL.signal(false);
+        PAUSED_AT.lock(|p| p.set(None));
+        RUN_WINDOW.lock(|run| run.set(None));
+        // Records still waiting for their header go through without it
+        while let Some((record, header)) = PAIRING.lock(|pairing| pairing.borrow_mut().drain()) {
+            check_record(record, header.as_ref());
+        }
+        // Records still held back by a trigger that never fired are dropped
+        TRIGGER.lock(|trigger| trigger.replace(None));
+        print_event(&Event::Stopped {
+            records: RECORD_SEQ.lock(|seq| seq.get()),
+            elapsed_ms: started.elapsed().as_millis(),
+        });
+        set_state(State::Idle);
+    }
+}
+
+// End of the current run, which a trigger may move
+fn run_end_at() -> Instant {
+    RUN_WINDOW
+        .lock(|run| run.get())
+        .map_or(Instant::MAX, |(_, end)| end)
+}
+
+// Completes when a stop is requested or the end of the run passes
+async fn run_end() {
+    loop {
+        match select3(
+            STOP_SIGNAL.wait(),
+            RUN_END_SIGNAL.wait(),
+            Timer::at(run_end_at()),
+        )
+        .await
+        {
+            Either3::Second(_) => {}
+            _ => return,
+        }
+    }
+}
+
+// Tells the filter which frames reply to the generated traffic. Stations send their
+// probes through the AP, which is known once associated and may change on a roam, so it
+// is looked up again every TRAFFIC_LOOKUP until the run ends.
+#[embassy_executor::task]
+async fn traffic_target() {
+    loop {
+        set_traffic_target(None);
+        if !TRAFFIC_SIGNAL.wait().await {
+            continue;
+        }
+        loop {
+            set_traffic_target(station_link());
+            // A new run keeps looking, the end of the run stops
+            if let Either::Second(false) =
+                select(Timer::after(TRAFFIC_LOOKUP), TRAFFIC_SIGNAL.wait()).await
+            {
+                break;
+            }
+        }
+    }
+}
+
+fn set_traffic_target(traffic: Option<(MacAddr, MacAddr)>) {
+    FILTER.lock(|filter| {
+        if let Some(filter) = filter.borrow_mut().as_mut() {
+            filter.set_traffic(traffic);
+        }
+    });
+}
+
+// Address of the station and of the AP it is associated with
+fn station_link() -> Option<(MacAddr, MacAddr)> {
+    let mut own = [0u8; 6];
+    let mut ap: wifi_ap_record_t = unsafe { core::mem::zeroed() };
+    unsafe {
+        if esp_wifi_get_mac(wifi_interface_t_WIFI_IF_STA, own.as_mut_ptr()) != 0
+            || esp_wifi_sta_get_ap_info(&mut ap) != 0
+        {
+            return None;
+        }
+    }
+    Some((MacAddr(own), MacAddr(ap.bssid)))
+}
+
+// Moves the collector to a new state, which must be reachable from the current one
+fn set_state(to: State) {
+    STATE.lock(|state| {
+        debug_assert!(state.get().can_become(to));
+        state.set(to)
+    });
+}
+
+// WiFi settings a collector was initialized with
+#[derive(PartialEq)]
+struct WifiSetup {
+    mode: WifiModeArg,
+    arch: NetArch,
+    // Credentials, SSID visibility and connection limit, as printed by show-config
+    wifi: heapless::String<256>,
+    // Set up with the stack, as an AP keeps its channel in its configuration
+    ap_channel: Option<ChannelConfig>,
+    country: Country,
+}
+
+impl WifiSetup {
+    fn of(collector: &CSICollector) -> Self {
+        let config = &collector.wifi_config;
+        let mut wifi = heapless::String::new();
+        write!(
+            wifi,
+            "{}|{}|{}|{}|{}|{}",
+            config.ssid,
+            config.password,
+            config.ap_ssid,
+            config.ap_password,
+            config.ssid_hidden,
+            config.max_connections
+        )
+        .unwrap();
+        let mode = wifi_mode(&collector.op_mode);
+        WifiSetup {
+            mode,
+            arch: net_arch(&collector.net_arch),
+            wifi,
+            ap_channel: match mode {
+                WifiModeArg::AccessPoint => CHANNEL_CONFIG.lock(|config| config.get()),
+                _ => None,
+            },
+            country: COUNTRY.lock(|country| country.get()),
+        }
+    }
+}
+
+// Sets up the WiFi stack for the collector, which must not be set up already
+fn init_collector(
+    collector: &mut CSICollector,
+    wifi: peripherals::WIFI,
+    wifi_hw: &'static EspWifiController<'static>,
+    seed: u64,
+    spawner: Spawner,
+) -> bool {
+    match collector.init(wifi, wifi_hw, seed, &spawner) {
+        Ok(_) => true,
+        Err(_e) => {
+            println!("Error Initializing CSI Collector");
+            false
+        }
+    }
+}
+
+// Tears down the WiFi stack set up by the previous init and hands back its peripheral.
+// The collector of the last run was dropped when the run ended. Stopping WiFi ends the
+// connection and traffic tasks init spawned, which get WIFI_TEARDOWN to exit before
+// the driver they use is deinitialized.
+async fn deinit_wifi() -> peripherals::WIFI {
+    if unsafe { esp_wifi_stop() } != 0 {
+        println!("Error Stopping WiFi");
+    }
+    Timer::after(WIFI_TEARDOWN).await;
+    if unsafe { esp_wifi_deinit() } != 0 {
+        println!("Error Deinitializing WiFi");
+    }
+    TAPPING.lock(|tapping| tapping.set(false));
+    // Nothing holds the peripheral once the driver is deinitialized
+    unsafe { peripherals::WIFI::steal() }
+}
+
+// Watches the start and event pins chosen with set-gpio
+#[embassy_executor::task]
+async fn gpio_inputs() {
+    loop {
+        let config = GPIO_CONFIG.lock(|config| config.get()).unwrap_or_default();
+        // The opposite pull keeps unconnected pins inactive
+        let pull = match config.polarity {
+            Polarity::High => Pull::Down,
+            Polarity::Low => Pull::Up,
+        };
+        // `set-gpio` only accepts pins left free by the firmware on this chip, and the
+        // inputs of the previous settings are dropped before the pins are taken again
+        let mut start = config
+            .start_pin
+            .map(|pin| Input::new(unsafe { AnyPin::steal(pin) }, pull));
+        let mut event = config
+            .event_pin
+            .map(|pin| Input::new(unsafe { AnyPin::steal(pin) }, pull));
+        let mut start_debounce = Debounce::new(config.debounce_ms);
+        let mut event_debounce = Debounce::new(config.debounce_ms);
+
+        loop {
+            match select3(
+                next_edge(start.as_mut()),
+                next_edge(event.as_mut()),
+                GPIO_SIGNAL.wait(),
+            )
+            .await
+            {
+                Either3::First((edge, at)) => {
+                    // Every edge goes through the debounce so bounces on release are dropped too
+                    if start_debounce.accept(at.as_millis())
+                        && edge == config.polarity.active_edge()
+                    {
+                        toggle_collection();
+                    }
+                }
+                Either3::Second((edge, at)) => {
+                    if event_debounce.accept(at.as_millis()) {
+                        print_event(&Event::Gpio {
+                            pin: config.event_pin.unwrap(),
+                            edge,
+                            timestamp_us: at.as_micros(),
+                        });
+                    }
+                }
+                Either3::Third(_) => break,
+            }
+        }
+    }
+}
+
+// Waits for the next edge on a pin and the time it was seen, forever if the pin is not set
+async fn next_edge(input: Option<&mut Input<'_>>) -> (Edge, Instant) {
+    let Some(input) = input else {
+        return core::future::pending().await;
+    };
+    input.wait_for_any_edge().await;
+    let at = Instant::now();
+    match input.is_high() {
+        true => (Edge::Rising, at),
+        false => (Edge::Falling, at),
+    }
+}
+
+// Starts a collection when idle and stops it when collecting, as `start` and `stop` would
+fn toggle_collection() {
+    let (start, stop) = STATE.lock(|state| {
+        let start = state.get().accepts(Request::Start).is_ok();
+        if start {
+            state.set(State::Starting);
+        }
+        (start, state.get().accepts(Request::Stop).is_ok())
+    });
+    if start {
+        // Run for one week, like `start` without a duration
+        START_SIGNAL.signal((Duration::from_secs(604800).as_secs(), None));
+    } else if stop {
+        STOP_SIGNAL.signal(());
+    }
+}
+
+// Switches a hopping sniffer to the next channel every dwell time, from the start of a
+// run until it ends
+#[embassy_executor::task]
+async fn channel_hop() {
+    loop {
+        if !HOP_SIGNAL.wait().await {
+            continue;
+        }
+        let hop = HOP.lock(|hop| hop.borrow().clone().unwrap_or_default());
+        let mut channels = hop.channels.0.iter().copied().cycle();
+        loop {
+            let Some(channel) = channels.next() else {
+                break;
+            };
+            let result =
+                unsafe { esp_wifi_set_channel(channel, wifi_second_chan_t_WIFI_SECOND_CHAN_NONE) };
+            if result != 0 {
+                println!("Failed to Set Channel {}", channel);
+            }
+            channel_changed(channel);
+            print_event(&Event::Hop {
+                channel,
+                timestamp_us: Instant::now().as_micros(),
+            });
+            // Both the end of the run and a new run end this cycle
+            match select(
+                Timer::after(Duration::from_millis(hop.dwell_ms)),
+                HOP_SIGNAL.wait(),
+            )
+            .await
+            {
+                Either::First(_) => {}
+                Either::Second(true) => {
+                    HOP_SIGNAL.signal(true);
+                    break;
+                }
+                Either::Second(false) => break,
+            }
+        }
+    }
+}
+
+// Waits for a manual start or the next scheduled window and returns the
+// collection duration in seconds and the record count to stop at, if any
+async fn next_collection() -> (u64, Option<u32>) {
+    loop {
+        let now_ms = Instant::now().as_millis();
+        let step = SCHEDULER.lock(|s| s.borrow_mut().as_mut().map(|s| s.next(now_ms)));
+        match step {
+            Some(Step::Collect { window, until_ms }) => {
+                print_event(&Event::Window { index: window });
+                // Round up so the window is not cut short
+                return ((until_ms - now_ms).div_ceil(1000), None);
+            }
+            Some(Step::Wait { until_ms }) => {
+                match select3(
+                    START_SIGNAL.wait(),
+                    SCHEDULE_SIGNAL.wait(),
+                    Timer::at(Instant::from_millis(until_ms)),
+                )
+                .await
+                {
+                    Either3::First(run) => return run,
+                    Either3::Second(_) | Either3::Third(_) => {}
+                }
+            }
+            Some(Step::Finished) | None => {
+                match select(START_SIGNAL.wait(), SCHEDULE_SIGNAL.wait()).await {
+                    Either::First(run) => return run,
+                    Either::Second(_) => {}
+                }
+            }
+        }
+    }
+}
+
+// Obtain copy from CSI collector in global context with the record printer attached
+fn configured_collector() -> CSICollector {
+    let mut collector =
+        CSI_COLLECTOR.lock(|collector| (collector.borrow().as_ref().unwrap().clone()));
+    collector.set_csi_callback(print_csi_record);
+    collector
+}
+
+// Called by the collector for every CSI frame received
+fn print_csi_record(packet: &CSIDataPacket) {
+    // Frames are only emitted during a run, so those received while paused, while WiFi
+    // is set up or torn down, or outside a run are dropped without using up sequence
+    // numbers. The first frame of a connecting station shows it is associated.
+    let collecting = STATE.lock(|state| match state.get() {
+        State::Collecting => true,
+        State::Connecting => {
+            state.set(State::Collecting);
+            true
+        }
+        _ => false,
+    });
+    if !collecting {
+        return;
+    }
+    // The sequence number is assigned once the record is emitted
+    let record = csi_record(packet, 0);
+    if !TAPPING.lock(|tapping| tapping.get()) {
+        check_record(record, None);
+        return;
+    }
+    let timestamp = record.timestamp;
+    if let Some((record, header)) = PAIRING.lock(|pairing| {
+        pairing
+            .borrow_mut()
+            .push_record(timestamp, pairing_now(), record)
+    }) {
+        check_record(record, header.as_ref());
+    }
+    release_paired();
+    PAIRING_SIGNAL.signal(());
+}
+
+// Clock the wait of a record for its header is timed by
+fn pairing_now() -> u32 {
+    Instant::now().as_micros() as u32
+}
+
+// Releases the records whose header arrived or that waited long enough for it
+fn release_paired() {
+    while let Some((record, header)) =
+        PAIRING.lock(|pairing| pairing.borrow_mut().pop_ready(pairing_now()))
+    {
+        check_record(record, header.as_ref());
+    }
+}
+
+// Releases records whose header never comes once they waited for it, rather than when the
+// next frame arrives, which may be long after on a quiet channel. Runs alongside the
+// collector and never returns.
+async fn release_waiting() {
+    loop {
+        match PAIRING.lock(|pairing| pairing.borrow().next_deadline()) {
+            Some(deadline) => {
+                // Past deadlines wrap to a negative wait
+                let wait = deadline.wrapping_sub(pairing_now()) as i32;
+                if wait > 0 {
+                    Timer::after(Duration::from_micros(wait as u64)).await;
+                }
+            }
+            None => PAIRING_SIGNAL.wait().await,
+        }
+        release_paired();
+    }
+}
+
+// Runs a record through the filter and the trigger, emitting it if it is let through
+fn check_record(record: CsiRecord, header: Option<&FrameHeader>) {
+    let passed = FILTER.lock(|filter| {
+        filter
+            .borrow_mut()
+            .as_mut()
+            .map_or(true, |filter| filter.check(&record, header))
+    });
+    if !passed {
+        return;
+    }
+    let feed = TRIGGER.lock(|trigger| match trigger.borrow_mut().as_mut() {
+        Some(trigger) => trigger.push(record),
+        None => Feed::Pass(record),
+    });
+    match feed {
+        Feed::Buffered => {}
+        Feed::Fired(cause) => on_trigger(cause),
+        Feed::Pass(record) => emit_record(record),
+    }
+}
+
+// Keeps the MAC header of every frame the promiscuous filter lets through, as the CSI
+// callback does not carry it
+unsafe extern "C" fn tap_header(buf: *mut core::ffi::c_void, _kind: wifi_promiscuous_pkt_type_t) {
+    let packet = &*(buf as *const wifi_promiscuous_pkt_t);
+    let len = (packet.rx_ctrl.sig_len() as usize).min(BEACON_LEN);
+    let frame = core::slice::from_raw_parts(packet.payload.as_ptr(), len);
+    let Some(header) = FrameHeader::parse(frame) else {
+        return;
+    };
+    // Runs in the driver, where a panic would take the WiFi stack down
+    FILTER.lock(|filter| {
+        if let Some(filter) = filter.borrow_mut().as_mut() {
+            filter.watch_ssid(frame);
+        }
+    });
+    let timestamp = packet.rx_ctrl.timestamp();
+    PAIRING.lock(|pairing| pairing.borrow_mut().push_header(timestamp, header));
+    release_paired();
+}
+
+// Narrows the promiscuous filter of the driver to the frame types kept, subtypes are
+// checked on every frame. Beacons are let through while an SSID is looked for. Has no
+// effect until WiFi is set up, so every run applies it again.
+//
+// The driver holds a single promiscuous callback and offers no way to read the one
+// registered by the collector back, so it can be neither chained nor restored. Headers
+// are only tapped once a filter stage needs them, until WiFi is set up again, and the
+// mask the collector set is left alone until then.
+fn apply_promiscuous_filter() {
+    let Some((mask, needs_header)) = FILTER.lock(|filter| {
+        let filter = filter.borrow();
+        let config = filter.as_ref()?.config();
+        Some((config.promiscuous_mask(), config.needs_header()))
+    }) else {
+        return;
+    };
+    let tapping = TAPPING.lock(|tapping| tapping.get());
+    if !needs_header && !tapping {
+        return;
+    }
+    unsafe {
+        if !tapping {
+            esp_wifi_set_promiscuous_rx_cb(Some(tap_header));
+        }
+        esp_wifi_set_promiscuous_filter(&wifi_promiscuous_filter_t { filter_mask: mask });
+    }
+    TAPPING.lock(|tapping| tapping.set(true));
+}
+
+// Releases the pre-trigger records and ends the run after the post-trigger duration
+fn on_trigger(cause: Cause) {
+    let (buffered, post_s) = TRIGGER.lock(|trigger| {
+        let trigger = trigger.borrow();
+        let trigger = trigger.as_ref().unwrap();
+        (trigger.buffered() as u32, trigger.config().post_s)
+    });
+    print_event(&Event::Triggered { cause, buffered });
+    RUN_WINDOW.lock(|run| {
+        if let Some((started, _)) = run.get() {
+            run.set(Some((
+                started,
+                Instant::now() + Duration::from_secs(post_s),
+            )));
+        }
+    });
+    RUN_END_SIGNAL.signal(());
+    // Records are taken one at a time so the lock is never held while printing
+    while let Some(record) =
+        TRIGGER.lock(|trigger| trigger.borrow_mut().as_mut().and_then(|t| t.pop()))
+    {
+        emit_record(record);
+    }
+}
+
+// Numbers and prints a record, stopping the run once the record count is reached
+fn emit_record(mut record: CsiRecord) {
+    let limit = RECORD_LIMIT.lock(|limit| limit.get());
+    let seq = RECORD_SEQ.lock(|seq| {
+        let current = seq.get();
+        // Frames arriving after the count is reached are dropped until the run stops
+        if limit.is_some_and(|limit| current >= limit) {
+            return None;
+        }
+        seq.set(current.wrapping_add(1));
+        Some(current)
+    });
+    let Some(seq) = seq else { return };
+    if limit == Some(seq + 1) {
+        STOP_SIGNAL.signal(());
+    }
+    LAST_CHANNEL.lock(|channel| channel.set(Some(record.channel)));
+    record.seq = seq;
+    let format = OUTPUT_FORMAT.lock(|format| format.get());
+    output(format_args!("{}", record.display(format)));
+}
+
+fn print_event(event: &Event) {
+    let format = OUTPUT_FORMAT.lock(|format| format.get());
+    output(format_args!("{}", event.display(format)));
+}
+
+fn csi_record(packet: &CSIDataPacket, seq: u32) -> CsiRecord {
+    let mut csi = heapless::Vec::new();
+    csi.extend(packet.csi_data.iter().copied().take(MAX_CSI_LEN));
+    CsiRecord {
+        seq,
+        mac: MacAddr(packet.mac),
+        rssi: packet.rssi as i8,
+        rate: packet.rate as u8,
+        sig_mode: packet.sig_mode as u8,
+        mcs: packet.mcs as u8,
+        bandwidth: packet.bandwidth as u8,
+        smoothing: packet.smoothing as u8,
+        not_sounding: packet.not_sounding as u8,
+        aggregation: packet.aggregation as u8,
+        stbc: packet.stbc as u8,
+        fec_coding: packet.fec_coding as u8,
+        sgi: packet.sgi as u8,
+        noise_floor: packet.noise_floor as i8,
+        ampdu_cnt: packet.ampdu_cnt as u8,
+        channel: packet.channel as u8,
+        secondary_channel: packet.secondary_channel as u8,
+        timestamp: packet.timestamp,
+        ant: packet.antenna as u8,
+        sig_len: packet.sig_len as u16,
+        rx_state: packet.rx_state as u8,
+        csi,
+    }
+}
 
 fn set_traffic<'a>(
     _menu: &Menu<SerialInterfaceType, Context>,
@@ -559,21 +2344,21 @@ fn set_traffic<'a>(
     serial: &mut SerialInterfaceType,
     _context: &mut Context,
 ) {
-    let traffic_en = argument_finder(item, args, "enable");
-    let traffic_type = argument_finder(item, args, "type");
-    let traffic_interval = argument_finder(item, args, "interval");
+    let traffic_en = argument_finder(item, args, arg::ENABLE);
+    let traffic_type = argument_finder(item, args, arg::TYPE);
+    let traffic_interval = argument_finder(item, args, arg::INTERVAL_MS);
 
     match traffic_en {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
             config.borrow_mut().as_mut().unwrap().traffic_enabled = true;
         }),
-        Err(_) => (),
+        _ => (),
     }
     match traffic_type {
         Ok(str) => {
             if str.is_some() {
-                match str.unwrap() {
-                    "icmp" => CSI_COLLECTOR.lock(|config| {
+                match str.unwrap().parse::<TrafficKind>() {
+                    Ok(TrafficKind::Icmp) => CSI_COLLECTOR.lock(|config| {
                         config
                             .borrow_mut()
                             .as_mut()
@@ -581,7 +2366,7 @@ fn set_traffic<'a>(
                             .traffic_config
                             .traffic_type = TrafficType::ICMPPing;
                     }),
-                    "udp" => CSI_COLLECTOR.lock(|config| {
+                    Ok(TrafficKind::Udp) => CSI_COLLECTOR.lock(|config| {
                         config
                             .borrow_mut()
                             .as_mut()
@@ -589,7 +2374,7 @@ fn set_traffic<'a>(
                             .traffic_config
                             .traffic_type = TrafficType::UDP;
                     }),
-                    _ => writeln!(serial, "Invalid Traffic Type").unwrap(),
+                    Err(_) => writeln!(serial, "Invalid Traffic Type").unwrap(),
                 }
             }
         }
@@ -646,6 +2431,29 @@ fn set_traffic<'a>(
         )
         .unwrap();
     });
+    apply_live(serial);
+}
+
+// Restarts a running collection with the updated configuration
+fn apply_live(serial: &mut SerialInterfaceType) {
+    if STATE.lock(|state| state.get().is_active()) {
+        RECONFIGURE_SIGNAL.signal(());
+        writeln!(serial, "Applied to the Running Collection").unwrap();
+    }
+}
+
+// Settings used by init can only change between collections
+fn rejected_while_collecting(serial: &mut SerialInterfaceType, setting: &str) -> bool {
+    let collecting = STATE.lock(|state| state.get().is_active());
+    if collecting {
+        writeln!(serial, "Cannot Change {} While Collecting", setting).unwrap();
+        writeln!(
+            serial,
+            "These Settings Are Applied When WiFi Is Set Up, Stop the Collection First"
+        )
+        .unwrap();
+    }
+    collecting
 }
 
 fn set_network<'a>(
@@ -655,29 +2463,32 @@ fn set_network<'a>(
     serial: &mut SerialInterfaceType,
     _context: &mut Context,
 ) {
-    let arch = argument_finder(item, args, "arch");
+    if rejected_while_collecting(serial, "Network Architecture") {
+        return;
+    }
+    let arch = argument_finder(item, args, arg::ARCH);
 
     match arch {
         Ok(str) => {
             if str.is_some() {
-                match str.unwrap() {
-                    "rsta" => CSI_COLLECTOR.lock(|config| {
+                match str.unwrap().parse::<NetArch>() {
+                    Ok(NetArch::RouterStation) => CSI_COLLECTOR.lock(|config| {
                         config.borrow_mut().as_mut().unwrap().net_arch =
                             NetworkArchitechture::RouterStation;
                     }),
-                    "rapsta" => CSI_COLLECTOR.lock(|config| {
+                    Ok(NetArch::RouterAccessPointStation) => CSI_COLLECTOR.lock(|config| {
                         config.borrow_mut().as_mut().unwrap().net_arch =
                             NetworkArchitechture::RouterAccessPointStation;
                     }),
-                    "apsta" => CSI_COLLECTOR.lock(|config| {
+                    Ok(NetArch::AccessPointStation) => CSI_COLLECTOR.lock(|config| {
                         config.borrow_mut().as_mut().unwrap().net_arch =
                             NetworkArchitechture::AccessPointStation;
                     }),
-                    "sniff" => CSI_COLLECTOR.lock(|config| {
+                    Ok(NetArch::Sniffer) => CSI_COLLECTOR.lock(|config| {
                         config.borrow_mut().as_mut().unwrap().net_arch =
                             NetworkArchitechture::Sniffer;
                     }),
-                    _ => writeln!(serial, "Invalid Network Type").unwrap(),
+                    Err(_) => writeln!(serial, "Invalid Network Type").unwrap(),
                 }
             }
         }
@@ -702,25 +2513,25 @@ fn set_csi<'a>(
     serial: &mut SerialInterfaceType,
     _context: &mut Context,
 ) {
-    let disable_csi = argument_finder(item, args, "disable-csi");
-    let disable_csi_legacy = argument_finder(item, args, "disable-csi-legacy");
-    let disable_csi_ht20 = argument_finder(item, args, "disable-csi-ht20");
-    let disable_csi_ht40 = argument_finder(item, args, "disable-csi-ht40");
-    let disable_csi_su = argument_finder(item, args, "disable-csi-su");
-    let disable_csi_mu = argument_finder(item, args, "disable-csi-mu");
-    let disable_csi_dcm = argument_finder(item, args, "disable-csi-dcm");
-    let disable_csi_beamformed = argument_finder(item, args, "disable-csi-beamformed");
-    let csi_he_stbc = argument_finder(item, args, "csi-he-stbc");
-    let val_scale_cfg = argument_finder(item, args, "val-scale-cfg");
+    let disable_csi = argument_finder(item, args, arg::DISABLE_CSI);
+    let disable_csi_legacy = argument_finder(item, args, arg::DISABLE_CSI_LEGACY);
+    let disable_csi_ht20 = argument_finder(item, args, arg::DISABLE_CSI_HT20);
+    let disable_csi_ht40 = argument_finder(item, args, arg::DISABLE_CSI_HT40);
+    let disable_csi_su = argument_finder(item, args, arg::DISABLE_CSI_SU);
+    let disable_csi_mu = argument_finder(item, args, arg::DISABLE_CSI_MU);
+    let disable_csi_dcm = argument_finder(item, args, arg::DISABLE_CSI_DCM);
+    let disable_csi_beamformed = argument_finder(item, args, arg::DISABLE_CSI_BEAMFORMED);
+    let csi_he_stbc = argument_finder(item, args, arg::CSI_HE_STBC);
+    let val_scale_cfg = argument_finder(item, args, arg::VAL_SCALE_CFG);
 
     match disable_csi {
-        Ok(_str) => {
+        Ok(Some(_)) => {
             CSI_COLLECTOR.lock(|config| config.borrow_mut().as_mut().unwrap().csi_config.enable = 0)
         }
-        Err(_) => (),
+        _ => (),
     }
     match disable_csi_legacy {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
             config
                 .borrow_mut()
                 .as_mut()
@@ -728,10 +2539,10 @@ fn set_csi<'a>(
                 .csi_config
                 .acquire_csi_legacy = 0;
         }),
-        Err(_) => (),
+        _ => (),
     }
     match disable_csi_ht20 {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
             config
                 .borrow_mut()
                 .as_mut()
@@ -739,10 +2550,10 @@ fn set_csi<'a>(
                 .csi_config
                 .acquire_csi_ht20 = 0;
         }),
-        Err(_) => (),
+        _ => (),
     }
     match disable_csi_ht40 {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
             config
                 .borrow_mut()
                 .as_mut()
@@ -750,10 +2561,10 @@ fn set_csi<'a>(
                 .csi_config
                 .acquire_csi_ht40 = 0;
         }),
-        Err(_) => (),
+        _ => (),
     }
     match disable_csi_su {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
             config
                 .borrow_mut()
                 .as_mut()
@@ -761,10 +2572,10 @@ fn set_csi<'a>(
                 .csi_config
                 .acquire_csi_su = 0;
         }),
-        Err(_) => (),
+        _ => (),
     }
     match disable_csi_mu {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
             config
                 .borrow_mut()
                 .as_mut()
@@ -772,10 +2583,10 @@ fn set_csi<'a>(
                 .csi_config
                 .acquire_csi_mu = 0;
         }),
-        Err(_) => (),
+        _ => (),
     }
     match disable_csi_dcm {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
             config
                 .borrow_mut()
                 .as_mut()
@@ -783,432 +2594,1576 @@ fn set_csi<'a>(
                 .csi_config
                 .acquire_csi_dcm = 0;
         }),
-        Err(_) => (),
+        _ => (),
     }
     match disable_csi_beamformed {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
+            config
+                .borrow_mut()
+                .as_mut()
+                .unwrap()
+                .csi_config
+                .acquire_csi_beamformed = 0;
+        }),
+        _ => (),
+    }
+    match csi_he_stbc {
+        Ok(str) => {
+            if str.is_some() {
+                match str.unwrap().parse::<u32>() {
+                    Ok(val) => CSI_COLLECTOR.lock(|config| {
+                        config
+                            .borrow_mut()
+                            .as_mut()
+                            .unwrap()
+                            .csi_config
+                            .acquire_csi_he_stbc = val;
+                    }),
+                    Err(_) => writeln!(serial, "Invalid Max Connections").unwrap(),
+                }
+            }
+        }
+        Err(_) => (),
+    }
+    match val_scale_cfg {
+        Ok(str) => {
+            if str.is_some() {
+                match str.unwrap().parse::<u32>() {
+                    Ok(val) => CSI_COLLECTOR.lock(|config| {
+                        config
+                            .borrow_mut()
+                            .as_mut()
+                            .unwrap()
+                            .csi_config
+                            .val_scale_cfg = val;
+                    }),
+                    Err(_) => writeln!(serial, "Invalid Max Connections").unwrap(),
+                }
+            }
+        }
+        Err(_) => (),
+    }
+
+    writeln!(serial, "\nUpdated CSI Configuration:\n").unwrap();
+    CSI_COLLECTOR.lock(|config| {
+        writeln!(
+            serial,
+            "Acquire CSI: {}",
+            config.borrow().as_ref().unwrap().csi_config.enable
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "Acquire Legacy CSI: {}",
+            config
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .csi_config
+                .acquire_csi_legacy
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "Acquire HT20: {}",
+            config
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .csi_config
+                .acquire_csi_ht20
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "Acquire HT40: {}",
+            config
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .csi_config
+                .acquire_csi_ht40
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "Acquire HE20 SU: {}",
+            config.borrow().as_ref().unwrap().csi_config.acquire_csi_su
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "Acquire HE20 MU: {}",
+            config.borrow().as_ref().unwrap().csi_config.acquire_csi_mu
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "Acquire HE20 DCM: {}",
+            config.borrow().as_ref().unwrap().csi_config.acquire_csi_dcm
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "Acquire HE20 Beamformed: {}",
+            config
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .csi_config
+                .acquire_csi_beamformed
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "STBC HE: {}",
+            config
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .csi_config
+                .acquire_csi_he_stbc
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "Scale Value: {}",
+            config.borrow().as_ref().unwrap().csi_config.val_scale_cfg
+        )
+        .unwrap();
+    });
+    apply_live(serial);
+}
+
+#[cfg(not(feature = "esp32c6"))]
+fn set_csi<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    let disable_lltf = argument_finder(item, args, arg::DISABLE_LLTF);
+    let disable_htltf = argument_finder(item, args, arg::DISABLE_HTLTF);
+    let disable_stbc_htltf = argument_finder(item, args, arg::DISABLE_STBC_HTLTF);
+    let disable_ltf_merge = argument_finder(item, args, arg::DISABLE_LTF_MERGE);
+
+    match disable_lltf {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
+            config
+                .borrow_mut()
+                .as_mut()
+                .unwrap()
+                .csi_config
+                .lltf_enabled = false;
+        }),
+        _ => (),
+    }
+    match disable_htltf {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
+            config
+                .borrow_mut()
+                .as_mut()
+                .unwrap()
+                .csi_config
+                .htltf_enabled = false;
+        }),
+        _ => (),
+    }
+    match disable_stbc_htltf {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
+            config
+                .borrow_mut()
+                .as_mut()
+                .unwrap()
+                .csi_config
+                .stbc_htltf2_enabled = false;
+        }),
+        _ => (),
+    }
+    match disable_ltf_merge {
+        Ok(Some(_)) => CSI_COLLECTOR.lock(|config| {
             config
                 .borrow_mut()
                 .as_mut()
                 .unwrap()
                 .csi_config
-                .acquire_csi_beamformed = 0;
+                .ltf_merge_enabled = false;
         }),
-        Err(_) => (),
-    }
-    match csi_he_stbc {
-        Ok(str) => {
-            if str.is_some() {
-                match str.unwrap().parse::<u32>() {
-                    Ok(val) => CSI_COLLECTOR.lock(|config| {
-                        config
-                            .borrow_mut()
-                            .as_mut()
-                            .unwrap()
-                            .csi_config
-                            .acquire_csi_he_stbc = val;
-                    }),
-                    Err(_) => writeln!(serial, "Invalid Max Connections").unwrap(),
-                }
-            }
-        }
-        Err(_) => (),
-    }
-    match val_scale_cfg {
-        Ok(str) => {
-            if str.is_some() {
-                match str.unwrap().parse::<u32>() {
-                    Ok(val) => CSI_COLLECTOR.lock(|config| {
-                        config
-                            .borrow_mut()
-                            .as_mut()
-                            .unwrap()
-                            .csi_config
-                            .val_scale_cfg = val;
-                    }),
-                    Err(_) => writeln!(serial, "Invalid Max Connections").unwrap(),
-                }
-            }
-        }
-        Err(_) => (),
+        _ => (),
     }
 
     writeln!(serial, "\nUpdated CSI Configuration:\n").unwrap();
     CSI_COLLECTOR.lock(|config| {
         writeln!(
             serial,
-            "Acquire CSI: {}",
-            config.borrow().as_ref().unwrap().csi_config.enable
+            "LLTF Enabled: {}",
+            config.borrow().as_ref().unwrap().csi_config.lltf_enabled
         )
         .unwrap();
         writeln!(
             serial,
-            "Acquire Legacy CSI: {}",
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_legacy
+            "HTLTF Enabled: {}",
+            config.borrow().as_ref().unwrap().csi_config.htltf_enabled
         )
         .unwrap();
         writeln!(
             serial,
-            "Acquire HT20: {}",
+            "STBC HTLTF Enabled: {}",
             config
                 .borrow()
                 .as_ref()
                 .unwrap()
                 .csi_config
-                .acquire_csi_ht20
+                .stbc_htltf2_enabled
         )
         .unwrap();
         writeln!(
             serial,
-            "Acquire HT40: {}",
+            "LTF Merge Enabled: {}",
             config
                 .borrow()
                 .as_ref()
                 .unwrap()
                 .csi_config
-                .acquire_csi_ht40
-        )
-        .unwrap();
-        writeln!(
-            serial,
-            "Acquire HE20 SU: {}",
-            config.borrow().as_ref().unwrap().csi_config.acquire_csi_su
-        )
-        .unwrap();
-        writeln!(
-            serial,
-            "Acquire HE20 MU: {}",
-            config.borrow().as_ref().unwrap().csi_config.acquire_csi_mu
+                .ltf_merge_enabled
         )
         .unwrap();
+    });
+    apply_live(serial);
+}
+
+fn set_wifi<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    if rejected_while_collecting(serial, "WiFi Settings") {
+        return;
+    }
+    // Every argument is checked before any is applied, so a bad one changes nothing
+    let mode = match argument_finder(item, args, arg::MODE) {
+        Ok(Some(str)) => match str.parse::<WifiModeArg>() {
+            Ok(mode) => Some(mode),
+            Err(_) => {
+                writeln!(serial, "Invalid WiFi Mode").unwrap();
+                return;
+            }
+        },
+        _ => None,
+    };
+    let max_connections = match argument_finder(item, args, arg::MAX_CONNECTIONS) {
+        Ok(Some(str)) => match str.parse::<u16>() {
+            Ok(max_conn) => Some(max_conn),
+            Err(_) => {
+                writeln!(serial, "Invalid Max Connections").unwrap();
+                return;
+            }
+        },
+        _ => None,
+    };
+    let hide_ssid = matches!(argument_finder(item, args, arg::HIDE_SSID), Ok(Some(_)));
+    let Ok(ap_ssid) = credential::<32>(item, args, arg::AP_SSID, serial) else {
+        return;
+    };
+    let Ok(ap_password) = credential::<64>(item, args, arg::AP_PASSWORD, serial) else {
+        return;
+    };
+    let Ok(sta_ssid) = credential::<32>(item, args, arg::STA_SSID, serial) else {
+        return;
+    };
+    let Ok(sta_password) = credential::<64>(item, args, arg::STA_PASSWORD, serial) else {
+        return;
+    };
+    let Some((channel, country, hop)) = channel_settings(item, args, serial) else {
+        return;
+    };
+    // An AP+STA follows the channel of the network its station joins
+    let ap_station = match mode {
+        Some(mode) => mode == WifiModeArg::AccessPointStation,
+        None => CSI_COLLECTOR.lock(|config| {
+            matches!(
+                config.borrow().as_ref().unwrap().op_mode,
+                WiFiMode::AccessPointStation
+            )
+        }),
+    };
+    if ap_station && channel.is_some() {
         writeln!(
             serial,
-            "Acquire HE20 DCM: {}",
-            config.borrow().as_ref().unwrap().csi_config.acquire_csi_dcm
+            "A Channel Cannot Be Set in ap-station Mode, Use --channel=0"
         )
         .unwrap();
+        return;
+    }
+
+    CSI_COLLECTOR.lock(|config| {
+        let mut config = config.borrow_mut();
+        let config = config.as_mut().unwrap();
+        if let Some(mode) = mode {
+            config.op_mode = match mode {
+                WifiModeArg::AccessPoint => WiFiMode::AccessPoint,
+                WifiModeArg::Station => WiFiMode::Station,
+                WifiModeArg::Sniffer => WiFiMode::Sniffer,
+                WifiModeArg::AccessPointStation => WiFiMode::AccessPointStation,
+            };
+        }
+        if let Some(max_conn) = max_connections {
+            config.wifi_config.max_connections = max_conn;
+        }
+        if hide_ssid {
+            config.wifi_config.ssid_hidden = true;
+        }
+        if let Some(ssid) = ap_ssid {
+            config.wifi_config.ap_ssid = ssid.try_into().unwrap();
+        }
+        if let Some(password) = ap_password {
+            config.wifi_config.ap_password = password.try_into().unwrap();
+        }
+        if let Some(ssid) = sta_ssid {
+            config.wifi_config.ssid = ssid.try_into().unwrap();
+        }
+        if let Some(password) = sta_password {
+            config.wifi_config.password = password.try_into().unwrap();
+        }
+    });
+    CHANNEL_CONFIG.lock(|config| config.set(channel));
+    COUNTRY.lock(|code| code.set(country));
+    HOP.lock(|config| config.replace(Some(hop)));
+
+    writeln!(serial, "\nUpdated WiFi Configuration:\n").unwrap();
+    CSI_COLLECTOR.lock(|config| {
         writeln!(
             serial,
-            "Acquire HE20 Beamformed: {}",
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_beamformed
+            "WiFi Operation Mode: {:?}",
+            config.borrow().as_ref().unwrap().op_mode
         )
         .unwrap();
         writeln!(
             serial,
-            "STBC HE: {}",
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_he_stbc
+            "Station WiFi Settings:\nSSID: '{}', Password: '{}'",
+            config.borrow().as_ref().unwrap().wifi_config.ssid,
+            config.borrow().as_ref().unwrap().wifi_config.password,
         )
         .unwrap();
         writeln!(
             serial,
-            "Scale Value: {}",
-            config.borrow().as_ref().unwrap().csi_config.val_scale_cfg
+            "Access Point WiFi Settings:\nSSID: '{}', Password: '{}', SSID Hidden: {}, Max Connections: {}",
+            config.borrow().as_ref().unwrap().wifi_config.ap_ssid,
+            config.borrow().as_ref().unwrap().wifi_config.ap_password,
+            config.borrow().as_ref().unwrap().wifi_config.ssid_hidden,
+            config.borrow().as_ref().unwrap().wifi_config.max_connections,
         )
         .unwrap();
     });
+    show_channel(serial);
+}
+
+// Reads an SSID or password of set-wifi, with underscores standing for spaces. Err once
+// the value has been reported as too long.
+fn credential<const N: usize>(
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    name: &str,
+    serial: &mut SerialInterfaceType,
+) -> Result<Option<heapless::String<N>>, ()> {
+    match argument_finder(item, args, name) {
+        Ok(Some(str)) => match heapless::String::try_from(str.replace("_", " ").as_str()) {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", name).unwrap();
+                Err(())
+            }
+        },
+        _ => Ok(None),
+    }
+}
+
+// Reads the channel arguments of set-wifi together, as the channel is only checked
+// against the country as a whole. None once an invalid one has been reported.
+fn channel_settings(
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+) -> Option<(Option<ChannelConfig>, Country, HopConfig)> {
+    let mut channel = CHANNEL_CONFIG.lock(|config| config.get());
+    let mut country = COUNTRY.lock(|country| country.get());
+    match argument_finder(item, args, arg::COUNTRY) {
+        Ok(Some(str)) => match str.parse::<Country>() {
+            Ok(code) => country = code,
+            Err(_) => {
+                writeln!(serial, "Invalid Country Code").unwrap();
+                return None;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::CHANNEL) {
+        Ok(Some(str)) => match str.parse::<u8>() {
+            Ok(0) => channel = None,
+            Ok(number) => {
+                channel = Some(ChannelConfig {
+                    channel: number,
+                    secondary: channel.map_or(Secondary::None, |c| c.secondary),
+                })
+            }
+            Err(_) => {
+                writeln!(serial, "Invalid Channel").unwrap();
+                return None;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::SECONDARY) {
+        Ok(Some(str)) => match (str.parse::<Secondary>(), channel.as_mut()) {
+            (Ok(secondary), Some(config)) => config.secondary = secondary,
+            (Ok(_), None) => {
+                writeln!(serial, "A Secondary Channel Needs --channel").unwrap();
+                return None;
+            }
+            (Err(_), _) => {
+                writeln!(serial, "Invalid Secondary Channel").unwrap();
+                return None;
+            }
+        },
+        _ => (),
+    }
+    let mut hop = HOP.lock(|hop| hop.borrow().clone().unwrap_or_default());
+    match argument_finder(item, args, arg::HOP) {
+        Ok(Some(str)) => match str.parse::<ChannelList>() {
+            Ok(channels) => hop.channels = channels,
+            Err(_) => {
+                writeln!(serial, "Invalid Hop Channels").unwrap();
+                return None;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::DWELL_MS) {
+        Ok(Some(str)) => match str.parse::<u64>() {
+            Ok(dwell_ms) => hop.dwell_ms = dwell_ms,
+            Err(_) => {
+                writeln!(serial, "Invalid Dwell Time").unwrap();
+                return None;
+            }
+        },
+        _ => (),
+    }
+    if let Some(Err(e)) = channel.map(|config| config.validate(&country)) {
+        writeln!(serial, "Invalid Channel Settings: {}", e).unwrap();
+        return None;
+    }
+    if let Err(e) = hop.validate(&country) {
+        writeln!(serial, "Invalid Channel Settings: {}", e).unwrap();
+        return None;
+    }
+    Some((channel, country, hop))
+}
+
+fn show_channel(serial: &mut SerialInterfaceType) {
+    match CHANNEL_CONFIG.lock(|config| config.get()) {
+        Some(config) => {
+            writeln!(serial, "{}: {}", key::WIFI_CHANNEL, config.channel).unwrap();
+            writeln!(
+                serial,
+                "{}: {}",
+                key::WIFI_SECONDARY_CHANNEL,
+                config.secondary
+            )
+            .unwrap();
+        }
+        None => {
+            writeln!(serial, "{}: auto", key::WIFI_CHANNEL).unwrap();
+            writeln!(serial, "{}: none", key::WIFI_SECONDARY_CHANNEL).unwrap();
+        }
+    }
+    let country = COUNTRY.lock(|country| country.get());
+    writeln!(serial, "{}: {}", key::WIFI_COUNTRY, country).unwrap();
+    HOP.lock(|hop| {
+        let hop = hop.borrow();
+        let hop = hop.as_ref().unwrap();
+        writeln!(serial, "{}: {}", key::WIFI_HOP, hop.channels).unwrap();
+        writeln!(serial, "{}: {}ms", key::WIFI_DWELL, hop.dwell_ms).unwrap();
+    });
+}
+
+// Sets the country and the AP channel chosen with set-wifi, which the collector leaves to
+// the driver, once WiFi is set up. The AP channel goes in the AP configuration, with the
+// driver placing the secondary channel of a 40 MHz channel.
+fn apply_wifi_setup(setup: &WifiSetup) {
+    let [first, second] = *setup.country.as_bytes();
+    let code = [first, second, 0];
+    let err = unsafe { esp_wifi_set_country_code(code.as_ptr() as *const _, false) };
+    if err != 0 {
+        println!("Failed to Set Country {}: Error {}", setup.country, err);
+    }
+    let Some(config) = setup.ap_channel else {
+        return;
+    };
+    let err = unsafe {
+        let mut ap: wifi_config_t = core::mem::zeroed();
+        let mut err = esp_wifi_get_config(wifi_interface_t_WIFI_IF_AP, &mut ap);
+        if err == 0 {
+            ap.ap.channel = config.channel;
+            err = esp_wifi_set_config(wifi_interface_t_WIFI_IF_AP, &mut ap);
+        }
+        if err == 0 && config.secondary != Secondary::None {
+            err =
+                esp_wifi_set_bandwidth(wifi_interface_t_WIFI_IF_AP, wifi_bandwidth_t_WIFI_BW_HT40);
+        }
+        err
+    };
+    if err != 0 {
+        println!("Failed to Set Channel {}: Error {}", config.channel, err);
+    }
+    channel_changed(config.channel);
+}
+
+// Tunes a sniffer to the channel chosen with set-wifi at the start of every run, as
+// hopping moves it. Stations follow the channel of the network they connect to, and an
+// AP keeps the channel it was set up with.
+fn apply_channel(mode: WifiModeArg) {
+    if !matches!(mode, WifiModeArg::Sniffer) {
+        return;
+    }
+    let Some(config) = CHANNEL_CONFIG.lock(|config| config.get()) else {
+        return;
+    };
+    let secondary = match config.secondary {
+        Secondary::None => wifi_second_chan_t_WIFI_SECOND_CHAN_NONE,
+        Secondary::Above => wifi_second_chan_t_WIFI_SECOND_CHAN_ABOVE,
+        Secondary::Below => wifi_second_chan_t_WIFI_SECOND_CHAN_BELOW,
+    };
+    if unsafe { esp_wifi_set_channel(config.channel, secondary) } != 0 {
+        println!("Failed to Set Channel {}", config.channel);
+    }
+    channel_changed(config.channel);
+}
+
+// A BSS found for the SSID filter on another channel is looked for again
+fn channel_changed(channel: u8) {
+    FILTER.lock(|filter| {
+        if let Some(filter) = filter.borrow_mut().as_mut() {
+            filter.set_channel(channel);
+        }
+    });
+}
+
+fn set_output<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    let format = argument_finder(item, args, arg::FORMAT);
+
+    match format {
+        Ok(str) => {
+            if str.is_some() {
+                match str.unwrap().parse::<OutputFormat>() {
+                    Ok(format) => OUTPUT_FORMAT.lock(|config| config.set(format)),
+                    Err(_) => writeln!(serial, "Invalid Output Format").unwrap(),
+                }
+            }
+        }
+        Err(_) => (),
+    }
+
+    writeln!(
+        serial,
+        "\nUpdated Output Format to {}",
+        OUTPUT_FORMAT.lock(|format| format.get())
+    )
+    .unwrap();
+}
+
+// Checks an operator request against the collector state and moves to `to` if given,
+// explaining a rejection
+fn accepted(serial: &mut SerialInterfaceType, request: Request, to: Option<State>) -> bool {
+    let result = STATE.lock(|state| {
+        state.get().accepts(request)?;
+        if let Some(to) = to {
+            state.set(to);
+        }
+        Ok(())
+    });
+    match result {
+        Ok(_) => true,
+        Err(rejected) => {
+            writeln!(serial, "Rejected: {}", rejected).unwrap();
+            false
+        }
+    }
+}
+
+fn start_csi_collect<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    let count = match argument_finder(item, args, arg::COUNT) {
+        Ok(Some(str)) => match str.parse::<u32>() {
+            Ok(count) if count > 0 => Some(count),
+            _ => {
+                writeln!(serial, "Invalid Count").unwrap();
+                return;
+            }
+        },
+        _ => None,
+    };
+    let interval = match argument_finder(item, args, arg::DURATION) {
+        Ok(Some(str)) => match str.parse::<u64>() {
+            Ok(interval) => interval,
+            Err(_) => {
+                writeln!(serial, "Invalid Duration").unwrap();
+                return;
+            }
+        },
+        // Run for one week if no value provided
+        // 604800 seconds is equivalent to one week
+        Ok(None) => Duration::from_secs(604800).as_secs(),
+        Err(_) => return,
+    };
+    // Starting until the collector picks the request up, so a second start or a change
+    // to the WiFi settings in between is rejected
+    if !accepted(serial, Request::Start, Some(State::Starting)) {
+        return;
+    }
+    START_SIGNAL.signal((interval, count));
+    if interval == 604800 && count.is_none() {
+        println!("Running Forever");
+    }
+}
+
+fn stop_csi_collect<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    _item: &Item<SerialInterfaceType, Context>,
+    _args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    if accepted(serial, Request::Stop, None) {
+        STOP_SIGNAL.signal(());
+    }
+}
+
+fn pause_csi_collect<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    if !accepted(serial, Request::Pause, Some(State::Paused)) {
+        return;
+    }
+    let traffic = match argument_finder(item, args, arg::TRAFFIC) {
+        Ok(Some(_)) => true,
+        _ => false,
+    };
+    PAUSED_AT.lock(|p| p.set(Some(Instant::now())));
+    if traffic {
+        // Drop a resume left over from an earlier pause of the output only
+        RESUME_SIGNAL.reset();
+        PAUSE_TRAFFIC_SIGNAL.signal(());
+    }
+    print_event(&Event::Paused {
+        records: RECORD_SEQ.lock(|seq| seq.get()),
+        traffic,
+    });
 }
 
-#[cfg(not(feature = "esp32c6"))]
-fn set_csi<'a>(
+fn resume_csi_collect<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    _item: &Item<SerialInterfaceType, Context>,
+    _args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    if !accepted(serial, Request::Resume, Some(State::Collecting)) {
+        return;
+    }
+    let paused_at = PAUSED_AT.lock(|p| p.take()).unwrap_or_else(Instant::now);
+    // Restarts traffic generation if it was paused, ignored otherwise
+    PAUSE_TRAFFIC_SIGNAL.reset();
+    RESUME_SIGNAL.signal(());
+    print_event(&Event::Resumed {
+        paused_ms: paused_at.elapsed().as_millis(),
+    });
+}
+
+fn set_trigger<'a>(
     _menu: &Menu<SerialInterfaceType, Context>,
     item: &Item<SerialInterfaceType, Context>,
     args: &[&str],
     serial: &mut SerialInterfaceType,
     _context: &mut Context,
 ) {
-    let disable_lltf = argument_finder(item, args, "disable-lltf");
-    let disable_htltf = argument_finder(item, args, "disable-htltf");
-    let disable_stbc_htltf = argument_finder(item, args, "disable-stbc-htltf");
-    let disable_ltf_merge = argument_finder(item, args, "disable-ltf-merge");
+    match argument_finder(item, args, arg::OFF) {
+        Ok(Some(_)) => {
+            TRIGGER_CONFIG.lock(|config| config.set(None));
+            writeln!(serial, "\nTrigger Disarmed").unwrap();
+            return;
+        }
+        _ => (),
+    }
 
-    match disable_lltf {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
-            config
-                .borrow_mut()
-                .as_mut()
-                .unwrap()
-                .csi_config
-                .lltf_enabled = false;
-        }),
-        Err(_) => (),
+    // Arguments left out keep their previous values
+    let mut config = TRIGGER_CONFIG
+        .lock(|config| config.get())
+        .unwrap_or_default();
+    match argument_finder(item, args, arg::RSSI) {
+        Ok(Some(str)) => match str.parse::<i8>() {
+            Ok(rssi) => config.min_rssi = Some(rssi),
+            Err(_) => {
+                writeln!(serial, "Invalid RSSI").unwrap();
+                return;
+            }
+        },
+        _ => (),
     }
-    match disable_htltf {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
-            config
-                .borrow_mut()
-                .as_mut()
-                .unwrap()
-                .csi_config
-                .htltf_enabled = false;
-        }),
-        Err(_) => (),
+    match argument_finder(item, args, arg::MAC) {
+        Ok(Some(str)) => match str.parse::<MacAddr>() {
+            Ok(mac) => config.mac = Some(mac),
+            Err(_) => {
+                writeln!(serial, "Invalid MAC Address").unwrap();
+                return;
+            }
+        },
+        _ => (),
     }
-    match disable_stbc_htltf {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
-            config
-                .borrow_mut()
-                .as_mut()
-                .unwrap()
-                .csi_config
-                .stbc_htltf2_enabled = false;
-        }),
-        Err(_) => (),
+    match argument_finder(item, args, arg::VARIANCE) {
+        Ok(Some(str)) => match str.parse::<f32>() {
+            Ok(variance) if variance >= 0.0 => config.min_variance = Some(variance),
+            _ => {
+                writeln!(serial, "Invalid Variance").unwrap();
+                return;
+            }
+        },
+        _ => (),
     }
-    match disable_ltf_merge {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
-            config
-                .borrow_mut()
-                .as_mut()
-                .unwrap()
-                .csi_config
-                .ltf_merge_enabled = false;
+    match argument_finder(item, args, arg::PRE) {
+        Ok(Some(str)) => match str.parse::<usize>() {
+            Ok(records) if records <= MAX_PRE_RECORDS => config.pre_records = records,
+            _ => {
+                writeln!(
+                    serial,
+                    "Invalid Pre-Trigger Records, at Most {} Are Kept",
+                    MAX_PRE_RECORDS
+                )
+                .unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::POST) {
+        Ok(Some(str)) => match str.parse::<u64>() {
+            Ok(post_s) => config.post_s = post_s,
+            Err(_) => {
+                writeln!(serial, "Invalid Post-Trigger Duration").unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+
+    TRIGGER_CONFIG.lock(|c| c.set(Some(config)));
+    writeln!(serial, "\nTrigger Armed for the Next Collection:\n").unwrap();
+    show_trigger(serial);
+}
+
+fn fire_trigger<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    _item: &Item<SerialInterfaceType, Context>,
+    _args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    if !accepted(serial, Request::Trigger, None) {
+        return;
+    }
+    match TRIGGER.lock(|trigger| trigger.borrow_mut().as_mut().map(|t| t.fire())) {
+        Some(true) => on_trigger(Cause::Manual),
+        Some(false) => writeln!(serial, "Trigger Already Fired").unwrap(),
+        None => writeln!(serial, "Trigger Not Armed for This Collection").unwrap(),
+    }
+}
+
+fn insert_mark<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    match argument_finder(item, args, arg::LABEL) {
+        Ok(Some(str)) if is_valid_label(str) => print_event(&Event::Mark {
+            label: Label::try_from(str).unwrap(),
+            timestamp_us: Instant::now().as_micros(),
         }),
-        Err(_) => (),
+        _ => writeln!(serial, "Invalid Label").unwrap(),
     }
+}
 
-    writeln!(serial, "\nUpdated CSI Configuration:\n").unwrap();
-    CSI_COLLECTOR.lock(|config| {
-        writeln!(
-            serial,
-            "LLTF Enabled: {}",
-            config.borrow().as_ref().unwrap().csi_config.lltf_enabled
-        )
-        .unwrap();
-        writeln!(
-            serial,
-            "HTLTF Enabled: {}",
-            config.borrow().as_ref().unwrap().csi_config.htltf_enabled
-        )
-        .unwrap();
-        writeln!(
-            serial,
-            "STBC HTLTF Enabled: {}",
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .stbc_htltf2_enabled
-        )
-        .unwrap();
-        writeln!(
-            serial,
-            "LTF Merge Enabled: {}",
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .ltf_merge_enabled
-        )
-        .unwrap();
+fn set_label<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    let action = match argument_finder(item, args, arg::ACTION) {
+        Ok(Some(str)) => match str.parse::<LabelAction>() {
+            Ok(action) => Some(action),
+            Err(_) => {
+                writeln!(serial, "Invalid Action").unwrap();
+                return;
+            }
+        },
+        _ => None,
+    };
+    match action {
+        Some(LabelAction::Set) => match argument_finder(item, args, arg::LABEL) {
+            Ok(Some(str)) if is_valid_label(str) => {
+                LABEL.lock(|label| label.replace(Label::try_from(str).unwrap()));
+                print_label();
+            }
+            Ok(Some(_)) => writeln!(serial, "Invalid Label").unwrap(),
+            _ => writeln!(serial, "Missing Label").unwrap(),
+        },
+        Some(LabelAction::Clear) => {
+            LABEL.lock(|label| label.borrow_mut().clear());
+            print_label();
+        }
+        None => {
+            let label = LABEL.lock(|label| label.borrow().clone());
+            match label.is_empty() {
+                true => writeln!(serial, "{}: none", state::key::LABEL).unwrap(),
+                false => writeln!(serial, "{}: {}", state::key::LABEL, label).unwrap(),
+            }
+        }
+    }
+}
+
+// Marks the start of the current label in the stream, or its end if cleared
+fn print_label() {
+    let label = LABEL.lock(|label| label.borrow().clone());
+    print_event(&Event::Label {
+        label,
+        timestamp_us: Instant::now().as_micros(),
     });
 }
 
-fn set_wifi<'a>(
+fn set_gpio<'a>(
     _menu: &Menu<SerialInterfaceType, Context>,
     item: &Item<SerialInterfaceType, Context>,
     args: &[&str],
     serial: &mut SerialInterfaceType,
     _context: &mut Context,
 ) {
-    let mode = argument_finder(item, args, "mode");
-    let max_connections = argument_finder(item, args, "max-connections");
-    let hide_ssid = argument_finder(item, args, "hide-ssid");
-    let ap_ssid = argument_finder(item, args, "ap-ssid");
-    let ap_password = argument_finder(item, args, "ap-password");
-    let sta_ssid = argument_finder(item, args, "sta-ssid");
-    let sta_password = argument_finder(item, args, "sta-password");
+    match argument_finder(item, args, arg::OFF) {
+        Ok(Some(_)) => {
+            GPIO_CONFIG.lock(|config| config.set(None));
+            GPIO_SIGNAL.signal(());
+            writeln!(serial, "\nGPIO Inputs Disabled").unwrap();
+            return;
+        }
+        _ => (),
+    }
 
-    match mode {
-        Ok(str) => {
-            if str.is_some() {
-                match str.unwrap() {
-                    "ap" => CSI_COLLECTOR.lock(|config| {
-                        config.borrow_mut().as_mut().unwrap().op_mode = WiFiMode::AccessPoint;
-                    }),
-                    "station" => CSI_COLLECTOR.lock(|config| {
-                        config.borrow_mut().as_mut().unwrap().op_mode = WiFiMode::Station;
-                    }),
-                    "sniffer" => CSI_COLLECTOR.lock(|config| {
-                        config.borrow_mut().as_mut().unwrap().op_mode = WiFiMode::Sniffer;
-                    }),
-                    "ap-station" => CSI_COLLECTOR.lock(|config| {
-                        config.borrow_mut().as_mut().unwrap().op_mode =
-                            WiFiMode::AccessPointStation;
-                    }),
-                    _ => writeln!(serial, "Invalid WiFi Mode").unwrap(),
+    // Arguments left out keep their previous values
+    let mut config = GPIO_CONFIG.lock(|config| config.get()).unwrap_or_default();
+    for (pin, name) in [
+        (&mut config.start_pin, arg::START_PIN),
+        (&mut config.event_pin, arg::EVENT_PIN),
+    ] {
+        match argument_finder(item, args, name) {
+            Ok(Some(str)) => match str.parse::<u8>() {
+                Ok(number) => *pin = Some(number),
+                Err(_) => {
+                    writeln!(serial, "Invalid Value for --{}", name).unwrap();
+                    return;
                 }
+            },
+            _ => (),
+        }
+    }
+    match argument_finder(item, args, arg::POLARITY) {
+        Ok(Some(str)) => match str.parse::<Polarity>() {
+            Ok(polarity) => config.polarity = polarity,
+            Err(_) => {
+                writeln!(serial, "Invalid Polarity").unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::DEBOUNCE_MS) {
+        Ok(Some(str)) => match str.parse::<u64>() {
+            Ok(debounce_ms) => config.debounce_ms = debounce_ms,
+            Err(_) => {
+                writeln!(serial, "Invalid Debounce Time").unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+
+    if let Err(e) = config.validate(CHIP) {
+        writeln!(serial, "Invalid GPIO Settings: {}", e).unwrap();
+        if let Some(pins) = input_pins(CHIP) {
+            write!(serial, "Usable Pins on {}:", CHIP).unwrap();
+            for pin in pins {
+                write!(serial, " {}", pin).unwrap();
             }
+            writeln!(serial).unwrap();
         }
-        Err(_) => (),
+        return;
     }
-    match max_connections {
-        Ok(str) => {
-            if str.is_some() {
-                match str.unwrap().parse::<u16>() {
-                    Ok(max_conn) => CSI_COLLECTOR.lock(|config| {
-                        config
-                            .borrow_mut()
-                            .as_mut()
-                            .unwrap()
-                            .wifi_config
-                            .max_connections = max_conn;
-                    }),
-                    Err(_) => writeln!(serial, "Invalid Max Connections").unwrap(),
-                }
+    GPIO_CONFIG.lock(|c| c.set(Some(config)));
+    GPIO_SIGNAL.signal(());
+    writeln!(serial, "\nGPIO Settings Applied:\n").unwrap();
+    show_gpio(serial);
+}
+
+fn set_headless<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    // Arguments left out keep their previous values
+    let mut config = HEADLESS.lock(|config| config.get());
+    let enable = matches!(argument_finder(item, args, arg::ENABLE), Ok(Some(_)));
+    let disable = matches!(argument_finder(item, args, arg::DISABLE), Ok(Some(_)));
+    match (enable, disable) {
+        (true, true) => {
+            writeln!(serial, "Choose Either --enable or --disable").unwrap();
+            return;
+        }
+        (true, false) => config.enabled = true,
+        (false, true) => config.enabled = false,
+        (false, false) => (),
+    }
+    match argument_finder(item, args, arg::SINK) {
+        Ok(Some(str)) => match str.parse::<Sink>() {
+            Ok(sink) => config.sink = sink,
+            Err(_) => {
+                writeln!(serial, "Invalid Sink").unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::DURATION) {
+        Ok(Some(str)) => match str.parse::<u64>() {
+            Ok(0) => config.duration_s = None,
+            Ok(duration_s) => config.duration_s = Some(duration_s),
+            Err(_) => {
+                writeln!(serial, "Invalid Duration").unwrap();
+                return;
             }
+        },
+        _ => (),
+    }
+
+    // The collector is set up at boot as it is now
+    let settings = match collector_settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            writeln!(serial, "Cannot Store the Collector Settings: {}", e).unwrap();
+            return;
         }
-        Err(_) => (),
+    };
+    if !store_headless(&config, &settings) {
+        writeln!(serial, "Failed to Store Headless Settings").unwrap();
+        return;
     }
-    match hide_ssid {
-        Ok(_str) => CSI_COLLECTOR.lock(|config| {
-            config
-                .borrow_mut()
-                .as_mut()
-                .unwrap()
-                .wifi_config
-                .ssid_hidden = true;
-        }),
-        Err(_) => (),
+    HEADLESS.lock(|c| c.set(config));
+    writeln!(
+        serial,
+        "\nHeadless Settings Stored, Applied at the Next Boot:\n"
+    )
+    .unwrap();
+    show_headless(serial);
+    writeln!(serial, "\nCollector Settings Stored With Them:\n").unwrap();
+    for line in settings.lines() {
+        writeln!(serial, "{}", line).unwrap();
     }
-    match ap_ssid {
-        Ok(str) => {
-            if let Some(s) = str {
-                let str_w_space = s.replace("_", " ");
-                // Convert the `mod_str` into a `heapless::String<32>`
-                let mut hpls_str_w_space = heapless::String::<32>::new();
-                hpls_str_w_space.push_str(&str_w_space).unwrap(); // Ensure it fits within the capacity
+}
 
-                CSI_COLLECTOR.lock(|config| {
-                    config.borrow_mut().as_mut().unwrap().wifi_config.ap_ssid =
-                        hpls_str_w_space.try_into().unwrap();
-                });
+fn set_filter<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    // Arguments left out keep their previous values
+    let mut config = match argument_finder(item, args, arg::OFF) {
+        Ok(Some(_)) => FilterConfig::default(),
+        _ => FILTER.lock(|filter| filter.borrow().as_ref().unwrap().config().clone()),
+    };
+    for (list, name) in [
+        (&mut config.allow_mac, arg::ALLOW_MAC),
+        (&mut config.deny_mac, arg::DENY_MAC),
+    ] {
+        match argument_finder(item, args, name) {
+            Ok(Some(str)) => match str.parse::<MacList>() {
+                Ok(patterns) => *list = patterns,
+                Err(_) => {
+                    writeln!(serial, "Invalid Value for --{}", name).unwrap();
+                    return;
+                }
+            },
+            _ => (),
+        }
+    }
+    for (bound, name) in [
+        (&mut config.min_rssi, arg::MIN_RSSI),
+        (&mut config.max_rssi, arg::MAX_RSSI),
+    ] {
+        match argument_finder(item, args, name) {
+            Ok(Some(str)) => match str.parse::<RssiBound>() {
+                Ok(rssi) => *bound = rssi.0,
+                Err(_) => {
+                    writeln!(serial, "Invalid Value for --{}", name).unwrap();
+                    return;
+                }
+            },
+            _ => (),
+        }
+    }
+    match argument_finder(item, args, arg::REPLIES) {
+        Ok(Some(str)) => match str.parse::<Switch>() {
+            Ok(switch) => config.replies = switch.into(),
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", arg::REPLIES).unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::FRAME_TYPES) {
+        Ok(Some(str)) => match str.parse::<FrameTypes>() {
+            Ok(types) => config.frame_types = types,
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", arg::FRAME_TYPES).unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::SIG_MODE) {
+        Ok(Some(str)) => match str.parse::<SigModes>() {
+            Ok(modes) => config.sig_mode = modes,
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", arg::SIG_MODE).unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::BANDWIDTH) {
+        Ok(Some(str)) => match str.parse::<Bandwidths>() {
+            Ok(widths) => config.bandwidth = widths,
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", arg::BANDWIDTH).unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::STBC) {
+        Ok(Some(str)) => match str.parse::<Stbc>() {
+            Ok(stbc) => config.stbc = stbc,
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", arg::STBC).unwrap();
+                return;
             }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::DEDUP) {
+        Ok(Some(str)) => match str.parse::<u8>() {
+            Ok(frames) => config.dedup = Some(frames),
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", arg::DEDUP).unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    // A BSS is selected by either, so setting one replaces the other
+    match argument_finder(item, args, arg::BSSID) {
+        Ok(Some(str)) => match str.parse::<MacAddr>() {
+            Ok(bssid) => {
+                config.bssid = Some(bssid);
+                config.ssid = None;
+            }
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", arg::BSSID).unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    match argument_finder(item, args, arg::SSID) {
+        Ok(Some(str)) => match heapless::String::try_from(str.replace("_", " ").as_str()) {
+            Ok(ssid) => {
+                config.ssid = Some(ssid);
+                config.bssid = None;
+            }
+            Err(_) => {
+                writeln!(serial, "Invalid Value for --{}", arg::SSID).unwrap();
+                return;
+            }
+        },
+        _ => (),
+    }
+    for (rate, name) in [
+        (&mut config.max_rate, arg::MAX_RATE),
+        (&mut config.max_rate_per_mac, arg::MAX_RATE_PER_MAC),
+    ] {
+        match argument_finder(item, args, name) {
+            Ok(Some(str)) => match str.parse::<u32>() {
+                Ok(fps) => *rate = Some(fps),
+                Err(_) => {
+                    writeln!(serial, "Invalid Value for --{}", name).unwrap();
+                    return;
+                }
+            },
+            _ => (),
         }
-        Err(_) => (),
     }
-    match ap_password {
-        Ok(str) => {
-            if let Some(s) = str {
-                let str_w_space = s.replace("_", " ");
-                // Convert the `mod_str` into a `heapless::String<32>`
-                let mut hpls_str_w_space = heapless::String::<64>::new();
-                hpls_str_w_space.push_str(&str_w_space).unwrap(); // Ensure it fits within the capacity
-
-                CSI_COLLECTOR.lock(|config| {
-                    config
-                        .borrow_mut()
-                        .as_mut()
-                        .unwrap()
-                        .wifi_config
-                        .ap_password = hpls_str_w_space.try_into().unwrap();
-                });
+    if let Err(e) = config.validate() {
+        writeln!(serial, "Invalid Filter Settings: {}", e).unwrap();
+        return;
+    }
+
+    FILTER.lock(|filter| filter.borrow_mut().as_mut().unwrap().set_config(config));
+    apply_promiscuous_filter();
+    writeln!(serial, "\nFilter Settings Applied:\n").unwrap();
+    show_filter(serial);
+}
+
+fn show_filter(serial: &mut SerialInterfaceType) {
+    FILTER.lock(|filter| {
+        let filter = filter.borrow();
+        let config = filter.as_ref().unwrap().config();
+        writeln!(serial, "{}: {}", key::FILTER_ALLOW_MAC, config.allow_mac).unwrap();
+        writeln!(serial, "{}: {}", key::FILTER_DENY_MAC, config.deny_mac).unwrap();
+        writeln!(
+            serial,
+            "{}: {}",
+            key::FILTER_MIN_RSSI,
+            OrNone(config.min_rssi)
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "{}: {}",
+            key::FILTER_MAX_RSSI,
+            OrNone(config.max_rssi)
+        )
+        .unwrap();
+        writeln!(
+            serial,
+            "{}: {}",
+            key::FILTER_FRAME_TYPES,
+            config.frame_types
+        )
+        .unwrap();
+        for (name, rate) in [
+            (key::FILTER_MAX_RATE, config.max_rate),
+            (key::FILTER_MAX_RATE_PER_MAC, config.max_rate_per_mac),
+        ] {
+            match rate {
+                Some(rate) => writeln!(serial, "{}: {}fps", name, rate).unwrap(),
+                None => writeln!(serial, "{}: none", name).unwrap(),
             }
         }
-        Err(_) => (),
+        writeln!(serial, "{}: {}", key::FILTER_SIG_MODE, config.sig_mode).unwrap();
+        writeln!(serial, "{}: {}", key::FILTER_BANDWIDTH, config.bandwidth).unwrap();
+        writeln!(serial, "{}: {}", key::FILTER_STBC, config.stbc).unwrap();
+        writeln!(serial, "{}: {}", key::FILTER_DEDUP, OrNone(config.dedup)).unwrap();
+        writeln!(serial, "{}: {}", key::FILTER_BSSID, OrNone(config.bssid)).unwrap();
+        match &config.ssid {
+            Some(ssid) => writeln!(serial, "{}: '{}'", key::FILTER_SSID, ssid).unwrap(),
+            None => writeln!(serial, "{}: none", key::FILTER_SSID).unwrap(),
+        }
+        writeln!(serial, "{}: {}", key::FILTER_REPLIES, config.replies).unwrap();
+    });
+}
+
+fn show_headless(serial: &mut SerialInterfaceType) {
+    let config = HEADLESS.lock(|config| config.get());
+    writeln!(serial, "{}: {}", key::HEADLESS_ENABLED, config.enabled).unwrap();
+    writeln!(serial, "{}: {}", key::HEADLESS_SINK, config.sink).unwrap();
+    match config.duration_s {
+        Some(duration_s) => {
+            writeln!(serial, "{}: {}s", key::HEADLESS_DURATION, duration_s).unwrap()
+        }
+        None => writeln!(serial, "{}: none", key::HEADLESS_DURATION).unwrap(),
     }
-    match sta_ssid {
-        Ok(str) => {
-            if let Some(s) = str {
-                let str_w_space = s.replace("_", " ");
-                // Convert the `mod_str` into a `heapless::String<32>`
-                let mut hpls_str_w_space = heapless::String::<32>::new();
-                hpls_str_w_space.push_str(&str_w_space).unwrap(); // Ensure it fits within the capacity
+}
 
-                CSI_COLLECTOR.lock(|config| {
-                    config.borrow_mut().as_mut().unwrap().wifi_config.ssid =
-                        hpls_str_w_space.try_into().unwrap();
-                });
+fn flash_log_command<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    item: &Item<SerialInterfaceType, Context>,
+    args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    let action = match argument_finder(item, args, arg::ACTION) {
+        Ok(Some(str)) => match str.parse::<LogAction>() {
+            Ok(action) => action,
+            Err(_) => {
+                writeln!(serial, "Invalid Action").unwrap();
+                return;
+            }
+        },
+        _ => LogAction::Show,
+    };
+    let mut flash = FlashStorage::new();
+    match action {
+        LogAction::Show => {
+            let end = LOG_END
+                .lock(|end| end.get())
+                .unwrap_or_else(|| find_log_end(&mut flash));
+            LOG_END.lock(|e| e.set(Some(end)));
+            let mut chunk = [0u8; LOG_PAGE_SIZE];
+            let mut offset = 0;
+            while offset < end {
+                if flash.read(LOG_OFFSET + offset, &mut chunk).is_err() {
+                    writeln!(serial, "\nFailed to Read the Flash Log").unwrap();
+                    return;
+                }
+                let len = (end - offset).min(LOG_PAGE_SIZE as u32) as usize;
+                // The log only holds the ASCII lines written to it
+                if let Ok(text) = core::str::from_utf8(&chunk[..len]) {
+                    write!(serial, "{}", text).unwrap();
+                }
+                offset += len as u32;
             }
+            writeln!(serial, "\nLog Size: {} Bytes", end).unwrap();
+            writeln!(
+                serial,
+                "Dropped Lines: {}",
+                LOG_DROPPED.lock(|dropped| dropped.get())
+            )
+            .unwrap();
+        }
+        LogAction::Clear => {
+            erase_log_sector(&mut flash, 0);
+            erase_log_sector(&mut flash, 1);
+            LOG_END.lock(|end| end.set(Some(0)));
+            LOG_DROPPED.lock(|dropped| dropped.set(0));
+            writeln!(serial, "\nFlash Log Cleared").unwrap();
         }
-        Err(_) => (),
     }
-    match sta_password {
-        Ok(str) => {
-            if let Some(s) = str {
-                let str_w_space = s.replace("_", " ");
-                // Convert the `mod_str` into a `heapless::String<32>`
-                let mut hpls_str_w_space = heapless::String::<64>::new();
-                hpls_str_w_space.push_str(&str_w_space).unwrap(); // Ensure it fits within the capacity
+}
 
-                CSI_COLLECTOR.lock(|config| {
-                    config.borrow_mut().as_mut().unwrap().wifi_config.password =
-                        hpls_str_w_space.try_into().unwrap();
-                });
-            }
+fn show_gpio(serial: &mut SerialInterfaceType) {
+    let config = GPIO_CONFIG.lock(|config| config.get()).unwrap_or_default();
+    writeln!(
+        serial,
+        "{}: {}",
+        key::GPIO_START_PIN,
+        OrNone(config.start_pin)
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        key::GPIO_EVENT_PIN,
+        OrNone(config.event_pin)
+    )
+    .unwrap();
+    writeln!(serial, "{}: {}", key::GPIO_POLARITY, config.polarity).unwrap();
+    writeln!(serial, "{}: {}ms", key::GPIO_DEBOUNCE, config.debounce_ms).unwrap();
+}
+
+fn show_trigger(serial: &mut SerialInterfaceType) {
+    let config = TRIGGER_CONFIG.lock(|config| config.get());
+    writeln!(serial, "{}: {}", key::TRIGGER_ARMED, config.is_some()).unwrap();
+    let config = config.unwrap_or_default();
+    writeln!(serial, "{}: {}", key::TRIGGER_RSSI, OrNone(config.min_rssi)).unwrap();
+    writeln!(serial, "{}: {}", key::TRIGGER_MAC, OrNone(config.mac)).unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        key::TRIGGER_VARIANCE,
+        OrNone(config.min_variance)
+    )
+    .unwrap();
+    writeln!(serial, "{}: {}", key::TRIGGER_PRE, config.pre_records).unwrap();
+    writeln!(serial, "{}: {}s", key::TRIGGER_POST, config.post_s).unwrap();
+}
+
+// Shows settings that are not set as `none`
+struct OrNone<T>(Option<T>);
+
+impl<T: core::fmt::Display> core::fmt::Display for OrNone<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.0 {
+            Some(value) => value.fmt(f),
+            None => f.write_str("none"),
         }
-        Err(_) => (),
     }
+}
 
-    writeln!(serial, "\nUpdated WiFi Configuration:\n").unwrap();
-    CSI_COLLECTOR.lock(|config| {
-        writeln!(
-            serial,
-            "WiFi Operation Mode: {:?}",
-            config.borrow().as_ref().unwrap().op_mode
-        )
-        .unwrap();
+fn show_status<'a>(
+    _menu: &Menu<SerialInterfaceType, Context>,
+    _item: &Item<SerialInterfaceType, Context>,
+    _args: &[&str],
+    serial: &mut SerialInterfaceType,
+    _context: &mut Context,
+) {
+    let status = STATE.lock(|state| state.get());
+    writeln!(serial, "\nStatus:").unwrap();
+    writeln!(serial, "{}: {}", state::key::STATE, status).unwrap();
+    if let Some((started, deadline)) = RUN_WINDOW.lock(|run| run.get()) {
         writeln!(
             serial,
-            "Station WiFi Settings:\nSSID: '{}', Password: '{}'",
-            config.borrow().as_ref().unwrap().wifi_config.ssid,
-            config.borrow().as_ref().unwrap().wifi_config.password,
+            "{}: {}s",
+            state::key::ELAPSED,
+            started.elapsed().as_secs()
         )
         .unwrap();
         writeln!(
             serial,
-            "Access Point WiFi Settings:\nSSID: '{}', Password: '{}', SSID Hidden: {}, Max Connections: {}",
-            config.borrow().as_ref().unwrap().wifi_config.ap_ssid,
-            config.borrow().as_ref().unwrap().wifi_config.ap_password,
-            config.borrow().as_ref().unwrap().wifi_config.ssid_hidden,
-            config.borrow().as_ref().unwrap().wifi_config.max_connections,
+            "{}: {}s",
+            state::key::REMAINING,
+            deadline.saturating_duration_since(Instant::now()).as_secs()
         )
         .unwrap();
+    }
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::RECORDS,
+        RECORD_SEQ.lock(|seq| seq.get())
+    )
+    .unwrap();
+    match LAST_CHANNEL.lock(|channel| channel.get()) {
+        Some(channel) => writeln!(serial, "{}: {}", state::key::CHANNEL, channel).unwrap(),
+        None => writeln!(serial, "{}: unknown", state::key::CHANNEL).unwrap(),
+    }
+    // WiFi settings cannot change while active, so the configured mode is the one in use
+    let mode = CSI_COLLECTOR.lock(|config| wifi_mode(&config.borrow().as_ref().unwrap().op_mode));
+    let association = match (mode, status) {
+        (WifiModeArg::AccessPoint | WifiModeArg::Sniffer, _) => "n/a",
+        (_, State::Connecting) => "associating",
+        (_, State::Collecting | State::Paused) => "associated",
+        _ => "not associated",
+    };
+    writeln!(serial, "{}: {}", state::key::ASSOCIATION, association).unwrap();
+    match LAST_ERROR.lock(|error| error.get()) {
+        Some(error) => writeln!(serial, "{}: {}", state::key::LAST_ERROR, error).unwrap(),
+        None => writeln!(serial, "{}: none", state::key::LAST_ERROR).unwrap(),
+    }
+    let trigger = TRIGGER.lock(|trigger| trigger.borrow().as_ref().map(|t| t.fired()));
+    match trigger {
+        Some(Some(cause)) => writeln!(serial, "{}: fired {}", state::key::TRIGGER, cause).unwrap(),
+        Some(None) => writeln!(serial, "{}: armed", state::key::TRIGGER).unwrap(),
+        None => writeln!(serial, "{}: off", state::key::TRIGGER).unwrap(),
+    }
+    let label = LABEL.lock(|label| label.borrow().clone());
+    match label.is_empty() {
+        true => writeln!(serial, "{}: none", state::key::LABEL).unwrap(),
+        false => writeln!(serial, "{}: {}", state::key::LABEL, label).unwrap(),
+    }
+    let stats = FILTER.lock(|filter| *filter.borrow().as_ref().unwrap().stats());
+    writeln!(serial, "{}: {}", state::key::FILTER_ALLOWED, stats.allowed).unwrap();
+    writeln!(serial, "{}: {}", state::key::FILTER_DENIED, stats.denied).unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_NOT_ALLOWED,
+        stats.not_allowed
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_RSSI_REJECTED,
+        stats.rssi_rejected
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_FRAME_TYPE_REJECTED,
+        stats.frame_type_rejected
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_BSS_REJECTED,
+        stats.bss_rejected
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_NOT_REPLY,
+        stats.not_reply
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_PPDU_REJECTED,
+        stats.ppdu_rejected
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_DUPLICATES,
+        stats.duplicates
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_UNCHECKED,
+        stats.unchecked
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_RATE_SHED,
+        stats.rate_shed
+    )
+    .unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        state::key::FILTER_GLOBAL_SHED,
+        stats.global_shed
+    )
+    .unwrap();
+    FILTER.lock(|filter| {
+        for (mac, shed) in filter.borrow().as_ref().unwrap().shed_by_source() {
+            writeln!(serial, "{} {}: {}", state::key::FILTER_SHED_BY, mac, shed).unwrap();
+        }
     });
+    let bssid = FILTER.lock(|filter| filter.borrow().as_ref().unwrap().bssid());
+    writeln!(serial, "{}: {}", state::key::FILTER_BSSID, OrNone(bssid)).unwrap();
 }
 
-fn start_csi_collect<'a>(
+fn schedule_csi_collect<'a>(
     _menu: &Menu<SerialInterfaceType, Context>,
     item: &Item<SerialInterfaceType, Context>,
     args: &[&str],
     serial: &mut SerialInterfaceType,
     _context: &mut Context,
 ) {
-    let duration = argument_finder(item, args, "duration");
-    match duration {
-        Ok(str) => {
-            if str.is_some() {
-                match str.unwrap().parse::<u64>() {
-                    Ok(interval) => START_SIGNAL.signal(interval),
-                    Err(_) => writeln!(serial, "Invalid Duration").unwrap(),
+    let action = argument_finder(item, args, arg::ACTION);
+    match action {
+        Ok(Some(str)) => {
+            match str.parse::<ScheduleAction>() {
+                Ok(ScheduleAction::Show) => show_schedule(serial),
+                Ok(ScheduleAction::Clear) => {
+                    SCHEDULER.lock(|s| s.replace(None));
+                    SCHEDULE_SIGNAL.signal(());
+                    writeln!(serial, "Schedule Cleared").unwrap();
                 }
-            } else {
-                // Run for one week if no value provided
-                // 604800 seconds is equivalent to one week
-                START_SIGNAL.signal(Duration::from_secs(604800).as_secs());
-                println!("Running Forever");
+                Err(_) => writeln!(serial, "Invalid Action").unwrap(),
             }
+            return;
         }
-        Err(_) => (),
+        _ => (),
+    }
+
+    let mut seconds = [0u64; 3];
+    for (value, name) in seconds
+        .iter_mut()
+        .zip([arg::START_IN, arg::EVERY, arg::DURATION])
+    {
+        match argument_finder(item, args, name) {
+            Ok(Some(str)) => match str.parse::<u64>() {
+                Ok(s) => *value = s,
+                Err(_) => {
+                    writeln!(serial, "Invalid Value for --{}", name).unwrap();
+                    return;
+                }
+            },
+            // Only the start delay is optional
+            _ if name != arg::START_IN => {
+                writeln!(serial, "Missing --{}", name).unwrap();
+                return;
+            }
+            _ => (),
+        }
+    }
+    let count = match argument_finder(item, args, arg::COUNT) {
+        Ok(Some(str)) => match str.parse::<u32>() {
+            Ok(count) => Some(count),
+            Err(_) => {
+                writeln!(serial, "Invalid Count").unwrap();
+                return;
+            }
+        },
+        _ => None,
+    };
+
+    let schedule = Schedule {
+        start_in_s: seconds[0],
+        every_s: seconds[1],
+        duration_s: seconds[2],
+        count,
+    };
+    match schedule.validate() {
+        Ok(_) => {
+            let now_ms = Instant::now().as_millis();
+            SCHEDULER.lock(|s| s.replace(Some(Scheduler::new(schedule, now_ms))));
+            SCHEDULE_SIGNAL.signal(());
+            show_schedule(serial);
+        }
+        Err(e) => writeln!(serial, "Invalid Schedule: {}", e).unwrap(),
     }
 }
 
+fn show_schedule(serial: &mut SerialInterfaceType) {
+    let now_ms = Instant::now().as_millis();
+    SCHEDULER.lock(|s| match s.borrow().as_ref() {
+        Some(scheduler) => {
+            let schedule = scheduler.schedule();
+            writeln!(serial, "\nSchedule:").unwrap();
+            writeln!(serial, "Every: {}s", schedule.every_s).unwrap();
+            writeln!(serial, "Duration: {}s", schedule.duration_s).unwrap();
+            match schedule.count {
+                Some(count) => writeln!(serial, "Count: {}", count).unwrap(),
+                None => writeln!(serial, "Count: unlimited").unwrap(),
+            }
+            writeln!(serial, "Windows Started: {}", scheduler.started()).unwrap();
+            writeln!(serial, "Windows Missed: {}", scheduler.missed()).unwrap();
+            match scheduler.next_start_ms() {
+                Some(start_ms) => writeln!(
+                    serial,
+                    "Next Window In: {}s",
+                    start_ms.saturating_sub(now_ms) / 1000
+                )
+                .unwrap(),
+                None => writeln!(serial, "Next Window In: finished").unwrap(),
+            }
+        }
+        None => writeln!(serial, "\nNo Schedule Set").unwrap(),
+    });
+}
+
 fn show_config<'a>(
     _menu: &Menu<SerialInterfaceType, Context>,
     _item: &Item<SerialInterfaceType, Context>,
@@ -1216,160 +4171,175 @@ fn show_config<'a>(
     serial: &mut SerialInterfaceType,
     _context: &mut Context,
 ) {
-    writeln!(serial, "\nTraffic Settings:").unwrap();
+    // One `key: value` per line so host tools can read the dump back
+    writeln!(serial, "\nBuild Information:").unwrap();
+    writeln!(serial, "{}: {}", key::CHIP, CHIP).unwrap();
+    writeln!(serial, "{}: {}", key::FEATURES, env!("ENABLED_FEATURES")).unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        key::FIRMWARE_VERSION,
+        env!("CARGO_PKG_VERSION")
+    )
+    .unwrap();
+    writeln!(serial, "{}: {}", key::GIT_HASH, env!("GIT_HASH")).unwrap();
     CSI_COLLECTOR.lock(|config| {
+        let config = config.borrow();
+        let config = config.as_ref().unwrap();
+        writeln!(serial, "\nTraffic Settings:").unwrap();
         writeln!(
             serial,
-            "Traffic Enabled: {}",
-            config.borrow().as_ref().unwrap().traffic_enabled
+            "{}: {}",
+            key::TRAFFIC_ENABLED,
+            config.traffic_enabled
         )
         .unwrap();
         writeln!(
             serial,
-            "Traffic Type: {:?}",
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .traffic_config
-                .traffic_type
+            "{}: {}",
+            key::TRAFFIC_TYPE,
+            traffic_kind(&config.traffic_config.traffic_type)
         )
         .unwrap();
         writeln!(
             serial,
-            "Traffic Interval: {}ms",
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .traffic_config
-                .traffic_interval_ms
+            "{}: {}ms",
+            key::TRAFFIC_INTERVAL,
+            config.traffic_config.traffic_interval_ms
         )
         .unwrap();
         writeln!(serial, "\nNetwork Architecture Settings:").unwrap();
         writeln!(
             serial,
-            "Network Architecture: {:?}",
-            config.borrow().as_ref().unwrap().net_arch
+            "{}: {}",
+            key::NETWORK_ARCH,
+            net_arch(&config.net_arch)
         )
         .unwrap();
         writeln!(serial, "\nCSI Settings:").unwrap();
+        let csi = &config.csi_config;
         #[cfg(not(feature = "esp32c6"))]
-        writeln!(
-            serial,
-            "CSI Feature Flags: LLTF: {}, HTLTF: {}, STBC HTLTF: {}, LTF Merge: {}, Channel Filter: {}",
-            config.borrow().as_ref().unwrap().csi_config.lltf_enabled,
-            config.borrow().as_ref().unwrap().csi_config.htltf_enabled,
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .stbc_htltf2_enabled,
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .ltf_merge_enabled,
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .channel_filter_enabled,
-        )
-        .unwrap();
+        {
+            writeln!(serial, "{}: {}", key::LLTF_ENABLED, csi.lltf_enabled).unwrap();
+            writeln!(serial, "{}: {}", key::HTLTF_ENABLED, csi.htltf_enabled).unwrap();
+            writeln!(
+                serial,
+                "{}: {}",
+                key::STBC_HTLTF_ENABLED,
+                csi.stbc_htltf2_enabled
+            )
+            .unwrap();
+            writeln!(
+                serial,
+                "{}: {}",
+                key::LTF_MERGE_ENABLED,
+                csi.ltf_merge_enabled
+            )
+            .unwrap();
+            writeln!(
+                serial,
+                "{}: {}",
+                key::CHANNEL_FILTER_ENABLED,
+                csi.channel_filter_enabled
+            )
+            .unwrap();
+        }
         #[cfg(feature = "esp32c6")]
-        writeln!(
-            serial,
-            "CSI Feature Flags: CSI Enable: {}, CSI Legacy: {}, HT20: {}, HT40: {}, HE20 SU: {}, HE20 MU: {}, HE20 DCM: {}, HE20 Beamformed: {}, HE STBC: {}, Scale Value: {}",
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .enable,
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_legacy,
-            config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_ht20,
-                config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_ht40,
-                config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_su,
-                config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_mu,
-                config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_dcm,
-                config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_beamformed,
-                config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .acquire_csi_he_stbc,
-                config
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .csi_config
-                .val_scale_cfg,
-        )
-        .unwrap();
+        {
+            writeln!(serial, "{}: {}", key::ACQUIRE_CSI, csi.enable).unwrap();
+            writeln!(
+                serial,
+                "{}: {}",
+                key::ACQUIRE_CSI_LEGACY,
+                csi.acquire_csi_legacy
+            )
+            .unwrap();
+            writeln!(
+                serial,
+                "{}: {}",
+                key::ACQUIRE_CSI_HT20,
+                csi.acquire_csi_ht20
+            )
+            .unwrap();
+            writeln!(
+                serial,
+                "{}: {}",
+                key::ACQUIRE_CSI_HT40,
+                csi.acquire_csi_ht40
+            )
+            .unwrap();
+            writeln!(serial, "{}: {}", key::ACQUIRE_CSI_SU, csi.acquire_csi_su).unwrap();
+            writeln!(serial, "{}: {}", key::ACQUIRE_CSI_MU, csi.acquire_csi_mu).unwrap();
+            writeln!(serial, "{}: {}", key::ACQUIRE_CSI_DCM, csi.acquire_csi_dcm).unwrap();
+            writeln!(
+                serial,
+                "{}: {}",
+                key::ACQUIRE_CSI_BEAMFORMED,
+                csi.acquire_csi_beamformed
+            )
+            .unwrap();
+            writeln!(serial, "{}: {}", key::CSI_HE_STBC, csi.acquire_csi_he_stbc).unwrap();
+            writeln!(serial, "{}: {}", key::VAL_SCALE_CFG, csi.val_scale_cfg).unwrap();
+        }
         writeln!(serial, "\nWiFi Settings:").unwrap();
+        writeln!(serial, "{}: {}", key::WIFI_MODE, wifi_mode(&config.op_mode)).unwrap();
+        let wifi = &config.wifi_config;
+        writeln!(serial, "{}: '{}'", key::STA_SSID, wifi.ssid).unwrap();
+        writeln!(serial, "{}: '{}'", key::STA_PASSWORD, wifi.password).unwrap();
+        writeln!(serial, "{}: '{}'", key::AP_SSID, wifi.ap_ssid).unwrap();
+        writeln!(serial, "{}: '{}'", key::AP_PASSWORD, wifi.ap_password).unwrap();
+        writeln!(serial, "{}: {}", key::AP_SSID_HIDDEN, wifi.ssid_hidden).unwrap();
         writeln!(
             serial,
-            "WiFi Operation Mode: {:?}",
-            config.borrow().as_ref().unwrap().op_mode,
-        )
-        .unwrap();
-        writeln!(
-            serial,
-            "Station WiFi Settings:\nSSID: '{}', Password: '{}'",
-            config.borrow().as_ref().unwrap().wifi_config.ssid,
-            config.borrow().as_ref().unwrap().wifi_config.password,
-        )
-        .unwrap();
-        writeln!(
-            serial,
-            "Access Point WiFi Settings:\nSSID: '{}', Password: '{}', SSID Hidden: {}, Max Connections: {}",
-            config.borrow().as_ref().unwrap().wifi_config.ap_ssid,
-            config.borrow().as_ref().unwrap().wifi_config.ap_password,
-            config.borrow().as_ref().unwrap().wifi_config.ssid_hidden,
-            config.borrow().as_ref().unwrap().wifi_config.max_connections,
+            "{}: {}",
+            key::AP_MAX_CONNECTIONS,
+            wifi.max_connections
         )
         .unwrap();
     });
+    show_channel(serial);
+    writeln!(serial, "\nOutput Settings:").unwrap();
+    writeln!(
+        serial,
+        "{}: {}",
+        key::OUTPUT_FORMAT,
+        OUTPUT_FORMAT.lock(|format| format.get())
+    )
+    .unwrap();
+    writeln!(serial, "\nTrigger Settings:").unwrap();
+    show_trigger(serial);
+    writeln!(serial, "\nGPIO Settings:").unwrap();
+    show_gpio(serial);
+    writeln!(serial, "\nHeadless Settings:").unwrap();
+    show_headless(serial);
+    writeln!(serial, "\nFilter Settings:").unwrap();
+    show_filter(serial);
+}
+
+// Keywords used on the command line for the esp-csi-rs settings
+fn traffic_kind(traffic_type: &TrafficType) -> TrafficKind {
+    match traffic_type {
+        TrafficType::ICMPPing => TrafficKind::Icmp,
+        TrafficType::UDP => TrafficKind::Udp,
+    }
+}
+
+fn net_arch(arch: &NetworkArchitechture) -> NetArch {
+    match arch {
+        NetworkArchitechture::RouterStation => NetArch::RouterStation,
+        NetworkArchitechture::RouterAccessPointStation => NetArch::RouterAccessPointStation,
+        NetworkArchitechture::AccessPointStation => NetArch::AccessPointStation,
+        NetworkArchitechture::Sniffer => NetArch::Sniffer,
+    }
+}
+
+fn wifi_mode(mode: &WiFiMode) -> WifiModeArg {
+    match mode {
+        WiFiMode::AccessPoint => WifiModeArg::AccessPoint,
+        WiFiMode::Station => WifiModeArg::Station,
+        WiFiMode::Sniffer => WifiModeArg::Sniffer,
+        WiFiMode::AccessPointStation => WifiModeArg::AccessPointStation,
+    }
 }
 
 fn reset_config<'a>(
@@ -1379,9 +4349,20 @@ fn reset_config<'a>(
     serial: &mut SerialInterfaceType,
     _context: &mut Context,
 ) {
+    if rejected_while_collecting(serial, "Network and WiFi Settings") {
+        return;
+    }
     CSI_COLLECTOR.lock(|config| {
         let default_config = CSICollector::new_with_defaults();
         config.replace(Some(default_config));
     });
+    OUTPUT_FORMAT.lock(|format| format.set(OutputFormat::default()));
+    TRIGGER_CONFIG.lock(|config| config.set(None));
+    GPIO_CONFIG.lock(|config| config.set(None));
+    GPIO_SIGNAL.signal(());
+    FILTER.lock(|filter| filter.replace(Some(Filter::for_chip(CHIP))));
+    CHANNEL_CONFIG.lock(|config| config.set(None));
+    COUNTRY.lock(|country| country.set(Country::DEFAULT));
+    HOP.lock(|hop| hop.replace(Some(HopConfig::default())));
     writeln!(serial, "\nConfiguration Reset to Default Values\n").unwrap();
 }